@@ -0,0 +1,365 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::search::{GameState, Score, DRAW, LOSS};
+use crate::tt::mix;
+use crate::{Bitboard, BoardError, Game, GameStatus, Move, MoveError, ParseMoveError, PlayerId};
+
+/// Move of [`Achi`]: a piece placed on an empty square while some are
+/// still in hand, then a piece slid to an empty square next to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AchiMove {
+    Place(Move),
+    Slide { from: Move, to: Move },
+}
+
+// Printed as the square placed on, or `from-to`
+impl fmt::Display for AchiMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AchiMove::Place(mv) => write!(f, "{}", mv),
+            AchiMove::Slide { from, to } => write!(f, "{}-{}", from, to),
+        }
+    }
+}
+
+// Parses a square to place on, or `a1-b2` to slide
+impl FromStr for AchiMove {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('-') {
+            Some((from, to)) => Ok(AchiMove::Slide {
+                from: from.parse()?,
+                to: to.parse()?,
+            }),
+            None => s.parse().map(AchiMove::Place),
+        }
+    }
+}
+
+/// Achi: players take turns placing four pieces each on a 3×3 board,
+/// then slide one of their pieces to the empty square next to it along a
+/// row, column or diagonal, until one of them aligns three. Three men's
+/// morris plays the same with three pieces each.
+///
+/// The board is an empty [`Game`] giving the win lines, pieces sliding
+/// between squares next to each other on some line. A player left
+/// without a move loses, and as pieces may slide back and forth forever,
+/// the game is drawn after a number of plies.
+#[derive(Clone, Debug)]
+pub struct Achi {
+    board: Game,
+    pieces: u8,
+    max_plies: u32,
+    // Squares next to each square along a win line
+    neighbours: Arc<[Bitboard]>,
+    playable: Bitboard,
+    stones: [Bitboard; 2],
+    turn: PlayerId,
+    history: Vec<AchiMove>,
+}
+
+impl Default for Achi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Achi {
+    /// Plies after which games are drawn by default.
+    pub const MAX_PLIES: u32 = 50;
+
+    /// Create an empty 3×3 board with four pieces in hand for each player.
+    pub fn new() -> Self {
+        Self::with_board(Game::new(), 4).expect("4 pieces fit the 3×3 board")
+    }
+
+    /// Create an empty 3×3 board with three pieces in hand for each
+    /// player, as in three men's morris.
+    pub fn three_mens_morris() -> Self {
+        Self::with_board(Game::new(), 3).expect("3 pieces fit the 3×3 board")
+    }
+
+    /// Play with `pieces` pieces per player on the board of a game, its
+    /// blocked squares and win lines included, any stones on it left
+    /// out. Once placed, pieces must leave an empty square and be enough
+    /// to complete a line.
+    pub fn with_board(mut board: Game, pieces: u8) -> Result<Self, BoardError> {
+        board.set_players([Bitboard::EMPTY; 2]);
+        let playable = board
+            .moves()
+            .into_iter()
+            .fold(Bitboard::EMPTY, |squares, mv| {
+                squares | Bitboard::bit(board.square(mv))
+            });
+        if 2 * u32::from(pieces) >= playable.count_ones() || pieces < board.win_length() {
+            return Err(BoardError::InvalidPieces(pieces));
+        }
+        let neighbours = (0..board.squares())
+            .map(|square| {
+                let mv = board.square_move(square);
+                board
+                    .wins()
+                    .iter()
+                    .filter(|mask| mask.contains(square))
+                    .fold(Bitboard::EMPTY, |near, &mask| near | mask)
+                    .ones()
+                    .filter(|&other| {
+                        let other = board.square_move(other);
+                        other != mv
+                            && other.row.abs_diff(mv.row) <= 1
+                            && other.col.abs_diff(mv.col) <= 1
+                    })
+                    .fold(Bitboard::EMPTY, |near, other| near | Bitboard::bit(other))
+            })
+            .collect();
+        Ok(Achi {
+            board,
+            pieces,
+            max_plies: Self::MAX_PLIES,
+            neighbours,
+            playable,
+            stones: [Bitboard::EMPTY; 2],
+            turn: 0,
+            history: vec![],
+        })
+    }
+
+    /// Same game drawn after `max_plies` plies instead of
+    /// [`Achi::MAX_PLIES`].
+    pub fn with_max_plies(self, max_plies: u32) -> Self {
+        Achi { max_plies, ..self }
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Pieces each player places before sliding them.
+    pub fn pieces(&self) -> u8 {
+        self.pieces
+    }
+
+    /// Pieces of both players on the board.
+    pub fn players(&self) -> [Bitboard; 2] {
+        self.stones
+    }
+
+    /// Empty board the game is played on.
+    pub fn board(&self) -> &Game {
+        &self.board
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[AchiMove] {
+        &self.history
+    }
+
+    /// Whether the player to move still has pieces to place.
+    pub fn is_placing(&self) -> bool {
+        self.stones[usize::from(self.turn)].count_ones() < u32::from(self.pieces)
+    }
+
+    pub fn check_move(&self, mv: AchiMove) -> Result<(), MoveError> {
+        let empty = self.playable & !(self.stones[0] | self.stones[1]);
+        let to = match mv {
+            AchiMove::Place(to) | AchiMove::Slide { to, .. } => to,
+        };
+        self.board.check_move(to)?;
+        if !empty.contains(self.board.square(to)) {
+            return Err(MoveError::Occupied);
+        }
+        if let AchiMove::Slide { from, .. } = mv {
+            self.board.check_move(from).map_err(|e| match e {
+                MoveError::Blocked => MoveError::NoPiece,
+                _ => e,
+            })?;
+            let from = self.board.square(from);
+            if !self.stones[usize::from(self.turn)].contains(from) {
+                return Err(MoveError::NoPiece);
+            }
+            if !self.neighbours[from as usize].contains(self.board.square(to)) {
+                return Err(MoveError::NotAdjacent);
+            }
+        }
+        if self.is_over() {
+            Err(MoveError::GameOver)
+        } else if matches!(mv, AchiMove::Place(_)) != self.is_placing() {
+            Err(MoveError::WrongPhase)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, mv: AchiMove) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+
+    // Whether the player who moved last aligned its pieces
+    fn has_line(&self) -> bool {
+        let stones = self.stones[usize::from(1 - self.turn)];
+        self.board.wins().iter().any(|&mask| stones & mask == mask)
+    }
+
+    // Whether the player to move has pieces to slide but none can move
+    fn is_stuck(&self) -> bool {
+        if self.is_placing() {
+            return false;
+        }
+        let empty = self.playable & !(self.stones[0] | self.stones[1]);
+        self.stones[usize::from(self.turn)]
+            .ones()
+            .all(|square| (self.neighbours[square as usize] & empty).is_empty())
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.has_line() || self.is_stuck() || self.history.len() as u32 >= self.max_plies
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.has_line() || self.is_stuck() {
+            GameStatus::Won(1 - self.turn)
+        } else if self.history.len() as u32 >= self.max_plies {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
+        }
+    }
+}
+
+// One row per line, X for the first player, O for the second, # when
+// blocked, . when empty
+impl fmt::Display for Achi {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.board.height() {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            let cells: Vec<_> = (0..self.board.width())
+                .map(|col| {
+                    let square = self.board.square(Move::new(row, col));
+                    if self.stones[0].contains(square) {
+                        "X"
+                    } else if self.stones[1].contains(square) {
+                        "O"
+                    } else if self.playable.contains(square) {
+                        "."
+                    } else {
+                        "#"
+                    }
+                })
+                .collect();
+            write!(f, "{}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Achi {
+    type Move = AchiMove;
+
+    fn legal_moves(&self) -> Vec<AchiMove> {
+        if self.is_over() {
+            return vec![];
+        }
+        let empty = self.playable & !(self.stones[0] | self.stones[1]);
+        let square_move = |square| self.board.square_move(square);
+        if self.is_placing() {
+            return empty
+                .ones()
+                .map(|square| AchiMove::Place(square_move(square)))
+                .collect();
+        }
+        self.stones[usize::from(self.turn)]
+            .ones()
+            .flat_map(|from| {
+                (self.neighbours[from as usize] & empty)
+                    .ones()
+                    .map(move |to| AchiMove::Slide {
+                        from: square_move(from),
+                        to: square_move(to),
+                    })
+            })
+            .collect()
+    }
+
+    fn apply(&mut self, mv: AchiMove) {
+        let stones = &mut self.stones[usize::from(self.turn)];
+        match mv {
+            AchiMove::Place(to) => stones.insert(self.board.square(to)),
+            AchiMove::Slide { from, to } => {
+                *stones ^=
+                    Bitboard::bit(self.board.square(from)) | Bitboard::bit(self.board.square(to))
+            }
+        }
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    fn undo(&mut self, mv: AchiMove) {
+        self.turn = 1 - self.turn;
+        let stones = &mut self.stones[usize::from(self.turn)];
+        match mv {
+            AchiMove::Place(to) => *stones ^= Bitboard::bit(self.board.square(to)),
+            AchiMove::Slide { from, to } => {
+                *stones ^=
+                    Bitboard::bit(self.board.square(from)) | Bitboard::bit(self.board.square(to))
+            }
+        }
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Placing squares first, then slides, by the squares they involve
+    fn move_index(&self, mv: AchiMove) -> Option<usize> {
+        let squares = self.board.squares() as usize;
+        let square = |mv| self.board.square(mv) as usize;
+        Some(match mv {
+            AchiMove::Place(to) => square(to),
+            AchiMove::Slide { from, to } => squares + square(from) * squares + square(to),
+        })
+    }
+
+    // Pieces, side to move and plies played, the draw by length depending
+    // on them
+    fn hash_key(&self) -> Option<u64> {
+        let words = self.stones[0]
+            .words()
+            .into_iter()
+            .chain(self.stones[1].words());
+        let key = words.fold(mix(0, u64::from(self.turn)), mix);
+        Some(mix(key, self.history.len() as u64))
+    }
+
+    fn evaluate(&self) -> Score {
+        match self.status() {
+            GameStatus::Won(_) => LOSS,
+            GameStatus::Draw => DRAW,
+            // Lines still open to each side, by the square of their pieces
+            GameStatus::InProgress => {
+                let (player, opponent) = (
+                    self.stones[usize::from(self.turn)],
+                    self.stones[usize::from(1 - self.turn)],
+                );
+                let open = |ours: Bitboard, theirs: Bitboard| -> Score {
+                    self.board
+                        .wins()
+                        .iter()
+                        .filter(|&&mask| (theirs & mask).is_empty())
+                        .map(|&mask| (ours & mask).count_ones().pow(2) as Score)
+                        .sum()
+                };
+                open(player, opponent) - open(opponent, player)
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rand::rngs::StdRng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+
+use crate::mcts::{Mcts, MctsOptions, Oracle};
+use crate::reinforce::Policy;
+use crate::search::{GameState, RootNoise};
+use crate::{Bitboard, Game, GameStatus, Move};
+
+/// Learnable policy and value by position: the softmax of move logits of a
+/// [`Policy`], and the winning chances of the side to move, 0.5 for unseen
+/// positions. Tables suit boards small enough for training to visit most
+/// positions played.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PolicyValueTable {
+    pub policy: Policy,
+    values: HashMap<[Bitboard; 2], f64>,
+}
+
+impl PolicyValueTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn value(&self, game: &Game) -> f64 {
+        self.values.get(&game.players()).copied().unwrap_or(0.5)
+    }
+
+    /// Move the prediction of a position toward a target: the value toward
+    /// the result, and the policy toward the move probabilities, by
+    /// following the gradient of the cross-entropy of the logits.
+    pub fn train(&mut self, sample: &Sample, learning_rate: f64) {
+        let value = self.value(&sample.position);
+        self.values.insert(
+            sample.position.players(),
+            value + learning_rate * (sample.result - value),
+        );
+        for (mv, p) in self.policy.probabilities(&sample.position) {
+            let target = sample
+                .policy
+                .iter()
+                .find(|&&(target, _)| target == mv)
+                .map_or(0.0, |&(_, target)| target);
+            let logits = &mut self.policy.preferences;
+            let logit = logits.value(&sample.position, mv);
+            logits.set_value(&sample.position, mv, logit + learning_rate * (target - p));
+        }
+    }
+
+    /// Write the table to a directory, the policy to `policy.txt` in the
+    /// format of [`QTable`](crate::qlearning::QTable) and the values to
+    /// `values.txt`, one `x o value` line per position.
+    pub fn save(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        self.policy.save(dir.join("policy.txt"))?;
+        let mut file = BufWriter::new(File::create(dir.join("values.txt"))?);
+        let mut values: Vec<_> = self.values.iter().collect();
+        values.sort_by_key(|(&key, _)| key);
+        for ([x, o], value) in values {
+            writeln!(file, "{:x} {:x} {}", x, o, value)?;
+        }
+        file.flush()
+    }
+
+    /// Read a table saved by [`PolicyValueTable::save`].
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let policy = Policy::load(dir.join("policy.txt"))?;
+        let mut values = HashMap::new();
+        let file = File::open(dir.join("values.txt"))?;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected `x o value`", i + 1),
+                )
+            };
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let [x, o, value] = fields[..] else {
+                return Err(invalid());
+            };
+            let x = Bitboard::from_hex(x).ok_or_else(invalid)?;
+            let o = Bitboard::from_hex(o).ok_or_else(invalid)?;
+            values.insert([x, o], value.parse().map_err(|_| invalid())?);
+        }
+        Ok(PolicyValueTable { policy, values })
+    }
+}
+
+impl Oracle<Game> for PolicyValueTable {
+    fn predict(&self, game: &Game) -> (Vec<f64>, f64) {
+        let priors = self
+            .policy
+            .probabilities(game)
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect();
+        (priors, self.value(game))
+    }
+}
+
+/// Position of a self-play game with the targets of its training: the
+/// visit shares of the moves searched from it and the result of its side
+/// to move, 1 for a win, 0.5 for a draw and 0 for a loss.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub position: Game,
+    pub policy: Vec<(Move, f64)>,
+    pub result: f64,
+}
+
+/// Latest samples of self-play, the oldest ones dropped past a capacity.
+#[derive(Clone, Debug)]
+pub struct ReplayBuffer {
+    pub capacity: usize,
+    samples: VecDeque<Sample>,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize) -> Self {
+        ReplayBuffer {
+            capacity,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        if self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Up to `size` distinct samples drawn at random.
+    pub fn batch(&self, size: usize, rng: &mut StdRng) -> Vec<&Sample> {
+        self.samples.iter().choose_multiple(rng, size)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Tunable behaviour of an [`AlphaZero`] training.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlphaZeroOptions {
+    /// Simulations of the guided search per move.
+    pub simulations: u32,
+    /// Weight of the priors in the search, see [`MctsOptions::exploration`].
+    pub exploration: f64,
+    /// Self-play games per round.
+    pub games: u32,
+    /// Moves of each game drawn in proportion to their visits, the most
+    /// visited move being played after them.
+    pub sampled_moves: usize,
+    /// Noise mixed into the priors at the root in self-play.
+    pub noise: RootNoise,
+    /// Capacity of the replay buffer.
+    pub buffer: usize,
+    /// Batches trained on per round.
+    pub batches: u32,
+    pub batch_size: usize,
+    pub learning_rate: f64,
+    /// Games per round between the trained function and the best one.
+    pub evaluation_games: u32,
+    /// Share of the points of the evaluation games the trained function
+    /// must reach to become the best one.
+    pub threshold: f64,
+    /// Directory the best function is saved to whenever it changes.
+    pub checkpoint: Option<PathBuf>,
+    pub seed: Option<u64>,
+}
+
+impl Default for AlphaZeroOptions {
+    fn default() -> Self {
+        AlphaZeroOptions {
+            simulations: 100,
+            exploration: 1.5,
+            games: 50,
+            sampled_moves: 4,
+            noise: RootNoise::default(),
+            buffer: 20_000,
+            batches: 50,
+            batch_size: 64,
+            learning_rate: 0.2,
+            evaluation_games: 20,
+            threshold: 0.55,
+            checkpoint: None,
+            seed: None,
+        }
+    }
+}
+
+/// Summary of a round of [`AlphaZero::round`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoundReport {
+    /// Samples added to the replay buffer.
+    pub samples: usize,
+    /// Share of the points of the trained function against the best one.
+    pub score: f64,
+    /// Whether the trained function became the best one.
+    pub promoted: bool,
+}
+
+/// AlphaZero-style training of a [`PolicyValueTable`]: each round, the best
+/// function so far plays games against itself with guided searches, their
+/// positions filling a replay buffer that a candidate function is trained
+/// on. The candidate then plays the best function and replaces it when it
+/// scores enough.
+#[derive(Clone, Debug)]
+pub struct AlphaZero {
+    pub options: AlphaZeroOptions,
+    pub best: PolicyValueTable,
+    pub candidate: PolicyValueTable,
+    pub buffer: ReplayBuffer,
+    start: Game,
+    rng: StdRng,
+}
+
+impl AlphaZero {
+    /// Training from scratch on games played from `start`.
+    pub fn new(start: &Game, options: AlphaZeroOptions) -> Self {
+        AlphaZero {
+            buffer: ReplayBuffer::new(options.buffer),
+            rng: options
+                .seed
+                .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+            options,
+            best: PolicyValueTable::new(),
+            candidate: PolicyValueTable::new(),
+            start: start.clone(),
+        }
+    }
+
+    /// Run `rounds` rounds, returning their reports.
+    pub fn train(&mut self, rounds: u32) -> io::Result<Vec<RoundReport>> {
+        (0..rounds).map(|_| self.round()).collect()
+    }
+
+    /// Run one round of self-play, training and evaluation, saving the
+    /// best function to the checkpoint when it changes.
+    pub fn round(&mut self) -> io::Result<RoundReport> {
+        let mut samples = 0;
+        for _ in 0..self.options.games {
+            for sample in self.self_play() {
+                self.buffer.push(sample);
+                samples += 1;
+            }
+        }
+        for _ in 0..self.options.batches {
+            let batch = self.buffer.batch(self.options.batch_size, &mut self.rng);
+            for sample in batch {
+                self.candidate.train(sample, self.options.learning_rate);
+            }
+        }
+        let score = self.evaluate();
+        let promoted = score >= self.options.threshold;
+        if promoted {
+            self.best = self.candidate.clone();
+            if let Some(dir) = &self.options.checkpoint {
+                self.best.save(dir)?;
+            }
+        }
+        Ok(RoundReport {
+            samples,
+            score,
+            promoted,
+        })
+    }
+
+    fn search(&mut self) -> Mcts {
+        Mcts::with_options(
+            self.options.simulations,
+            MctsOptions {
+                exploration: self.options.exploration,
+                seed: Some(self.rng.gen()),
+                ..MctsOptions::default()
+            },
+        )
+    }
+
+    // Move played after the visits of a search, drawn in proportion to
+    // them for the first moves of a game
+    fn pick(&mut self, game: &Game, visits: &[(Move, u32)]) -> Move {
+        let sampled =
+            game.history().len() < self.start.history().len() + self.options.sampled_moves;
+        if sampled {
+            visits
+                .choose_weighted(&mut self.rng, |&(_, visits)| visits)
+                .expect("Moves were visited")
+                .0
+        } else {
+            visits
+                .iter()
+                .max_by_key(|&&(_, visits)| visits)
+                .expect("Moves were visited")
+                .0
+        }
+    }
+
+    // Samples of a game of the best function against itself
+    fn self_play(&mut self) -> Vec<Sample> {
+        let mut game = self.start.clone();
+        let mut positions = vec![];
+        while !game.is_over() {
+            let mut search = self.search();
+            let noise = Some(self.options.noise);
+            let visits = search.guided_visits(&mut game, &self.best, noise);
+            let total: u32 = visits.iter().map(|&(_, visits)| visits).sum();
+            let policy = visits
+                .iter()
+                .map(|&(mv, count)| (mv, f64::from(count) / f64::from(total.max(1))))
+                .collect();
+            positions.push((game.clone(), policy));
+            let mv = self.pick(&game, &visits);
+            game.apply(mv);
+        }
+        let status = game.status();
+        positions
+            .into_iter()
+            .map(|(position, policy)| {
+                let result = match status {
+                    GameStatus::Won(player) if player == position.turn() => 1.0,
+                    GameStatus::Won(_) => 0.0,
+                    _ => 0.5,
+                };
+                Sample {
+                    position,
+                    policy,
+                    result,
+                }
+            })
+            .collect()
+    }
+
+    // Share of the points of the candidate against the best function, each
+    // moving first in half of the games
+    fn evaluate(&mut self) -> f64 {
+        let games = self.options.evaluation_games.max(1);
+        let mut points = 0.0;
+        for i in 0..games {
+            let candidate_side = (i % 2) as u8;
+            let mut game = self.start.clone();
+            while !game.is_over() {
+                let mut search = self.search();
+                let oracle = if game.turn() == candidate_side {
+                    &self.candidate
+                } else {
+                    &self.best
+                };
+                let visits = search.guided_visits(&mut game, oracle, None);
+                let mv = self.pick(&game, &visits);
+                game.apply(mv);
+            }
+            points += match game.status() {
+                GameStatus::Won(player) if player == candidate_side => 1.0,
+                GameStatus::Won(_) => 0.0,
+                _ => 0.5,
+            };
+        }
+        points / f64::from(games)
+    }
+}
@@ -0,0 +1,85 @@
+use std::env;
+
+use rand::thread_rng;
+
+use tictactoe_rust::eval::Weights;
+use tictactoe_rust::player::NegamaxPlayer;
+use tictactoe_rust::tune::{self, Corpus, Genetic, TdLambda};
+use tictactoe_rust::Game;
+
+// Tune evaluation weights on self-play games and print them as TOML:
+// `tune [size] [win length] [games] [--solved | --genetic | --td]
+// [--random-start=pairs]`, labeling positions with their exact values
+// rather than game results with `--solved`, evolving the weights over
+// `games` generations of tournaments with `--genetic`, or learning them by
+// TD(λ) with `--td`. Self-play games start from random positions with
+// `pairs` moves of each player with `--random-start`.
+fn main() {
+    let solved = env::args().any(|arg| arg == "--solved");
+    let genetic = env::args().any(|arg| arg == "--genetic");
+    let td = env::args().any(|arg| arg == "--td");
+    let random_start: Option<u8> = env::args().find_map(|arg| {
+        arg.strip_prefix("--random-start=")
+            .map(|pairs| pairs.parse().expect("Invalid number of pairs"))
+    });
+    let numbers: Vec<u32> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .map(|arg| arg.parse().expect("Invalid number"))
+        .collect();
+    let size = numbers.first().map_or(3, |&n| n as u8);
+    let win_length = numbers.get(1).map_or(size, |&n| n as u8);
+    let games = numbers
+        .get(2)
+        .map_or(if genetic { 20 } else { 200 }, |&n| n as usize);
+    let start = Game::with_win_length(size, win_length).expect("Invalid board");
+    let initial = Weights::default();
+    if genetic {
+        let genetic = Genetic {
+            generations: games as u32,
+            ..Genetic::default()
+        };
+        let weights = genetic.evolve(&start, initial);
+        print!("{}", toml::to_string(&weights).expect("Weights serialize"));
+        return;
+    }
+    if td {
+        let mut td = TdLambda::new(initial);
+        td.train(&start, games as u32);
+        print!(
+            "{}",
+            toml::to_string(&td.weights()).expect("Weights serialize")
+        );
+        return;
+    }
+
+    // Shallow players drawing their moves, for varied games
+    let player = || {
+        let mut player = NegamaxPlayer::new(1);
+        player.temperature = Some(40.0);
+        player
+    };
+    let (mut first, mut second) = (player(), player());
+    let mut corpus = match random_start {
+        Some(pairs) => {
+            let mut rng = thread_rng();
+            let starts: Vec<_> = (0..games)
+                .map(|_| start.random_start(pairs, &mut rng))
+                .collect();
+            Corpus::self_play_from(&starts, [&mut first, &mut second])
+        }
+        None => Corpus::self_play(&start, games, [&mut first, &mut second]),
+    };
+    if solved {
+        corpus.label_solved();
+    }
+    let weights = tune::texel(&corpus, initial);
+    let scale = tune::fit_scale(&corpus, initial);
+    eprintln!(
+        "{} positions, error {:.5} -> {:.5}",
+        corpus.len(),
+        tune::prediction_error(&corpus, initial, scale),
+        tune::prediction_error(&corpus, weights, scale),
+    );
+    print!("{}", toml::to_string(&weights).expect("Weights serialize"));
+}
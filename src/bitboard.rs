@@ -0,0 +1,226 @@
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Shl, Shr};
+
+// 64 bit words of a bitboard, least significant first
+const WORDS: usize = 4;
+
+/// Set of squares, bit `square` set for each square in the set. Wide
+/// enough for a 15×15 board, stored as four 64 bit words.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard([u64; WORDS]);
+
+impl Bitboard {
+    /// Number of bits of a bitboard.
+    pub const BITS: u32 = u64::BITS * WORDS as u32;
+
+    /// Set of no square.
+    pub const EMPTY: Bitboard = Bitboard([0; WORDS]);
+
+    /// Set of the single square at a bit index.
+    pub fn bit(index: u32) -> Self {
+        let mut words = [0; WORDS];
+        words[(index / u64::BITS) as usize] = 1 << (index % u64::BITS);
+        Bitboard(words)
+    }
+
+    /// Set of the first `count` squares.
+    pub fn below(count: u32) -> Self {
+        Bitboard(std::array::from_fn(|i| {
+            let bits = count.saturating_sub(i as u32 * u64::BITS);
+            if bits >= u64::BITS {
+                u64::MAX
+            } else {
+                (1 << bits) - 1
+            }
+        }))
+    }
+
+    pub fn is_empty(self) -> bool {
+        self == Self::EMPTY
+    }
+
+    /// Whether the square at a bit index is in the set.
+    pub fn contains(self, index: u32) -> bool {
+        self.0[(index / u64::BITS) as usize] & 1 << (index % u64::BITS) != 0
+    }
+
+    /// Add the square at a bit index to the set.
+    pub fn insert(&mut self, index: u32) {
+        self.0[(index / u64::BITS) as usize] |= 1 << (index % u64::BITS);
+    }
+
+    pub fn count_ones(self) -> u32 {
+        self.0.iter().map(|word| word.count_ones()).sum()
+    }
+
+    /// Index of the first square of the set, [`Bitboard::BITS`] when empty.
+    pub fn trailing_zeros(self) -> u32 {
+        self.0
+            .iter()
+            .position(|&word| word != 0)
+            .map_or(Self::BITS, |i| {
+                i as u32 * u64::BITS + self.0[i].trailing_zeros()
+            })
+    }
+
+    /// Indices of the squares of the set in increasing order.
+    pub fn ones(self) -> impl Iterator<Item = u32> {
+        self.0.into_iter().enumerate().flat_map(|(i, mut word)| {
+            std::iter::from_fn(move || {
+                (word != 0).then(|| {
+                    let bit = word.trailing_zeros();
+                    word &= word - 1;
+                    i as u32 * u64::BITS + bit
+                })
+            })
+        })
+    }
+
+    /// Words of the set, least significant first.
+    pub fn words(self) -> [u64; WORDS] {
+        self.0
+    }
+
+    /// Parse a set written in hexadecimal, as by `{:x}`.
+    pub fn from_hex(src: &str) -> Option<Self> {
+        let digits = src.trim_start_matches('0');
+        if src.is_empty() || digits.len() > Self::BITS as usize / 4 {
+            return None;
+        }
+        let mut words = [0; WORDS];
+        let mut end = digits.len();
+        for word in &mut words {
+            if end == 0 {
+                break;
+            }
+            let start = end.saturating_sub(16);
+            *word = u64::from_str_radix(digits.get(start..end)?, 16).ok()?;
+            end = start;
+        }
+        Some(Bitboard(words))
+    }
+}
+
+// Sets sort like the integers of their bits, most significant word first
+impl Ord for Bitboard {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (0..WORDS)
+            .rev()
+            .map(|i| self.0[i].cmp(&other.0[i]))
+            .find(|order| order.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Bitboard {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Printed as the hexadecimal integer of its bits
+impl fmt::LowerHex for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let top = self.0.iter().rposition(|&word| word != 0).unwrap_or(0);
+        write!(f, "{:x}", self.0[top])?;
+        for word in self.0[..top].iter().rev() {
+            write!(f, "{:016x}", word)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Bitboard({:#x})", self)
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Bitboard(std::array::from_fn(|i| self.0[i] & other.0[i]))
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Bitboard(std::array::from_fn(|i| self.0[i] | other.0[i]))
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Self;
+
+    fn bitxor(self, other: Self) -> Self {
+        Bitboard(std::array::from_fn(|i| self.0[i] ^ other.0[i]))
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Bitboard(self.0.map(|word| !word))
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, other: Self) {
+        *self = *self & other;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, other: Self) {
+        *self = *self | other;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, other: Self) {
+        *self = *self ^ other;
+    }
+}
+
+// Moves every square `shift` indices up, dropping those past the last bit
+impl Shl<u32> for Bitboard {
+    type Output = Self;
+
+    fn shl(self, shift: u32) -> Self {
+        let (words, bits) = ((shift / u64::BITS) as usize, shift % u64::BITS);
+        Bitboard(std::array::from_fn(|i| {
+            let Some(from) = i.checked_sub(words) else {
+                return 0;
+            };
+            let carry = match from.checked_sub(1) {
+                Some(below) if bits > 0 => self.0[below] >> (u64::BITS - bits),
+                _ => 0,
+            };
+            self.0[from] << bits | carry
+        }))
+    }
+}
+
+// Moves every square `shift` indices down, dropping those below the first
+impl Shr<u32> for Bitboard {
+    type Output = Self;
+
+    fn shr(self, shift: u32) -> Self {
+        let (words, bits) = ((shift / u64::BITS) as usize, shift % u64::BITS);
+        Bitboard(std::array::from_fn(|i| {
+            let from = i + words;
+            if from >= WORDS {
+                return 0;
+            }
+            let carry = match self.0.get(from + 1) {
+                Some(above) if bits > 0 => above << (u64::BITS - bits),
+                _ => 0,
+            };
+            self.0[from] >> bits | carry
+        }))
+    }
+}
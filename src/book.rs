@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::search::{GameState, Search};
+use crate::{Game, Move, ParseMoveError};
+
+/// Opening book, recommended replies with their weights by sequence of
+/// moves played from the empty board.
+///
+/// Book files have one line per sequence, the moves of the sequence and
+/// the weighted replies separated by `->`, such as `b2 a1 -> c3=3
+/// c1=1`. Lines starting with `#` are comments.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: HashMap<Vec<Move>, Vec<(Move, u32)>>,
+}
+
+impl Book {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Book of every optimal move up to `plies` moves deep, following only
+    /// optimal moves from `game`, each one weighted 1. Scores come from
+    /// exhaustive searches, long past a few plies on large boards.
+    pub fn generate(game: &Game, plies: u8) -> Self {
+        let mut book = Book::new();
+        let mut search = Search::new();
+        book.expand(&mut game.clone(), &mut search, plies);
+        book
+    }
+
+    fn expand(&mut self, game: &mut Game, search: &mut Search<Game>, plies: u8) {
+        if plies == 0 || game.is_over() {
+            return;
+        }
+        let n = game.legal_moves().len();
+        let lines = search.multipv(game, u8::MAX, n);
+        let Some(best) = lines.first().map(|line| line.score) else {
+            return;
+        };
+        let replies: Vec<_> = lines
+            .iter()
+            .filter(|line| line.score == best)
+            .filter_map(|line| line.best())
+            .collect();
+        self.entries.insert(
+            game.history().to_vec(),
+            replies.iter().map(|&mv| (mv, 1)).collect(),
+        );
+        for mv in replies {
+            game.apply(mv);
+            self.expand(game, search, plies - 1);
+            game.undo(mv);
+        }
+    }
+
+    /// Add a reply to a sequence, adding to its weight when already there.
+    pub fn insert(&mut self, moves: &[Move], reply: Move, weight: u32) {
+        let replies = self.entries.entry(moves.to_vec()).or_default();
+        match replies.iter_mut().find(|(mv, _)| *mv == reply) {
+            Some((_, total)) => *total += weight,
+            None => replies.push((reply, weight)),
+        }
+    }
+
+    /// Weighted replies to a sequence, empty when out of book.
+    pub fn replies(&self, moves: &[Move]) -> &[(Move, u32)] {
+        self.entries.get(moves).map_or(&[], Vec::as_slice)
+    }
+
+    /// Reply drawn according to the weights, `None` when out of book.
+    pub fn probe(&self, moves: &[Move], rng: &mut impl Rng) -> Option<Move> {
+        self.replies(moves)
+            .choose_weighted(rng, |&(_, weight)| weight)
+            .ok()
+            .map(|&(mv, _)| mv)
+    }
+
+    /// Number of sequences in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the book to a file, shortest sequences first.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let mut sequences: Vec<_> = self.entries.iter().collect();
+        sequences.sort_by_key(|(moves, _)| (moves.len(), moves.to_vec()));
+        for (moves, replies) in sequences {
+            let moves: Vec<_> = moves.iter().map(Move::to_string).collect();
+            let replies: Vec<_> = replies
+                .iter()
+                .map(|(mv, weight)| format!("{}={}", mv, weight))
+                .collect();
+            writeln!(file, "{} -> {}", moves.join(" "), replies.join(" "))?;
+        }
+        file.flush()
+    }
+
+    /// Read a book file, see [`Book`] for the format.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut book = Book::new();
+        for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: String| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", i + 1, reason),
+                )
+            };
+            let (moves, replies) = line
+                .split_once("->")
+                .ok_or_else(|| invalid("missing `->`".to_string()))?;
+            let moves = moves
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<Move>, _>>()
+                .map_err(|e| invalid(e.to_string()))?;
+            for reply in replies.split_whitespace() {
+                let (mv, weight) = reply.split_once('=').unwrap_or((reply, "1"));
+                let mv = mv
+                    .parse()
+                    .map_err(|e: ParseMoveError| invalid(e.to_string()))?;
+                let weight = weight
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid weight {:?}", weight)))?;
+                book.insert(&moves, mv, weight);
+            }
+        }
+        Ok(book)
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use crate::player::Player;
+use crate::{Game, GameStatus, PlayerId};
+
+/// Time of each player for the whole game, and time added after each of
+/// their moves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub total: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    pub fn new(total: Duration, increment: Duration) -> Self {
+        TimeControl { total, increment }
+    }
+}
+
+/// Clocks of both players, running for one of them at a time.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    // Player whose clock runs and since when
+    running: Option<(PlayerId, Instant)>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Clock {
+            control,
+            remaining: [control.total; 2],
+            running: None,
+        }
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    /// Time left to a player, its running move included.
+    pub fn remaining(&self, player: PlayerId) -> Duration {
+        let spent = match self.running {
+            Some((running, since)) if running == player => since.elapsed(),
+            _ => Duration::ZERO,
+        };
+        self.remaining[player as usize].saturating_sub(spent)
+    }
+
+    /// Start the clock of a player about to think, stopping the other one.
+    pub fn start(&mut self, player: PlayerId) {
+        self.stop();
+        self.running = Some((player, Instant::now()));
+    }
+
+    /// Stop the running clock, adding the increment when its player still
+    /// has time. Returns whether it has.
+    pub fn stop(&mut self) -> bool {
+        let Some((player, since)) = self.running.take() else {
+            return true;
+        };
+        let remaining = &mut self.remaining[player as usize];
+        match remaining.checked_sub(since.elapsed()) {
+            Some(left) => {
+                *remaining = left + self.control.increment;
+                true
+            }
+            None => {
+                *remaining = Duration::ZERO;
+                false
+            }
+        }
+    }
+
+    /// Whether a player ran out of time.
+    pub fn flagged(&self, player: PlayerId) -> bool {
+        self.remaining(player).is_zero()
+    }
+}
+
+/// Time to think about a move with `remaining` time left, spreading it
+/// over the `moves` the player may still have to make and spending most
+/// of the increment, while keeping a margin not to lose on time.
+pub fn allocate(remaining: Duration, increment: Duration, moves: u32) -> Duration {
+    let share = remaining / moves.max(1) + increment * 3 / 4;
+    share.min(remaining * 9 / 10)
+}
+
+/// Play a game to the end between two players under a time control, a
+/// player running out of time losing it. Players get their remaining time
+/// through [`Player::choose_move_timed`].
+pub fn play_timed(
+    game: &mut Game,
+    players: [&mut dyn Player; 2],
+    control: TimeControl,
+) -> GameStatus {
+    let mut clock = Clock::new(control);
+    while !game.is_over() {
+        let turn = game.turn();
+        clock.start(turn);
+        let mv = players[turn as usize].choose_move_timed(
+            game,
+            clock.remaining(turn),
+            control.increment,
+        );
+        if !clock.stop() {
+            return GameStatus::Won(1 - turn);
+        }
+        game.make_move(mv).expect("Illegal move chosen");
+    }
+    game.status()
+}
@@ -0,0 +1,419 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::search::{GameState, Score, DRAW, LOSS};
+use crate::tt::mix;
+use crate::{Bitboard, BoardError, Game, GameStatus, Move, MoveError, PlayerId};
+
+/// Most stones placed in a turn.
+pub const MAX_STONES: u8 = 4;
+
+/// Rules of a Connect(m, n, k, p, q) game: an `m`×`n` board won by `k` in
+/// a row, players placing `p` stones a turn, but `q` on the very first
+/// one. Connect6 is Connect(19, 19, 6, 2, 1), on boards up to 15×15 here.
+///
+/// Rules parse from `m,n,k,p,q`, and with the `serde` feature load from
+/// config files as their named fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct ConnectRules {
+    /// Columns of the board.
+    pub m: u8,
+    /// Rows of the board.
+    pub n: u8,
+    /// Stones in a row winning the game.
+    pub k: u8,
+    /// Stones placed each turn.
+    pub p: u8,
+    /// Stones placed on the first turn.
+    pub q: u8,
+}
+
+impl ConnectRules {
+    /// Connect6 on the largest board that fits, 15×15.
+    pub fn connect6() -> Self {
+        ConnectRules {
+            m: 15,
+            n: 15,
+            k: 6,
+            p: 2,
+            q: 1,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ConnectRules {
+    /// Read rules from a TOML file, such as `m = 15` on a line for each
+    /// parameter.
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+// Printed as `m,n,k,p,q`
+impl fmt::Display for ConnectRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ConnectRules { m, n, k, p, q } = self;
+        write!(f, "{},{},{},{},{}", m, n, k, p, q)
+    }
+}
+
+/// Error returned when a string is not `m,n,k,p,q` rules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseRulesError(String);
+
+impl fmt::Display for ParseRulesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected `m,n,k,p,q`, got {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRulesError {}
+
+impl FromStr for ConnectRules {
+    type Err = ParseRulesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseRulesError(s.to_string());
+        let numbers = s
+            .split(',')
+            .map(|number| number.trim().parse().map_err(|_| err()))
+            .collect::<Result<Vec<u8>, _>>()?;
+        match numbers[..] {
+            [m, n, k, p, q] => Ok(ConnectRules { m, n, k, p, q }),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// Connect(m, n, k, p, q) game, k-in-a-row with several stones a turn.
+/// Moves are the stones of a whole turn as a [`Bitboard`] of squares,
+/// fewer than `p` only when the board has no more empty squares, so
+/// searches alternate between players as usual.
+///
+/// The board is an empty [`Game`] of the same size giving the win masks.
+/// Moves combine every empty square, or with [`Connect::with_reach`] only
+/// those near stones, which large boards need with several stones a turn.
+#[derive(Clone, Debug)]
+pub struct Connect {
+    board: Game,
+    rules: ConnectRules,
+    reach: Option<u8>,
+    // Indices of the win masks through each square
+    lines: Arc<[Vec<usize>]>,
+    stones: [Bitboard; 2],
+    turn: PlayerId,
+    history: Vec<Bitboard>,
+}
+
+impl Connect {
+    /// Create an empty board for the rules with the first player to move.
+    pub fn new(rules: ConnectRules) -> Result<Self, BoardError> {
+        let ConnectRules { m, n, k, p, q } = rules;
+        if !(1..=MAX_STONES).contains(&p) {
+            return Err(BoardError::InvalidPieces(p));
+        }
+        if !(1..=MAX_STONES).contains(&q) {
+            return Err(BoardError::InvalidPieces(q));
+        }
+        let board = Game::with_dimensions(m, n, k)?;
+        let lines = (0..board.squares())
+            .map(|square| {
+                (0..board.wins().len())
+                    .filter(|&i| board.wins()[i].contains(square))
+                    .collect()
+            })
+            .collect();
+        Ok(Connect {
+            board,
+            rules,
+            reach: None,
+            lines,
+            stones: [Bitboard::EMPTY; 2],
+            turn: 0,
+            history: vec![],
+        })
+    }
+
+    /// Same game where searches only consider empty squares within
+    /// `reach` steps of a stone along rows, columns and diagonals, or of
+    /// the center on an empty board. Any empty square may still be played.
+    pub fn with_reach(self, reach: u8) -> Self {
+        Connect {
+            reach: Some(reach),
+            ..self
+        }
+    }
+
+    pub fn rules(&self) -> ConnectRules {
+        self.rules
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Stones of both players on the board.
+    pub fn players(&self) -> [Bitboard; 2] {
+        self.stones
+    }
+
+    /// Empty board the game is played on.
+    pub fn board(&self) -> &Game {
+        &self.board
+    }
+
+    /// Stones placed each turn so far, oldest first.
+    pub fn history(&self) -> &[Bitboard] {
+        &self.history
+    }
+
+    /// Squares of the stones of a move.
+    pub fn squares(&self, stones: Bitboard) -> Vec<Move> {
+        stones
+            .ones()
+            .map(|square| self.board.square_move(square))
+            .collect()
+    }
+
+    // Empty squares
+    fn empty(&self) -> Bitboard {
+        Bitboard::below(self.board.squares()) & !(self.stones[0] | self.stones[1])
+    }
+
+    /// Stones the player to move places this turn.
+    pub fn stones_to_place(&self) -> u8 {
+        let stones = if self.history.is_empty() {
+            self.rules.q
+        } else {
+            self.rules.p
+        };
+        stones.min(self.empty().count_ones() as u8)
+    }
+
+    /// Check that stones can be placed together this turn.
+    pub fn check_move(&self, squares: &[Move]) -> Result<(), MoveError> {
+        let mut stones = Bitboard::EMPTY;
+        for &mv in squares {
+            self.board.check_move(mv)?;
+            let square = self.board.square(mv);
+            if !self.empty().contains(square) || stones.contains(square) {
+                return Err(MoveError::Occupied);
+            }
+            stones.insert(square);
+        }
+        if self.is_over() {
+            Err(MoveError::GameOver)
+        } else if squares.len() != usize::from(self.stones_to_place()) {
+            Err(MoveError::StoneCount)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Place the stones of a turn.
+    pub fn make_move(&mut self, squares: &[Move]) -> Result<(), MoveError> {
+        self.check_move(squares)?;
+        let stones = squares.iter().fold(Bitboard::EMPTY, |stones, &mv| {
+            stones | Bitboard::bit(self.board.square(mv))
+        });
+        self.apply(stones);
+        Ok(())
+    }
+
+    // Whether the player who moved last has a line
+    fn has_line(&self) -> bool {
+        let stones = self.stones[usize::from(1 - self.turn)];
+        self.board.wins().iter().any(|&mask| stones & mask == mask)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.has_line() || self.empty().is_empty()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.has_line() {
+            GameStatus::Won(1 - self.turn)
+        } else if self.empty().is_empty() {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    // Squares searches combine into moves
+    fn candidates(&self) -> Bitboard {
+        let empty = self.empty();
+        let Some(reach) = self.reach else {
+            return empty;
+        };
+        let stones = self.stones[0] | self.stones[1];
+        let seeds = if stones.is_empty() {
+            let center = Move::new(self.board.height() / 2, self.board.width() / 2);
+            Bitboard::bit(self.board.square(center))
+        } else {
+            stones
+        };
+        let near = self.board.near(seeds, i32::from(reach)) & empty;
+        // Enough squares to place every stone of the turn
+        if near.count_ones() < u32::from(self.stones_to_place()) {
+            empty
+        } else {
+            near
+        }
+    }
+}
+
+// Every way to pick `count` squares of a bitboard, after those already
+// picked
+fn combinations(squares: &[u32], count: u8, picked: Bitboard, moves: &mut Vec<Bitboard>) {
+    if count == 0 {
+        moves.push(picked);
+        return;
+    }
+    for (i, &square) in squares.iter().enumerate() {
+        combinations(
+            &squares[i + 1..],
+            count - 1,
+            picked | Bitboard::bit(square),
+            moves,
+        );
+    }
+}
+
+// One row per line, X for the first player, O for the second, . when empty
+impl fmt::Display for Connect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.board.height() {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            let cells: Vec<_> = (0..self.board.width())
+                .map(|col| {
+                    let square = self.board.square(Move::new(row, col));
+                    if self.stones[0].contains(square) {
+                        "X"
+                    } else if self.stones[1].contains(square) {
+                        "O"
+                    } else {
+                        "."
+                    }
+                })
+                .collect();
+            write!(f, "{}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Connect {
+    type Move = Bitboard;
+
+    fn legal_moves(&self) -> Vec<Bitboard> {
+        if self.is_over() {
+            return vec![];
+        }
+        let squares: Vec<_> = self.candidates().ones().collect();
+        let mut moves = vec![];
+        combinations(
+            &squares,
+            self.stones_to_place(),
+            Bitboard::EMPTY,
+            &mut moves,
+        );
+        moves
+    }
+
+    fn apply(&mut self, stones: Bitboard) {
+        self.stones[usize::from(self.turn)] |= stones;
+        self.turn = 1 - self.turn;
+        self.history.push(stones);
+    }
+
+    fn undo(&mut self, stones: Bitboard) {
+        self.turn = 1 - self.turn;
+        self.stones[usize::from(self.turn)] ^= stones;
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Stones extending the longest open lines of either side first,
+    // making threats or blocking them
+    fn move_priority(&self, stones: Bitboard) -> i32 {
+        let [x, o] = self.stones;
+        stones
+            .ones()
+            .flat_map(|square| self.lines[square as usize].iter())
+            .map(|&i| {
+                let mask = self.board.wins()[i];
+                match ((x & mask).count_ones(), (o & mask).count_ones()) {
+                    (stones, 0) | (0, stones) => (stones * stones) as i32,
+                    _ => 0,
+                }
+            })
+            .sum()
+    }
+
+    // Stones and side to move; whether it's the first turn follows from
+    // the stones
+    fn hash_key(&self) -> Option<u64> {
+        let words = self.stones[0]
+            .words()
+            .into_iter()
+            .chain(self.stones[1].words());
+        Some(words.fold(mix(0, u64::from(self.turn)), mix))
+    }
+
+    // Lines still open to each side, by the square of their stones
+    fn threat_scores(&self) -> [Score; 2] {
+        [self.turn, 1 - self.turn].map(|player| {
+            let (own, theirs) = (
+                self.stones[usize::from(player)],
+                self.stones[usize::from(1 - player)],
+            );
+            self.board
+                .wins()
+                .iter()
+                .filter(|&&mask| (theirs & mask).is_empty())
+                .map(|&mask| (own & mask).count_ones().pow(2) as Score)
+                .sum()
+        })
+    }
+
+    fn evaluate(&self) -> Score {
+        match self.status() {
+            GameStatus::Won(_) => LOSS,
+            GameStatus::Draw => DRAW,
+            // Lines still open to each side, by the square of their stones
+            GameStatus::InProgress => {
+                let (player, opponent) = (
+                    self.stones[usize::from(self.turn)],
+                    self.stones[usize::from(1 - self.turn)],
+                );
+                self.board
+                    .wins()
+                    .iter()
+                    .map(|&mask| {
+                        match ((player & mask).count_ones(), (opponent & mask).count_ones()) {
+                            (ours, 0) => (ours * ours) as Score,
+                            (0, theirs) => -((theirs * theirs) as Score),
+                            _ => 0,
+                        }
+                    })
+                    .sum()
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+
+use crate::search::{GameState, Score};
+use crate::tt::mix;
+use crate::{Bitboard, BoardError, Game, GameStatus, Move, MoveError, PlayerId};
+
+/// Connect Four: players drop stones into the columns of an upright 7×6
+/// board, each stone falling to the lowest free row, and four in a row,
+/// column or diagonal win.
+///
+/// The board is a [`Game`] of the same size, whose row 0 is the top one,
+/// sharing its win masks and evaluation. Moves are column numbers.
+#[derive(Clone, Debug)]
+pub struct ConnectFour {
+    board: Game,
+    // Stones in each column
+    heights: Vec<u8>,
+}
+
+impl Default for ConnectFour {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConnectFour {
+    /// Create an empty 7×6 board with the first player to move.
+    pub fn new() -> Self {
+        Self::with_dimensions(7, 6, 4).expect("7×6 is a valid size")
+    }
+
+    /// Create an empty board of `width` columns and `height` rows won by
+    /// aligning `win_length` stones.
+    pub fn with_dimensions(width: u8, height: u8, win_length: u8) -> Result<Self, BoardError> {
+        Ok(ConnectFour {
+            board: Game::with_dimensions(width, height, win_length)?,
+            heights: vec![0; usize::from(width)],
+        })
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.board.turn()
+    }
+
+    /// Columns played so far, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = u8> + '_ {
+        self.board.history().iter().map(|mv| mv.col)
+    }
+
+    /// Stones on the board, as a [`Game`] of the same size.
+    pub fn board(&self) -> &Game {
+        &self.board
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.board.is_over()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.board.status()
+    }
+
+    /// Square a stone dropped into a column lands on, `None` when the
+    /// column is full or outside the board.
+    pub fn landing(&self, col: u8) -> Option<Move> {
+        let height = self.board.height();
+        let stones = *self.heights.get(usize::from(col))?;
+        (stones < height).then(|| Move::new(height - 1 - stones, col))
+    }
+
+    /// Check that a stone can be dropped into a column.
+    pub fn check_move(&self, col: u8) -> Result<(), MoveError> {
+        if col >= self.board.width() {
+            Err(MoveError::OutOfBounds)
+        } else if self.landing(col).is_none() {
+            Err(MoveError::Occupied)
+        } else if self.is_over() {
+            Err(MoveError::GameOver)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, col: u8) -> Result<(), MoveError> {
+        self.check_move(col)?;
+        self.apply(col);
+        Ok(())
+    }
+
+    // Squares where the next stone of a column lands
+    fn playable(&self) -> Bitboard {
+        (0..self.board.width())
+            .filter_map(|col| self.landing(col))
+            .fold(Bitboard::EMPTY, |squares, mv| {
+                squares | Bitboard::bit(self.board.square(mv))
+            })
+    }
+
+    // Stones mirrored left to right, the only symmetry gravity keeps
+    fn mirror(&self, stones: Bitboard) -> Bitboard {
+        let (width, height) = (u32::from(self.board.width()), self.board.height());
+        let first_column = (0..height).fold(Bitboard::EMPTY, |column, row| {
+            column | Bitboard::bit(u32::from(row) * width)
+        });
+        (0..width).fold(Bitboard::EMPTY, |image, col| {
+            image | ((stones >> col) & first_column) << (width - 1 - col)
+        })
+    }
+}
+
+// One row per line, top first, X for the first player, O for the second, .
+// when empty, column numbers below
+impl fmt::Display for ConnectFour {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.board)?;
+        let columns: Vec<_> = (0..self.board.width()).map(|col| col.to_string()).collect();
+        write!(f, "{}", columns.join(" "))
+    }
+}
+
+impl GameState for ConnectFour {
+    type Move = u8;
+
+    fn legal_moves(&self) -> Vec<u8> {
+        if self.is_over() {
+            return vec![];
+        }
+        (0..self.board.width())
+            .filter(|&col| self.landing(col).is_some())
+            .collect()
+    }
+
+    fn apply(&mut self, col: u8) {
+        let mv = self.landing(col).expect("Column isn't full");
+        self.board.apply(mv);
+        self.heights[usize::from(col)] += 1;
+    }
+
+    fn undo(&mut self, col: u8) {
+        self.heights[usize::from(col)] -= 1;
+        let mv = self.landing(col).expect("Column has a stone");
+        self.board.undo(mv);
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Central columns first, being part of the most lines
+    fn move_priority(&self, col: u8) -> i32 {
+        let center = i32::from(self.board.width() - 1);
+        -(2 * i32::from(col) - center).abs()
+    }
+
+    fn move_index(&self, col: u8) -> Option<usize> {
+        Some(usize::from(col))
+    }
+
+    // Keyed by the orientation sorting first of the board and its mirror
+    fn hash_key(&self) -> Option<u64> {
+        let players = self.board.players();
+        let [x, o] = players.min(players.map(|stones| self.mirror(stones)));
+        let words = x.words().into_iter().chain(o.words());
+        let key = words.fold(mix(0, u64::from(self.turn())), mix);
+        Some(key)
+    }
+
+    // Columns winning at once, or failing that columns stopping the
+    // opponent from winning at once
+    fn forcing_moves(&self) -> Vec<u8> {
+        if self.is_over() {
+            return vec![];
+        }
+        let playable = self.playable();
+        let turn = self.turn();
+        let wins = self.board.winning_squares(turn) & playable;
+        let squares = if !wins.is_empty() {
+            wins
+        } else {
+            self.board.winning_squares(1 - turn) & playable
+        };
+        squares
+            .ones()
+            .map(|square| self.board.square_move(square).col)
+            .collect()
+    }
+
+    // Open lines of each side, only gravity deciding which squares are
+    // playable
+    fn threat_scores(&self) -> [Score; 2] {
+        self.board.threat_scores()
+    }
+
+    // The evaluation of the plain game, open lines being what matters
+    // with or without gravity
+    fn evaluate(&self) -> Score {
+        self.board.evaluate()
+    }
+}
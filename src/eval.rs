@@ -0,0 +1,444 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::search::{GameState, Score};
+use crate::{Bitboard, Game, MAX_SIZE};
+
+/// Default weight of a [`ThreatEvaluator`], leaving room for finer
+/// evaluation terms.
+pub const THREAT_SCORE: Score = 10;
+
+// Species of runs of stones, by length and open ends
+const RUNS: usize = 6;
+
+// Most stones of a player on a win line, plus one
+const LINE_STONES: usize = MAX_SIZE as usize + 1;
+
+// Longest win lines scored by pattern, longer ones by their number of
+// stones of each side
+const PATTERN_SQUARES: usize = 8;
+
+// Number of patterns of a line of PATTERN_SQUARES squares, each square
+// being empty or taken by either side
+const PATTERNS: usize = 3usize.pow(PATTERN_SQUARES as u32);
+
+// Stones of the side to move and of the opponent in each pattern, a line
+// whose base 3 digits are those of its squares, 1 for a stone of the side
+// to move and 2 for one of the opponent
+const PATTERN_STONES: [[u8; 2]; PATTERNS] = pattern_stones();
+
+const fn pattern_stones() -> [[u8; 2]; PATTERNS] {
+    let mut stones = [[0; 2]; PATTERNS];
+    let mut pattern = 1;
+    while pattern < PATTERNS {
+        // The leading digits are those of pattern / 3, already counted
+        let [ours, theirs] = stones[pattern / 3];
+        stones[pattern] = match pattern % 3 {
+            1 => [ours + 1, theirs],
+            2 => [ours, theirs + 1],
+            _ => [ours, theirs],
+        };
+        pattern += 1;
+    }
+    stones
+}
+
+// Stones of both sides on a win line, with its pattern when short enough
+struct Line {
+    mask: Bitboard,
+    pattern: Option<usize>,
+    ours: u32,
+    theirs: u32,
+}
+
+// Win lines of a position from the side to move, short lines being read
+// square by square into their pattern and their stones counted from it
+fn line_stones(
+    game: &Game,
+    player: Bitboard,
+    opponent: Bitboard,
+) -> impl Iterator<Item = Line> + '_ {
+    let mut cells = [0u8; Bitboard::BITS as usize];
+    for square in player.ones() {
+        cells[square as usize] = 1;
+    }
+    for square in opponent.ones() {
+        cells[square as usize] = 2;
+    }
+    let wins = game.wins().iter().zip(game.line_squares());
+    wins.map(move |(&mask, squares)| {
+        if squares.len() > PATTERN_SQUARES {
+            let ours = (player & mask).count_ones();
+            let theirs = (opponent & mask).count_ones();
+            return Line {
+                mask,
+                pattern: None,
+                ours,
+                theirs,
+            };
+        }
+        let pattern = squares.iter().fold(0, |pattern, &square| {
+            pattern * 3 + usize::from(cells[usize::from(square)])
+        });
+        let [ours, theirs] = PATTERN_STONES[pattern];
+        Line {
+            mask,
+            pattern: Some(pattern),
+            ours: ours.into(),
+            theirs: theirs.into(),
+        }
+    })
+}
+
+/// Static evaluation of the positions where a [`Search`](crate::Search)
+/// stops before the end of the game. Closures taking a position and
+/// returning its score are evaluators too.
+pub trait Evaluator<G: GameState> {
+    /// Score of a position whose game isn't over, from the side to move.
+    /// Scores must stay below the decisive range, see
+    /// [`is_decisive`](crate::is_decisive).
+    fn evaluate(&self, game: &G) -> Score;
+}
+
+impl<G: GameState, F: Fn(&G) -> Score> Evaluator<G> for F {
+    fn evaluate(&self, game: &G) -> Score {
+        self(game)
+    }
+}
+
+/// Evaluation the game itself provides, [`GameState::evaluate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DefaultEvaluator;
+
+impl<G: GameState> Evaluator<G> for DefaultEvaluator {
+    fn evaluate(&self, game: &G) -> Score {
+        game.evaluate()
+    }
+}
+
+/// Named parameters of a [`ThreatEvaluator`]. With the `serde` feature,
+/// they load from TOML files such as `threat = 12`, missing parameters
+/// keeping their default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Weights {
+    /// Score of each unit of threat.
+    pub threat: Score,
+    /// Power the stones of a line still open are raised to, the square by
+    /// default, favouring lines close to completion over many lines barely
+    /// started.
+    pub occupancy: u32,
+    /// Score of each empty square where a player would make two threats
+    /// at once, a fork the opponent can block only one side of.
+    pub fork: Score,
+    /// Score of each empty square where a player would make a threat, for
+    /// flexible positions with many ways to go on. Not scored by default.
+    pub mobility: Score,
+    /// Score of each win line through a square, for each stone on it
+    /// whether the line is still open or not. This values the center over
+    /// the corners over the edges on the 3×3 board, and follows the shape
+    /// of larger ones.
+    pub position: Score,
+    /// Score of each run of two stones of a player, along a row, column or
+    /// diagonal, with empty squares at both ends. Runs aren't scored by
+    /// default, open lines covering most of what they tell on small boards.
+    pub open_two: Score,
+    /// Same as `open_two`, one end being blocked by the opponent or the
+    /// edge of the board.
+    pub closed_two: Score,
+    pub open_three: Score,
+    pub closed_three: Score,
+    pub open_four: Score,
+    pub closed_four: Score,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            threat: THREAT_SCORE,
+            occupancy: 2,
+            fork: 5 * THREAT_SCORE,
+            mobility: 0,
+            position: THREAT_SCORE / 5,
+            open_two: 0,
+            closed_two: 0,
+            open_three: 0,
+            closed_three: 0,
+            open_four: 0,
+            closed_four: 0,
+        }
+    }
+}
+
+impl Weights {
+    /// Number of weights adjusted by tuning, every one but the occupancy
+    /// power, also the number of [`ThreatEvaluator::features`].
+    pub const TUNABLE: usize = 10;
+
+    // Weights adjusted by tuning, in the order of the features
+    pub(crate) fn tunable_mut(&mut self) -> [&mut Score; Self::TUNABLE] {
+        [
+            &mut self.threat,
+            &mut self.fork,
+            &mut self.mobility,
+            &mut self.position,
+            &mut self.open_two,
+            &mut self.closed_two,
+            &mut self.open_three,
+            &mut self.closed_three,
+            &mut self.open_four,
+            &mut self.closed_four,
+        ]
+    }
+
+    // Weights of the runs of stones, in the order of `runs`
+    fn patterns(&self) -> [Score; RUNS] {
+        [
+            self.open_two,
+            self.closed_two,
+            self.open_three,
+            self.closed_three,
+            self.open_four,
+            self.closed_four,
+        ]
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Weights {
+    /// Read weights from a TOML file.
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        toml::from_str(&text)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    /// Write weights to a TOML file, every parameter included.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let text = toml::to_string(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        std::fs::write(path, text)
+    }
+}
+
+/// Difference between the threats of both sides, lines still open to a
+/// player scoring by the number of stones it has on them, between the
+/// squares where they would make threats or forks and between the positions
+/// of their stones, and optionally between their runs of stones. This is
+/// the evaluation of [`Game`] by default.
+///
+/// Win lines are scored from a table built with the weights, indexed by the
+/// pattern of stones read from the squares of the line, in a single pass
+/// over them. Lines of more than eight squares are scored by the number of
+/// stones of each side on them instead. Under misère rules, where lines
+/// lose, the score is the opposite.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ThreatEvaluator {
+    weights: Weights,
+    // Open line and position scores of a win line, by number of stones of
+    // the side to move and of the opponent on it
+    lines: [[Score; LINE_STONES]; LINE_STONES],
+    // The same scores by pattern of a line, see PATTERN_STONES
+    patterns: Arc<[Score]>,
+}
+
+impl fmt::Debug for ThreatEvaluator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreatEvaluator")
+            .field("weights", &self.weights)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ThreatEvaluator {
+    fn default() -> Self {
+        Self::new(Weights::default())
+    }
+}
+
+impl ThreatEvaluator {
+    pub fn new(weights: Weights) -> Self {
+        let mut lines = [[0; LINE_STONES]; LINE_STONES];
+        let open = |stones: usize| (stones as Score).saturating_pow(weights.occupancy);
+        for (ours, row) in lines.iter_mut().enumerate() {
+            for (theirs, score) in row.iter_mut().enumerate() {
+                let threats = match (ours, theirs) {
+                    (0, 0) => 0,
+                    (_, 0) => open(ours),
+                    (0, _) => -open(theirs),
+                    _ => 0,
+                };
+                let position = ours as Score - theirs as Score;
+                *score = threats
+                    .saturating_mul(weights.threat)
+                    .saturating_add(position.saturating_mul(weights.position));
+            }
+        }
+        let patterns = PATTERN_STONES
+            .iter()
+            .map(|&[ours, theirs]| lines[usize::from(ours)][usize::from(theirs)])
+            .collect();
+        ThreatEvaluator {
+            weights,
+            lines,
+            patterns,
+        }
+    }
+
+    pub fn weights(&self) -> &Weights {
+        &self.weights
+    }
+
+    // Runs of stones of `turn` along rows, columns and diagonals of each
+    // layer, counted by length and by number of empty ends in the order of
+    // the weights
+    fn runs(game: &Game, turn: u8) -> [Score; RUNS] {
+        let [x, o] = game.players();
+        let player = if turn == 0 { x } else { o };
+        let taken = x | o | game.blocked();
+        let width = i32::from(game.width());
+        let height = i32::from(game.height() / game.layers());
+        let mut runs = [0; RUNS];
+        for layer in 0..i32::from(game.layers()) {
+            let square = |row: i32, col: i32| {
+                ((0..height).contains(&row) && (0..width).contains(&col))
+                    .then(|| ((layer * height + row) * width + col) as u32)
+            };
+            let stone = |row, col| square(row, col).is_some_and(|square| player.contains(square));
+            let empty = |row, col| square(row, col).is_some_and(|square| !taken.contains(square));
+            for (dr, dc) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+                for row in 0..height {
+                    for col in 0..width {
+                        // Runs are walked from their first stone
+                        if !stone(row, col) || stone(row - dr, col - dc) {
+                            continue;
+                        }
+                        let mut length = 1;
+                        while stone(row + length * dr, col + length * dc) {
+                            length += 1;
+                        }
+                        let ends = u8::from(empty(row - dr, col - dc))
+                            + u8::from(empty(row + length * dr, col + length * dc));
+                        let species = match (length, ends) {
+                            (2..=4, 1..=2) => (length - 2) * 2 + i32::from(ends == 1),
+                            _ => continue,
+                        };
+                        runs[species as usize] += 1;
+                    }
+                }
+            }
+        }
+        runs
+    }
+
+    /// Terms of the evaluation of a position before weighting, the score
+    /// being their sum weighted like [`Weights`] lists them: open lines,
+    /// forks, mobility, position, then runs from open twos to closed fours.
+    /// Each term is the difference between the side to move and the
+    /// opponent, open lines having their stones raised to `occupancy`, the
+    /// other way round under misère rules.
+    pub fn features(game: &Game, occupancy: u32) -> [Score; Weights::TUNABLE] {
+        let turn = game.turn();
+        let [x, o] = game.players();
+        let (player, opponent) = if turn == 0 { (x, o) } else { (o, x) };
+        let win_length = u32::from(game.win_length());
+        let open = |stones: u32| (stones as Score).saturating_pow(occupancy);
+        let mut threat_squares = [[Bitboard::EMPTY; 2]; 2];
+        let (mut lines, mut position) = (0, 0);
+        for Line {
+            mask, ours, theirs, ..
+        } in line_stones(game, player, opponent)
+        {
+            lines += match (ours, theirs) {
+                (0, 0) => 0,
+                (_, 0) => open(ours),
+                (0, _) => -open(theirs),
+                _ => 0,
+            };
+            position += ours as Score - theirs as Score;
+            if theirs == 0 && ours + 2 == win_length {
+                add_threat(&mut threat_squares[0], mask & !player);
+            }
+            if ours == 0 && theirs + 2 == win_length {
+                add_threat(&mut threat_squares[1], mask & !opponent);
+            }
+        }
+        let count = |side: usize, i: usize| threat_squares[side][i].count_ones() as Score;
+        let (ours, theirs) = (Self::runs(game, turn), Self::runs(game, 1 - turn));
+        let mut features = [0; Weights::TUNABLE];
+        features[..4].copy_from_slice(&[
+            lines,
+            count(0, 1) - count(1, 1),
+            count(0, 0) - count(1, 0),
+            position,
+        ]);
+        for (i, feature) in features[4..].iter_mut().enumerate() {
+            *feature = ours[i] - theirs[i];
+        }
+        if game.is_misere() {
+            features = features.map(|feature| -feature);
+        }
+        features
+    }
+}
+
+// Add the squares where a line misses two stones to those where one side
+// makes threats, at least one and at least two
+fn add_threat([once, twice]: &mut [Bitboard; 2], missing: Bitboard) {
+    *twice |= *once & missing;
+    *once |= missing;
+}
+
+impl Evaluator<Game> for ThreatEvaluator {
+    fn evaluate(&self, game: &Game) -> Score {
+        let turn = game.turn();
+        let weights = &self.weights;
+        let [x, o] = game.players();
+        let (player, opponent) = if turn == 0 { (x, o) } else { (o, x) };
+        let win_length = u32::from(game.win_length());
+        // Squares where each side would make at least one threat, and at
+        // least two
+        let squares = weights.mobility != 0 || weights.fork != 0;
+        let mut threat_squares = [[Bitboard::EMPTY; 2]; 2];
+        let mut score = 0;
+        for Line {
+            mask,
+            pattern,
+            ours,
+            theirs,
+        } in line_stones(game, player, opponent)
+        {
+            score += match pattern {
+                Some(pattern) => self.patterns[pattern],
+                None => self.lines[ours as usize][theirs as usize],
+            };
+            if squares && theirs == 0 && ours + 2 == win_length {
+                add_threat(&mut threat_squares[0], mask & !player);
+            }
+            if squares && ours == 0 && theirs + 2 == win_length {
+                add_threat(&mut threat_squares[1], mask & !opponent);
+            }
+        }
+        if squares {
+            let count = |side: usize, i: usize| threat_squares[side][i].count_ones() as Score;
+            score += (count(0, 0) - count(1, 0)) * weights.mobility;
+            score += (count(0, 1) - count(1, 1)) * weights.fork;
+        }
+        let patterns = weights.patterns();
+        if patterns.iter().any(|&weight| weight != 0) {
+            let (ours, theirs) = (Self::runs(game, turn), Self::runs(game, 1 - turn));
+            for (i, weight) in patterns.into_iter().enumerate() {
+                score += (ours[i] - theirs[i]) * weight;
+            }
+        }
+        if game.is_misere() {
+            -score
+        } else {
+            score
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use crate::search::{from_node, GameState};
+
+/// Distribution of the moves of the opponent modeled by an [`Expectimax`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Opponent {
+    /// Uniform over legal moves, like [`Game::random_move`](crate::Game::random_move).
+    Uniform,
+    /// Softmax of [`GameState::move_priority`] at this temperature, higher
+    /// temperatures getting closer to uniform.
+    Priority(f64),
+}
+
+impl Opponent {
+    // Probability of each move
+    fn weights<G: GameState>(self, game: &G, moves: &[G::Move]) -> Vec<f64> {
+        match self {
+            Opponent::Uniform => vec![1.0 / moves.len() as f64; moves.len()],
+            Opponent::Priority(temperature) => {
+                let weights: Vec<_> = moves
+                    .iter()
+                    .map(|&mv| (f64::from(game.move_priority(mv)) / temperature).exp())
+                    .collect();
+                let total: f64 = weights.iter().sum();
+                weights.into_iter().map(|weight| weight / total).collect()
+            }
+        }
+    }
+}
+
+/// Expectimax search, maximizing the expected score of the side to move
+/// against an opponent playing moves drawn from a known distribution
+/// rather than its best ones.
+#[derive(Clone, Debug)]
+pub struct Expectimax {
+    pub opponent: Opponent,
+    /// Number of positions visited so far.
+    pub evaluations: u32,
+    // Expected scores by position key, remaining depth and ply
+    memo: HashMap<(u64, u8, u8), f64>,
+}
+
+impl Expectimax {
+    pub fn new(opponent: Opponent) -> Self {
+        Expectimax {
+            opponent,
+            evaluations: 0,
+            memo: HashMap::new(),
+        }
+    }
+
+    /// Move of the side to move with the best expected score at `depth`,
+    /// along with that score. `None` when the game is over.
+    pub fn best_move<G: GameState>(&mut self, game: &mut G, depth: u8) -> Option<(G::Move, f64)> {
+        self.memo.clear();
+        let mut best = None;
+        for mv in game.legal_moves() {
+            self.evaluations += 1;
+            game.apply(mv);
+            let score = self.expected(game, depth.saturating_sub(1), 1, false);
+            game.undo(mv);
+            if best.is_none_or(|(_, value)| score > value) {
+                best = Some((mv, score));
+            }
+        }
+        best
+    }
+
+    // Expected score for the side to move at the root, `ours` telling
+    // whether it is to move here
+    fn expected<G: GameState>(&mut self, game: &mut G, depth: u8, ply: u8, ours: bool) -> f64 {
+        let sign = if ours { 1.0 } else { -1.0 };
+        if game.is_terminal() {
+            return sign * f64::from(from_node(game.evaluate(), ply));
+        } else if depth == 0 {
+            return sign * f64::from(game.evaluate());
+        }
+        let key = game.hash_key().map(|key| (key, depth, ply));
+        if let Some(&score) = key.and_then(|key| self.memo.get(&key)) {
+            return score;
+        }
+        let moves = game.legal_moves();
+        let mut scores = Vec::with_capacity(moves.len());
+        for &mv in &moves {
+            self.evaluations += 1;
+            game.apply(mv);
+            scores.push(self.expected(game, depth - 1, ply + 1, !ours));
+            game.undo(mv);
+        }
+        let score = if ours {
+            scores.into_iter().fold(f64::NEG_INFINITY, f64::max)
+        } else {
+            let weights = self.opponent.weights(game, &moves);
+            scores
+                .iter()
+                .zip(weights)
+                .map(|(score, weight)| score * weight)
+                .sum()
+        };
+        if let Some(key) = key {
+            self.memo.insert(key, score);
+        }
+        score
+    }
+}
@@ -0,0 +1,59 @@
+use crate::Game;
+
+/// Number of values of the vector of a board.
+pub fn len(width: u8, height: u8, win_length: u8) -> usize {
+    2 * usize::from(width) * usize::from(height) + 1 + 2 * usize::from(win_length)
+}
+
+/// Flat vector of the numbers of a position for machine learning tools.
+/// The vector of a board of `width` by `height` squares and win length `k`
+/// holds [`len`] values, in this order:
+///
+/// 1. `width * height` values, 1 for each square with a stone of the first
+///    player and 0 otherwise, squares in row-major order.
+/// 2. The same for the stones of the second player.
+/// 3. The side to move, 0 for the first player and 1 for the second one.
+/// 4. `k` values, the number of win lines holding 1 to `k` stones of the
+///    first player and none of the second.
+/// 5. The same for the lines of the second player.
+///
+/// The layout depends only on the board and the win length, so vectors of
+/// games of the same rules line up.
+pub fn extract(game: &Game) -> Vec<f32> {
+    let players = game.players();
+    let mut features = Vec::with_capacity(len(game.width(), game.height(), game.win_length()));
+    for stones in players {
+        features
+            .extend((0..game.squares()).map(|square| f32::from(u8::from(stones.contains(square)))));
+    }
+    features.push(f32::from(game.turn()));
+    for (side, stones) in players.into_iter().enumerate() {
+        let opponent = players[1 - side];
+        let mut lines = vec![0.0; usize::from(game.win_length())];
+        for &mask in game.wins() {
+            let count = (stones & mask).count_ones() as usize;
+            if count > 0 && (opponent & mask).is_empty() {
+                lines[count - 1] += 1.0;
+            }
+        }
+        features.extend(lines);
+    }
+    features
+}
+
+/// Names of the values of the vector of a board, for headers of CSV files
+/// and such: `x_r0c2` for the stone of the first player on row 0 and column
+/// 2, `turn`, or `o_lines3` for the lines with 3 stones of the second player.
+pub fn names(width: u8, height: u8, win_length: u8) -> Vec<String> {
+    let mut names = Vec::with_capacity(len(width, height, win_length));
+    for side in ["x", "o"] {
+        for row in 0..height {
+            names.extend((0..width).map(|col| format!("{}_r{}c{}", side, row, col)));
+        }
+    }
+    names.push("turn".to_string());
+    for side in ["x", "o"] {
+        names.extend((1..=win_length).map(|stones| format!("{}_lines{}", side, stones)));
+    }
+    names
+}
@@ -0,0 +1,1335 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+use crate::bitboard::Bitboard;
+use crate::eval::{Evaluator, ThreatEvaluator};
+use crate::moves::Move;
+use crate::search::{GameState, Score, Search, SearchResult, Solution, DRAW, LOSS, WIN};
+use crate::tablebase::Tablebase;
+use crate::tt::mix;
+
+/// Index of a player, 0 for the first player and 1 for the second.
+pub type PlayerId = u8;
+
+/// Outcome of a game so far.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameStatus {
+    InProgress,
+    Draw,
+    Won(PlayerId),
+}
+
+/// Reason a move was rejected by [`Game::make_move`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoveError {
+    /// The square already holds a piece.
+    Occupied,
+    /// The square is not on the board.
+    OutOfBounds,
+    /// The game is already won or drawn.
+    GameOver,
+    /// The square is on another board than the one the move must be
+    /// played on, in [`Ultimate`](crate::ultimate::Ultimate) tic-tac-toe.
+    WrongBoard,
+    /// The square can't be played for the whole game, see
+    /// [`Game::with_blocked`].
+    Blocked,
+    /// A piece is placed once all of them are on the board, or slid before,
+    /// in [`Achi`](crate::achi::Achi).
+    WrongPhase,
+    /// The square slid from holds no piece of the player to move.
+    NoPiece,
+    /// The square slid to isn't next to the one slid from along a line.
+    NotAdjacent,
+    /// A [`Connect`](crate::connect::Connect) turn places more or fewer
+    /// stones than the rules ask.
+    StoneCount,
+    /// An [`Othello`](crate::othello::Othello) disc placed there would
+    /// outflank none of the opponent's.
+    NoFlip,
+    /// The player to move passes while a disc can still be placed.
+    CannotPass,
+    /// A [`Nim`](crate::nim::Nim) move takes no object, or more than the
+    /// heap holds.
+    InvalidTake,
+    /// The player isn't one of the two of the game, see
+    /// [`Game::with_handicap`].
+    InvalidPlayer,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::Occupied => write!(f, "square is already occupied"),
+            MoveError::OutOfBounds => write!(f, "square is outside the board"),
+            MoveError::GameOver => write!(f, "game is already over"),
+            MoveError::WrongBoard => write!(f, "square is outside the board to play"),
+            MoveError::Blocked => write!(f, "square is blocked"),
+            MoveError::WrongPhase => write!(f, "pieces are placed first, then slid"),
+            MoveError::NoPiece => write!(f, "square holds no piece of the player to move"),
+            MoveError::NotAdjacent => write!(f, "square isn't next to the piece along a line"),
+            MoveError::StoneCount => write!(f, "wrong number of stones for this turn"),
+            MoveError::NoFlip => write!(f, "disc would flip none of the opponent's"),
+            MoveError::CannotPass => write!(f, "a disc can still be placed"),
+            MoveError::InvalidTake => write!(f, "must take between one object and the whole heap"),
+            MoveError::InvalidPlayer => write!(f, "no such player"),
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+/// Smallest supported side length.
+pub const MIN_SIZE: u8 = 2;
+/// Largest side length of a square board whose squares fit in a
+/// [`Bitboard`].
+pub const MAX_SIZE: u8 = 15;
+/// Largest number of squares of a board, rectangular boards are
+/// supported as long as they fit and squares are numbered by a byte.
+pub const MAX_SQUARES: u32 = Bitboard::BITS - 1;
+
+/// Reason a board could not be built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoardError {
+    /// Side shorter than `MIN_SIZE` or more than `MAX_SQUARES` squares.
+    InvalidSize { width: u8, height: u8 },
+    /// Win length of less than 2 or more than the longest side or
+    /// `MAX_SIZE`.
+    InvalidWinLength(u8),
+    /// Fewer than 2 players, or more than a
+    /// [`MultiGame`](crate::multiplayer::MultiGame) seats.
+    InvalidPlayers(u8),
+    /// Win line, by index, with fewer than 2 or more than `MAX_SIZE`
+    /// squares, or squares outside the board or repeated. Index 0 also
+    /// stands for an empty list of lines.
+    InvalidLine(usize),
+    /// Pieces per player leaving no empty square for
+    /// [`Achi`](crate::achi::Achi) pieces to slide to, or too few to
+    /// complete a line, or stones placed per turn of a
+    /// [`Connect`](crate::connect::Connect) game not between 1 and
+    /// [`MAX_STONES`](crate::connect::MAX_STONES).
+    InvalidPieces(u8),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::InvalidSize { width, height } => write!(
+                f,
+                "{}×{} board is smaller than {}×{} or has more than {} squares",
+                width, height, MIN_SIZE, MIN_SIZE, MAX_SQUARES
+            ),
+            BoardError::InvalidWinLength(length) => {
+                write!(f, "win length {} does not fit the board", length)
+            }
+            BoardError::InvalidPlayers(players) => {
+                write!(f, "{} players can't share a board", players)
+            }
+            BoardError::InvalidLine(line) => write!(f, "win line {} is invalid", line),
+            BoardError::InvalidPieces(pieces) => {
+                write!(f, "{} pieces per player don't fit the board", pieces)
+            }
+        }
+    }
+}
+
+impl Error for BoardError {}
+
+// Generate masks for win conditions, every run of win_length squares
+// along horizontals, verticals and both diagonals of each layer, and along
+// the lines across layers on boards of several layers. On toroidal boards
+// runs wrap around the edges, those crossing a square twice left out
+fn win_masks(width: u8, height: u8, layers: u8, win_length: u8, toroidal: bool) -> Vec<Bitboard> {
+    let size = [layers, height, width].map(i32::from);
+    let length = i32::from(win_length);
+    // Directions whose first nonzero step is positive, within a layer first
+    let mut directions = vec![[0, 0, 1], [0, 1, 0], [0, 1, 1], [0, 1, -1]];
+    for d_row in -1..=1 {
+        for d_col in -1..=1 {
+            directions.push([1, d_row, d_col]);
+        }
+    }
+    let mut wins = vec![];
+    for direction in directions {
+        for layer in 0..size[0] {
+            for row in 0..size[1] {
+                for col in 0..size[2] {
+                    let start = [layer, row, col];
+                    let fits = (0..3).all(|axis| {
+                        (0..size[axis]).contains(&(start[axis] + direction[axis] * (length - 1)))
+                    });
+                    if !fits && !toroidal {
+                        continue;
+                    }
+                    let mask = (0..length).fold(Bitboard::EMPTY, |mask, i| {
+                        let [l, r, c] = [0, 1, 2]
+                            .map(|axis| (start[axis] + direction[axis] * i).rem_euclid(size[axis]));
+                        mask | Bitboard::bit(((l * size[1] + r) * size[2] + c) as u32)
+                    });
+                    if mask.count_ones() == u32::from(win_length) && !wins.contains(&mask) {
+                        wins.push(mask);
+                    }
+                }
+            }
+        }
+    }
+    wins
+}
+
+// Squares of each win line, for evaluations reading lines square by square
+fn line_squares(wins: &[Bitboard]) -> Arc<[Vec<u8>]> {
+    wins.iter()
+        .map(|mask| mask.ones().map(|square| square as u8).collect())
+        .collect()
+}
+
+// Square permutations mapping the board onto itself, identity excluded:
+// the reflections of each axis and the swaps of axes of the same length,
+// which on a single layer are both flips and the half turn, plus diagonal
+// reflections and quarter turns on square boards. Toroidal boards add
+// every translation, and their compositions with the others
+fn symmetries(width: u8, height: u8, layers: u8, toroidal: bool) -> Vec<Vec<u8>> {
+    let size = [layers, height, width];
+    let squares: Vec<_> = (0..layers)
+        .flat_map(|l| (0..height).flat_map(move |r| (0..width).map(move |c| [l, r, c])))
+        .collect();
+    let identity: Vec<u8> = (0..squares.len() as u8).collect();
+    let mut perms = vec![];
+    for axes in [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ] {
+        if (0..3).any(|axis| size[axes[axis]] != size[axis]) {
+            continue;
+        }
+        for flips in 0..8 {
+            let perm: Vec<u8> = squares
+                .iter()
+                .map(|square| {
+                    let [l, r, c] = [0, 1, 2].map(|axis| {
+                        let coord = square[axes[axis]];
+                        if flips & (1 << axis) != 0 {
+                            size[axis] - 1 - coord
+                        } else {
+                            coord
+                        }
+                    });
+                    (l * height + r) * width + c
+                })
+                .collect();
+            if perm != identity && !perms.contains(&perm) {
+                perms.push(perm);
+            }
+        }
+    }
+    if !toroidal {
+        return perms;
+    }
+    let base: Vec<_> = std::iter::once(identity.clone()).chain(perms).collect();
+    let mut seen = HashSet::new();
+    let mut perms = vec![];
+    for shift in &squares {
+        for perm in &base {
+            let moved: Vec<u8> = perm
+                .iter()
+                .map(|&image| {
+                    let square = squares[usize::from(image)];
+                    let [l, r, c] = [0, 1, 2].map(|axis| (square[axis] + shift[axis]) % size[axis]);
+                    (l * height + r) * width + c
+                })
+                .collect();
+            if moved != identity && seen.insert(moved.clone()) {
+                perms.push(moved);
+            }
+        }
+    }
+    perms
+}
+
+/// Tic-tac-toe position stored as one bitboard per player. Cloning is
+/// cheap, win masks are shared between copies.
+///
+/// The board size is runtime data rather than a const generic, so that
+/// sizes read from the command line or from saved games, and the other
+/// dimensions, win lengths and rules built on top of them, share one type.
+/// Sizes are checked when the game is built, see [`Game::with_layers`];
+/// [`SquareGame`](crate::square::SquareGame) checks square sizes at
+/// compile time instead.
+///
+/// With the `serde` feature, games serialize as their dimensions, win
+/// length, rules and move history; moves are replayed and validated on
+/// load.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "GameRepr", into = "GameRepr")
+)]
+pub struct Game {
+    players: [Bitboard; 2],
+    turn: u8,
+    width: u8,
+    height: u8,
+    layers: u8,
+    win_length: u8,
+    misere: bool,
+    toroidal: bool,
+    // Squares nobody may play
+    blocked: Bitboard,
+    // Stones of each player placed before the first move
+    handicap: [Bitboard; 2],
+    wins: Arc<[Bitboard]>,
+    // Squares of each win line, in the order of the masks
+    line_squares: Arc<[Vec<u8>]>,
+    // Win lines given instead of the straight ones, and a key telling
+    // them apart
+    lines: Option<Arc<[Bitboard]>>,
+    lines_key: u64,
+    history: Vec<Move>,
+    symmetries: Arc<[Vec<u8>]>,
+    // Squares that may be played
+    full: Bitboard,
+    total_evaluations: u32,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    /// Create an empty 3×3 board with the first player to move.
+    pub fn new() -> Self {
+        Self::with_size(3).expect("3 is a valid size")
+    }
+
+    /// Create a 3×3 game by replaying a sequence of moves.
+    pub fn from_moves(moves: &[Move]) -> Result<Self, MoveError> {
+        let mut game = Self::new();
+        game.play_moves(moves)?;
+        Ok(game)
+    }
+
+    /// Create an empty `size`×`size` board with the first player to move,
+    /// won by completing a full row, column or diagonal. Sizes the board
+    /// can't hold give a [`BoardError`].
+    pub fn with_size(size: u8) -> Result<Self, BoardError> {
+        Self::with_win_length(size, size)
+    }
+
+    /// Create an empty `size`×`size` board won by aligning `win_length`
+    /// pieces in a row, column or diagonal.
+    pub fn with_win_length(size: u8, win_length: u8) -> Result<Self, BoardError> {
+        Self::with_dimensions(size, size, win_length)
+    }
+
+    /// Create an empty board of `width` columns and `height` rows won by
+    /// aligning `win_length` pieces in a row, column or diagonal.
+    pub fn with_dimensions(width: u8, height: u8, win_length: u8) -> Result<Self, BoardError> {
+        Self::with_layers(width, height, 1, win_length)
+    }
+
+    /// Create an empty board of `layers` layers of `width` columns and
+    /// `height` rows each, won by aligning `win_length` pieces along any
+    /// straight line, within a layer or across them. Layers are stacked
+    /// along the rows of moves: layer `l` holds rows `l * height` to
+    /// `(l + 1) * height - 1`.
+    pub fn with_layers(
+        width: u8,
+        height: u8,
+        layers: u8,
+        win_length: u8,
+    ) -> Result<Self, BoardError> {
+        let squares = u32::from(width) * u32::from(height) * u32::from(layers);
+        if width < MIN_SIZE || height < MIN_SIZE || layers == 0 || squares > MAX_SQUARES {
+            let height = height.saturating_mul(layers);
+            return Err(BoardError::InvalidSize { width, height });
+        }
+        if !(2..=width.max(height).max(layers).min(MAX_SIZE)).contains(&win_length) {
+            return Err(BoardError::InvalidWinLength(win_length));
+        }
+        let wins = win_masks(width, height, layers, win_length, false);
+        let line_squares = line_squares(&wins);
+        Ok(Game {
+            players: [Bitboard::EMPTY; 2],
+            turn: 0,
+            width,
+            height: height * layers,
+            layers,
+            win_length,
+            misere: false,
+            toroidal: false,
+            blocked: Bitboard::EMPTY,
+            handicap: [Bitboard::EMPTY; 2],
+            wins: wins.into(),
+            line_squares,
+            lines: None,
+            lines_key: 0,
+            history: vec![],
+            symmetries: symmetries(width, height, layers, false).into(),
+            full: Bitboard::below(squares),
+            total_evaluations: 0,
+        })
+    }
+
+    /// Same game under misère rules, where completing a line loses.
+    pub fn misere(self) -> Self {
+        Game {
+            misere: true,
+            ..self
+        }
+    }
+
+    /// Same game on a torus, lines wrapping around the edges of the board
+    /// as if opposite edges were glued together. Every square then lies on
+    /// as many lines, and translations join rotations and reflections among
+    /// the symmetries searches share positions across.
+    pub fn toroidal(self) -> Self {
+        Game {
+            toroidal: true,
+            ..self
+        }
+        .with_geometry()
+    }
+
+    /// Same game with squares nobody may play for the whole game, lines
+    /// through them never winning. Symmetries of the board not mapping the
+    /// blocked squares onto themselves are dropped.
+    pub fn with_blocked(mut self, squares: &[Move]) -> Result<Self, MoveError> {
+        for &mv in squares {
+            self.check_move(mv)?;
+            self.blocked.insert(self.square(mv));
+        }
+        self.full &= !self.blocked;
+        Ok(self.with_geometry())
+    }
+
+    /// Same game with stones of `player` on squares before the first move,
+    /// as a handicap. They count as the player's stones in every way but
+    /// aren't moves: the first player still moves first and they are left
+    /// out of the history. Stones completing a line or filling the board
+    /// are refused, the game being over before it starts.
+    pub fn with_handicap(mut self, player: PlayerId, squares: &[Move]) -> Result<Self, MoveError> {
+        if player >= 2 {
+            return Err(MoveError::InvalidPlayer);
+        }
+        for &mv in squares {
+            self.check_move(mv)?;
+            let square = Bitboard::bit(self.square(mv));
+            self.players[usize::from(player)] |= square;
+            self.handicap[usize::from(player)] |= square;
+        }
+        // Lines of the player who moves first aren't seen by `is_over`
+        let line = self
+            .wins
+            .iter()
+            .any(|&mask| self.players.iter().any(|&stones| stones & mask == mask));
+        if line || self.is_full() {
+            return Err(MoveError::GameOver);
+        }
+        Ok(self)
+    }
+
+    /// Same game won by completing any of the given lines instead of
+    /// straight ones, such as the triples of a magic square adding up to
+    /// 15, or the edges of a graph. Lines hold 2 to `MAX_SIZE` squares and
+    /// may differ in length, the win length becoming the longest one.
+    /// Symmetries of the board not mapping the lines onto each other are
+    /// dropped.
+    pub fn with_lines(mut self, lines: &[Vec<Move>]) -> Result<Self, BoardError> {
+        if lines.is_empty() {
+            return Err(BoardError::InvalidLine(0));
+        }
+        let mut masks = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            let mut mask = Bitboard::EMPTY;
+            for &mv in line {
+                if mv.row >= self.height || mv.col >= self.width {
+                    return Err(BoardError::InvalidLine(i));
+                }
+                mask.insert(self.square(mv));
+            }
+            let squares = mask.count_ones() as usize;
+            if squares != line.len() || !(2..=usize::from(MAX_SIZE)).contains(&squares) {
+                return Err(BoardError::InvalidLine(i));
+            }
+            if !masks.contains(&mask) {
+                masks.push(mask);
+            }
+        }
+        self.win_length = masks
+            .iter()
+            .map(|mask| mask.count_ones())
+            .max()
+            .unwrap_or(0) as u8;
+        self.lines_key = masks
+            .iter()
+            .flat_map(|mask| mask.words())
+            .fold(mix(0, masks.len() as u64), mix);
+        self.lines = Some(masks.into());
+        Ok(self.with_geometry())
+    }
+
+    // Win masks and symmetries of the board, lines through blocked squares
+    // and symmetries moving them or not keeping custom lines left out
+    fn with_geometry(self) -> Self {
+        let rows = self.height / self.layers;
+        let (width, layers, toroidal) = (self.width, self.layers, self.toroidal);
+        let blocked = self.blocked;
+        let lines = match &self.lines {
+            Some(lines) => lines.to_vec(),
+            None => win_masks(width, rows, layers, self.win_length, toroidal),
+        };
+        let wins: Vec<_> = lines
+            .iter()
+            .copied()
+            .filter(|&mask| (mask & blocked).is_empty())
+            .collect();
+        let permute = |mask: Bitboard, perm: &[u8]| {
+            mask.ones().fold(Bitboard::EMPTY, |image, square| {
+                image | Bitboard::bit(u32::from(perm[square as usize]))
+            })
+        };
+        let custom = self.lines.is_some();
+        let symmetries: Vec<_> = symmetries(width, rows, layers, toroidal)
+            .into_iter()
+            .filter(|perm| permute(blocked, perm) == blocked)
+            .filter(|perm| {
+                !custom
+                    || lines
+                        .iter()
+                        .all(|&mask| lines.contains(&permute(mask, perm)))
+            })
+            .collect();
+        Game {
+            line_squares: line_squares(&wins),
+            wins: wins.into(),
+            symmetries: symmetries.into(),
+            ..self
+        }
+    }
+
+    /// Create an empty game of Qubic, 3D tic-tac-toe on four layers of 4×4
+    /// boards won by four in a line, with 76 winning lines.
+    pub fn qubic() -> Self {
+        Self::with_layers(4, 4, 4, 4).expect("4×4×4 is a valid size")
+    }
+
+    /// Index of the player to move.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Number of columns of the board.
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Number of rows of the board, those of every layer.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Number of layers of the board, 1 on flat boards.
+    pub fn layers(&self) -> u8 {
+        self.layers
+    }
+
+    /// Number of squares of the board.
+    pub fn squares(&self) -> u32 {
+        u32::from(self.width) * u32::from(self.height)
+    }
+
+    /// Whether completing a line loses rather than wins.
+    pub fn is_misere(&self) -> bool {
+        self.misere
+    }
+
+    /// Whether lines wrap around the edges of the board.
+    pub fn is_toroidal(&self) -> bool {
+        self.toroidal
+    }
+
+    /// Squares nobody may play, see [`Game::with_blocked`].
+    pub fn blocked(&self) -> Bitboard {
+        self.blocked
+    }
+
+    /// Stones of both players placed before the first move, see
+    /// [`Game::with_handicap`].
+    pub fn handicap(&self) -> [Bitboard; 2] {
+        self.handicap
+    }
+
+    /// Number of aligned pieces needed to win.
+    pub fn win_length(&self) -> u8 {
+        self.win_length
+    }
+
+    /// Bitboards of both players, bit `square` set for each occupied square.
+    pub fn players(&self) -> [Bitboard; 2] {
+        self.players
+    }
+
+    /// Masks of every winning line.
+    pub fn wins(&self) -> &[Bitboard] {
+        &self.wins
+    }
+
+    /// Squares of every winning line, in the order of [`Game::wins`].
+    pub fn line_squares(&self) -> &[Vec<u8>] {
+        &self.line_squares
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// Number of positions visited by searches on this game so far.
+    pub fn total_evaluations(&self) -> u32 {
+        self.total_evaluations
+    }
+
+    /// Bit index of the square of a move
+    pub fn square(&self, mv: Move) -> u32 {
+        u32::from(mv.row) * u32::from(self.width) + u32::from(mv.col)
+    }
+
+    /// Move playing the square at a bit index
+    pub fn square_move(&self, square: u32) -> Move {
+        let width = u32::from(self.width);
+        Move::new((square / width) as u8, (square % width) as u8)
+    }
+
+    /// Check that a move can be played in the current position
+    pub fn check_move(&self, mv: Move) -> Result<(), MoveError> {
+        if mv.row >= self.height || mv.col >= self.width {
+            Err(MoveError::OutOfBounds)
+        } else if self.blocked.contains(self.square(mv)) {
+            Err(MoveError::Blocked)
+        } else if (self.players[0] | self.players[1]).contains(self.square(mv)) {
+            Err(MoveError::Occupied)
+        } else if self.is_over() {
+            Err(MoveError::GameOver)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Make a move and changes player, rejecting illegal moves
+    pub fn make_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.play(mv);
+        Ok(())
+    }
+
+    /// Play a sequence of moves, stopping at the first illegal one
+    pub fn play_moves(&mut self, moves: &[Move]) -> Result<(), MoveError> {
+        moves.iter().try_for_each(|&mv| self.make_move(mv))
+    }
+
+    // Make a move without legality checks and changes player
+    fn play(&mut self, mv: Move) {
+        let mask = Bitboard::bit(self.square(mv));
+        self.players[self.turn as usize] ^= mask;
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    /// Copy of the game with a move played, leaving this one untouched
+    pub fn with_move(&self, mv: Move) -> Result<Game, MoveError> {
+        self.check_move(mv)?;
+        let mut next = self.clone();
+        next.play(mv);
+        Ok(next)
+    }
+
+    /// Reverse a move and changes player
+    pub fn undo_move(&mut self, mv: Move) {
+        let mask = Bitboard::bit(self.square(mv));
+        self.turn = 1 - self.turn;
+        self.players[self.turn as usize] ^= mask;
+        self.history.pop();
+    }
+
+    /// Take back the last move played, if any
+    pub fn take_back(&mut self) -> Option<Move> {
+        let mv = *self.history.last()?;
+        self.undo_move(mv);
+        Some(mv)
+    }
+
+    // Replace the stones on the board, the first player having moved first
+    // once handicap stones are put aside, forgetting the history
+    pub(crate) fn set_players(&mut self, players: [Bitboard; 2]) {
+        self.players = players;
+        let [x, o] = players.map(Bitboard::count_ones);
+        let [x_handicap, o_handicap] = self.handicap.map(Bitboard::count_ones);
+        self.turn = u8::from(x + o_handicap > o + x_handicap);
+        self.history.clear();
+    }
+
+    // Squares within `reach` steps of some of `squares` along rows, columns
+    // or diagonals, those squares included
+    pub(crate) fn near(&self, squares: Bitboard, reach: i32) -> Bitboard {
+        let (width, height) = (i32::from(self.width), i32::from(self.height));
+        let mut near = Bitboard::EMPTY;
+        for square in squares.ones() {
+            let (row, col) = ((square as i32) / width, (square as i32) % width);
+            for r in (row - reach).max(0)..=(row + reach).min(height - 1) {
+                for c in (col - reach).max(0)..=(col + reach).min(width - 1) {
+                    let (dr, dc) = (r - row, c - col);
+                    if dr == 0 || dc == 0 || dr.abs() == dc.abs() {
+                        near.insert((r * width + c) as u32);
+                    }
+                }
+            }
+        }
+        near
+    }
+
+    /// Compute possible next moves
+    pub fn moves(&self) -> Vec<Move> {
+        let board = self.players[0] | self.players[1];
+        self.square_moves(self.full & !board)
+    }
+
+    // Moves playing the squares of a bitboard
+    fn square_moves(&self, squares: Bitboard) -> Vec<Move> {
+        squares
+            .ones()
+            .map(|square| self.square_move(square))
+            .collect()
+    }
+
+    /// Check if game was won by the player who moved last, never the case
+    /// under misère rules
+    pub fn is_won(&self) -> bool {
+        !self.misere && self.has_line()
+    }
+
+    /// Check if the player who moved last completed a line, winning the
+    /// game, or losing it under misère rules
+    pub fn has_line(&self) -> bool {
+        let x = self.players[(1 - self.turn) as usize];
+        self.wins.iter().any(|&mask| x & mask == mask)
+    }
+
+    /// Squares of the line completed by the player who moved last, which
+    /// won the game or lost it under misère rules, of every one of them
+    /// when the last move completed several
+    pub fn winning_line(&self) -> Option<Bitboard> {
+        let x = self.players[(1 - self.turn) as usize];
+        let line = self
+            .wins
+            .iter()
+            .filter(|&&mask| x & mask == mask)
+            .fold(Bitboard::EMPTY, |line, &mask| line | mask);
+        (!line.is_empty()).then_some(line)
+    }
+
+    /// Check if no more move is possible, blocked squares never being
+    /// played
+    pub fn is_full(&self) -> bool {
+        self.players[0] | self.players[1] == self.full
+    }
+
+    /// Check game over, either by full, line or both
+    pub fn is_over(&self) -> bool {
+        self.is_full() | self.has_line()
+    }
+
+    /// Bitboards of the player in the orientation of the board that sorts
+    /// first among its rotations and reflections, equal for equivalent
+    /// positions.
+    pub fn canonical(&self) -> [Bitboard; 2] {
+        let permute = |board: Bitboard, perm: &[u8]| {
+            let mut image = Bitboard::EMPTY;
+            for square in board.ones() {
+                image.insert(u32::from(perm[square as usize]));
+            }
+            image
+        };
+        self.symmetries
+            .iter()
+            .map(|perm| self.players.map(|board| permute(board, perm)))
+            .fold(self.players, |min, image| min.min(image))
+    }
+
+    /// Current outcome of the game
+    pub fn status(&self) -> GameStatus {
+        if self.has_line() {
+            GameStatus::Won(if self.misere {
+                self.turn
+            } else {
+                1 - self.turn
+            })
+        } else if self.is_full() {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// Player who completed a line, if any
+    pub fn winner(&self) -> Option<PlayerId> {
+        match self.status() {
+            GameStatus::Won(player) => Some(player),
+            _ => None,
+        }
+    }
+
+    /// Compute number of free lines and occupancy for player
+    pub fn threats(&self, turn: u8) -> Score {
+        let player = self.players[turn as usize];
+        let opponent = self.players[(1 - turn) as usize];
+        let threats: u32 = self
+            .wins
+            .iter()
+            .filter(|&&mask| (opponent & mask).is_empty())
+            .map(|&mask| (player & mask).count_ones().pow(2))
+            .sum();
+        threats as Score
+    }
+
+    /// Empty squares completing a line of `turn`
+    pub fn winning_squares(&self, turn: u8) -> Bitboard {
+        let player = self.players[turn as usize];
+        let opponent = self.players[(1 - turn) as usize];
+        self.wins
+            .iter()
+            .filter(|&&mask| (opponent & mask).is_empty())
+            .map(|&mask| mask & !player)
+            .filter(|missing| missing.count_ones() == 1)
+            .fold(Bitboard::EMPTY, |squares, missing| squares | missing)
+    }
+
+    /// Score heuristic based on both sides threats, from the side to move,
+    /// see [`ThreatEvaluator`]
+    pub fn heuristic(&self) -> Score {
+        static EVALUATOR: OnceLock<ThreatEvaluator> = OnceLock::new();
+        EVALUATOR
+            .get_or_init(ThreatEvaluator::default)
+            .evaluate(self)
+    }
+
+    /// Return best move according to minimax. Read from the embedded
+    /// 3×3 table without searching when the search would reach the end of
+    /// the game anyway
+    pub fn best_move(&mut self, alpha: Score, beta: Score, depth: u8) -> Move {
+        let exhaustive = alpha <= LOSS && beta >= WIN && depth as usize >= self.moves().len();
+        if let Some(mv) = exhaustive
+            .then(Tablebase::classic)
+            .filter(|table| table.is_for(self))
+            .and_then(|table| table.best_move(self, &mut thread_rng()))
+        {
+            return mv;
+        }
+        self.negamax(alpha, beta, depth)
+            .best()
+            .expect("Can't chose from 0 moves")
+    }
+
+    /// Return best move found by iterative deepening within a time budget
+    pub fn best_move_within(&mut self, budget: Duration) -> Move {
+        let mut search = Search::new();
+        let result = search.iterative_deepening(self, u8::MAX, budget);
+        self.total_evaluations += search.evaluations;
+        result.best().expect("Can't chose from 0 moves")
+    }
+
+    /// Return the exact outcome of the game and an optimal move
+    pub fn solve(&mut self) -> Solution<Move> {
+        let mut search = Search::new();
+        let solution = search.solve(self);
+        self.total_evaluations += search.evaluations;
+        solution
+    }
+
+    /// Play randomly
+    pub fn random_move(&mut self) -> Move {
+        self.random_move_with(&mut thread_rng())
+    }
+
+    /// Play randomly, drawing from a given generator for reproducible games
+    pub fn random_move_with(&self, rng: &mut impl Rng) -> Move {
+        *self.moves().choose(rng).expect("Can't chose from 0 moves")
+    }
+
+    /// Random position after `pairs` moves of each player from this one,
+    /// none of them ending the game, for varied openings in tournaments
+    /// and training data. Stops short when every move would end the game.
+    pub fn random_start(&self, pairs: u8, rng: &mut impl Rng) -> Game {
+        self.with_random_moves(pairs.saturating_mul(2), rng)
+    }
+
+    // Position after `plies` random moves not ending the game, or fewer
+    // when every move would
+    pub(crate) fn with_random_moves(&self, plies: u8, rng: &mut impl Rng) -> Game {
+        let mut game = self.clone();
+        for _ in 0..plies {
+            let moves: Vec<_> = game
+                .moves()
+                .into_iter()
+                .filter(|&mv| game.with_move(mv).is_ok_and(|next| !next.is_over()))
+                .collect();
+            match moves.choose(rng) {
+                Some(&mv) => game.play(mv),
+                None => break,
+            }
+        }
+        game
+    }
+
+    /// Evaluate positions according to the negamax algorithm, returning
+    /// the score from the side to move and the expected line of play
+    pub fn negamax(&mut self, alpha: Score, beta: Score, depth: u8) -> SearchResult<Move> {
+        let mut search = Search::new();
+        let result = search.negamax(self, alpha, beta, depth);
+        self.total_evaluations += search.evaluations;
+        result
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Game {
+    /// Same game won by the lines of a TOML file, or a JSON one when its
+    /// extension is `.json`, as [`Game::with_lines`]. Files hold a single
+    /// `lines` list of lines, each one a list of squares such as `"b2"`:
+    ///
+    /// ```toml
+    /// lines = [["a1", "b1", "c1"], ["a1", "b2", "c3"]]
+    /// ```
+    pub fn load_lines(self, path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let file: LinesFile = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text).map_err(|e| invalid(e.to_string()))?
+        } else {
+            toml::from_str(&text).map_err(|e| invalid(e.to_string()))?
+        };
+        let lines = file
+            .lines
+            .iter()
+            .map(|line| line.iter().map(|square| square.parse()).collect())
+            .collect::<Result<Vec<Vec<Move>>, _>>()
+            .map_err(|e| invalid(e.to_string()))?;
+        self.with_lines(&lines).map_err(|e| invalid(e.to_string()))
+    }
+}
+
+// Win lines file, squares given like moves are typed
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LinesFile {
+    lines: Vec<Vec<String>>,
+}
+
+// Serialized form of a game, without the derived win masks
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameRepr {
+    width: u8,
+    // Rows of each layer
+    height: u8,
+    #[serde(default = "one_layer", skip_serializing_if = "is_one_layer")]
+    layers: u8,
+    win_length: u8,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    misere: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    toroidal: bool,
+    // Custom win lines, none for straight ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    lines: Vec<Vec<Move>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    blocked: Vec<Move>,
+    // Handicap stones of each player
+    #[serde(default, skip_serializing_if = "no_handicap")]
+    handicap: [Vec<Move>; 2],
+    moves: Vec<Move>,
+}
+
+#[cfg(feature = "serde")]
+fn no_handicap(handicap: &[Vec<Move>; 2]) -> bool {
+    handicap.iter().all(Vec::is_empty)
+}
+
+#[cfg(feature = "serde")]
+fn one_layer() -> u8 {
+    1
+}
+
+#[cfg(feature = "serde")]
+fn is_one_layer(layers: &u8) -> bool {
+    *layers == 1
+}
+
+#[cfg(feature = "serde")]
+impl From<Game> for GameRepr {
+    fn from(game: Game) -> Self {
+        GameRepr {
+            width: game.width,
+            height: game.height / game.layers,
+            layers: game.layers,
+            win_length: game.win_length,
+            misere: game.misere,
+            toroidal: game.toroidal,
+            lines: game
+                .lines
+                .iter()
+                .flat_map(|lines| lines.iter())
+                .map(|&mask| game.square_moves(mask))
+                .collect(),
+            blocked: game.square_moves(game.blocked),
+            handicap: game.handicap.map(|stones| game.square_moves(stones)),
+            moves: game.history,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<GameRepr> for Game {
+    type Error = String;
+
+    fn try_from(repr: GameRepr) -> Result<Self, Self::Error> {
+        // The win length of custom lines follows from them
+        let win_length = if repr.lines.is_empty() {
+            repr.win_length
+        } else {
+            2
+        };
+        let mut game = Game::with_layers(repr.width, repr.height, repr.layers, win_length)
+            .map_err(|e| e.to_string())?;
+        game.misere = repr.misere;
+        if repr.toroidal {
+            game = game.toroidal();
+        }
+        if !repr.lines.is_empty() {
+            game = game.with_lines(&repr.lines).map_err(|e| e.to_string())?;
+        }
+        game = game
+            .with_blocked(&repr.blocked)
+            .map_err(|e| e.to_string())?;
+        for (player, stones) in (0..2).zip(&repr.handicap) {
+            game = game
+                .with_handicap(player, stones)
+                .map_err(|e| e.to_string())?;
+        }
+        game.play_moves(&repr.moves).map_err(|e| e.to_string())?;
+        Ok(game)
+    }
+}
+
+/// Board of a [`Game`] printed like the game, optionally with a square
+/// marked `*` such as a suggested move, with the column letters and row
+/// numbers of squares, and in color, see [`Game::highlight`] and
+/// [`Game::labelled`].
+#[derive(Clone, Copy, Debug)]
+pub struct BoardView<'a> {
+    game: &'a Game,
+    mark: Option<Move>,
+    labels: bool,
+    colored: bool,
+}
+
+impl BoardView<'_> {
+    /// Same board with `*` on the square of `mv`.
+    pub fn highlight(self, mv: Move) -> Self {
+        BoardView {
+            mark: Some(mv),
+            ..self
+        }
+    }
+
+    /// Same board with column letters above it and row numbers to its
+    /// left.
+    pub fn labelled(self) -> Self {
+        BoardView {
+            labels: true,
+            ..self
+        }
+    }
+
+    /// Same board drawn with ANSI colors, X in red and O in blue, the last
+    /// move in bold and underlined and the winning line in reverse video.
+    pub fn colored(self) -> Self {
+        BoardView {
+            colored: true,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for BoardView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let game = self.game;
+        let rows = game.height / game.layers;
+        let pad = game.height.to_string().len();
+        let win = game.winning_line().unwrap_or(Bitboard::EMPTY);
+        if self.labels {
+            let letters: Vec<_> = (0..game.width)
+                .map(|col| char::from(b'a' + col).to_string())
+                .collect();
+            writeln!(f, "{:pad$} {}", "", letters.join(" "))?;
+        }
+        for row in 0..game.height {
+            if row > 0 {
+                writeln!(f)?;
+                if row % rows == 0 {
+                    writeln!(f)?;
+                }
+            }
+            if self.labels {
+                write!(f, "{:>pad$} ", row + 1)?;
+            }
+            for col in 0..game.width {
+                if col > 0 {
+                    write!(f, " ")?;
+                }
+                let mv = Move::new(row, col);
+                let square = game.square(mv);
+                let symbol = if self.mark == Some(mv) {
+                    '*'
+                } else if game.players[0].contains(square) {
+                    'X'
+                } else if game.players[1].contains(square) {
+                    'O'
+                } else if game.blocked.contains(square) {
+                    '#'
+                } else {
+                    '.'
+                };
+                if !self.colored {
+                    write!(f, "{}", symbol)?;
+                    continue;
+                }
+                // Select graphic rendition parameters of the square
+                let mut styles = vec![];
+                styles.extend(match symbol {
+                    'X' => Some("31"),
+                    'O' => Some("34"),
+                    '*' => Some("33"),
+                    _ => None,
+                });
+                if game.history.last() == Some(&mv) {
+                    styles.push("1;4");
+                }
+                if win.contains(square) {
+                    styles.push("7");
+                }
+                if styles.is_empty() {
+                    write!(f, "{}", symbol)?;
+                } else {
+                    write!(f, "\x1b[{}m{}\x1b[0m", styles.join(";"), symbol)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Game {
+    /// Board printed like the game, with `*` on the square of `mv`.
+    pub fn highlight(&self, mv: Move) -> BoardView<'_> {
+        self.view().highlight(mv)
+    }
+
+    /// Board printed like the game, with the coordinates of squares.
+    pub fn labelled(&self) -> BoardView<'_> {
+        self.view().labelled()
+    }
+
+    fn view(&self) -> BoardView<'_> {
+        BoardView {
+            game: self,
+            mark: None,
+            labels: false,
+            colored: false,
+        }
+    }
+}
+
+// One row per line, X for the first player, O for the second, . when empty,
+// layers separated by blank lines
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.view())
+    }
+}
+
+impl GameState for Game {
+    type Move = Move;
+
+    fn legal_moves(&self) -> Vec<Move> {
+        self.moves()
+    }
+
+    fn apply(&mut self, mv: Move) {
+        self.play(mv)
+    }
+
+    fn undo(&mut self, mv: Move) {
+        self.undo_move(mv)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Center squares first, then corners, then the rest. Across layers,
+    // squares on the most win lines first, the corners and centers of the
+    // Qubic cube
+    fn move_priority(&self, mv: Move) -> i32 {
+        if self.layers > 1 {
+            let square = self.square(mv);
+            return self
+                .wins
+                .iter()
+                .filter(|mask| mask.contains(square))
+                .count() as i32;
+        }
+        let last_row = self.height - 1;
+        let last_col = self.width - 1;
+        let center_row = mv.row == last_row / 2 || mv.row == last_row.div_ceil(2);
+        let center_col = mv.col == last_col / 2 || mv.col == last_col.div_ceil(2);
+        if center_row && center_col {
+            2
+        } else if (mv.row == 0 || mv.row == last_row) && (mv.col == 0 || mv.col == last_col) {
+            1
+        } else {
+            0
+        }
+    }
+
+    // Under misère rules, lines lose and aren't worth extending searches
+    fn forcing_moves(&self) -> Vec<Move> {
+        if self.is_over() || self.misere {
+            return vec![];
+        }
+        let wins = self.winning_squares(self.turn);
+        let squares = if !wins.is_empty() {
+            wins
+        } else {
+            self.winning_squares(1 - self.turn)
+        };
+        squares
+            .ones()
+            .map(|square| self.square_move(square))
+            .collect()
+    }
+
+    // Open lines of each side, see `Game::threats`, none under misère
+    // rules where they lose
+    fn threat_scores(&self) -> [Score; 2] {
+        if self.misere {
+            return [0, 0];
+        }
+        [self.threats(self.turn), self.threats(1 - self.turn)]
+    }
+
+    // A stone never hurts its owner, passing is never better than moving.
+    // Under misère rules it may, so passing isn't allowed
+    fn pass(&mut self) -> bool {
+        if self.misere {
+            return false;
+        }
+        self.turn = 1 - self.turn;
+        true
+    }
+
+    fn undo_pass(&mut self) {
+        self.turn = 1 - self.turn;
+    }
+
+    fn move_index(&self, mv: Move) -> Option<usize> {
+        Some(self.square(mv) as usize)
+    }
+
+    // Keyed by canonical orientation so symmetric positions share entries
+    fn hash_key(&self) -> Option<u64> {
+        let [x, o] = self.canonical();
+        let words = x.words().into_iter().chain(o.words());
+        let mut key = words.fold(mix(0, u64::from(self.turn)), mix);
+        // Blocked squares only when there are some, leaving other keys as
+        // they were
+        if !self.blocked.is_empty() {
+            key = self.blocked.words().into_iter().fold(key, mix);
+        }
+        if self.lines.is_some() {
+            key = mix(key, self.lines_key);
+        }
+        Some(key)
+    }
+
+    fn evaluate(&self) -> Score {
+        if self.has_line() {
+            if self.misere {
+                WIN
+            } else {
+                LOSS
+            }
+        } else if self.is_full() {
+            DRAW
+        } else {
+            self.heuristic()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_thin_board() {
+        assert_eq!(
+            Game::with_dimensions(20, 2, 20).err(),
+            Some(BoardError::InvalidWinLength(20))
+        );
+        let mut game = Game::with_dimensions(20, 2, MAX_SIZE).unwrap();
+        for col in 0..MAX_SIZE - 1 {
+            game.play_moves(&[Move { row: 0, col }, Move { row: 1, col }])
+                .unwrap();
+        }
+        game.heuristic();
+    }
+
+    #[test]
+    fn handicap_checks_player_and_lines() {
+        let squares = |names: &[&str]| -> Vec<Move> {
+            names.iter().map(|name| name.parse().unwrap()).collect()
+        };
+        assert_eq!(
+            Game::new().with_handicap(2, &squares(&["b2"])).err(),
+            Some(MoveError::InvalidPlayer)
+        );
+        for player in 0..2 {
+            assert_eq!(
+                Game::new()
+                    .with_handicap(player, &squares(&["a1", "b1", "c1"]))
+                    .err(),
+                Some(MoveError::GameOver)
+            );
+        }
+        let game = Game::new()
+            .with_handicap(0, &squares(&["a1", "b1"]))
+            .unwrap();
+        assert!(!game.is_over());
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::search::{GameState, Reductions, Score, Search, SearchOptions};
+use crate::tss::ThreatSearch;
+use crate::{Bitboard, BoardError, Game, GameStatus, Move, MoveError, PlayerId};
+
+// Stones in a row winning the game
+const WIN_LENGTH: u8 = 5;
+
+// Farthest distance from a stone, along rows, columns or diagonals, of
+// the squares searches consider
+const REACH: i32 = 2;
+
+/// Freestyle gomoku: players take turns on a 15×15 board and five stones
+/// or more in a row, column or diagonal win.
+///
+/// The board is a [`Game`] of the same size, sharing its win masks,
+/// symmetries and evaluation. With 225 squares, searches only consider
+/// the empty squares within two steps of a stone, the center on an empty
+/// board, while any empty square may be played.
+#[derive(Clone, Debug)]
+pub struct Gomoku {
+    board: Game,
+    // Indices of the win masks through each square
+    lines: Arc<[Vec<usize>]>,
+}
+
+impl Default for Gomoku {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Gomoku {
+    /// Create an empty 15×15 board with the first player to move.
+    pub fn new() -> Self {
+        Self::with_size(15).expect("15×15 is a valid size")
+    }
+
+    /// Create an empty `size`×`size` board won by five in a row.
+    pub fn with_size(size: u8) -> Result<Self, BoardError> {
+        let board = Game::with_win_length(size, WIN_LENGTH)?;
+        let lines = (0..board.squares())
+            .map(|square| {
+                (0..board.wins().len())
+                    .filter(|&i| board.wins()[i].contains(square))
+                    .collect()
+            })
+            .collect();
+        Ok(Gomoku { board, lines })
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.board.turn()
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[Move] {
+        self.board.history()
+    }
+
+    /// Stones on the board, as a [`Game`] of the same size.
+    pub fn board(&self) -> &Game {
+        &self.board
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.board.is_over()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.board.status()
+    }
+
+    /// Check that a move can be played in the current position.
+    pub fn check_move(&self, mv: Move) -> Result<(), MoveError> {
+        self.board.check_move(mv)
+    }
+
+    pub fn make_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        self.board.make_move(mv)
+    }
+
+    /// Best move found within a time budget: the start of a win by
+    /// continuous threats when [`ThreatSearch`] finds one, else the
+    /// result of iterative deepening with late move reductions and the
+    /// history heuristic.
+    pub fn best_move_within(&self, budget: Duration) -> Option<Move> {
+        if let Some(line) = ThreatSearch::default().find_win(&self.board) {
+            return line.first().copied();
+        }
+        let options = SearchOptions {
+            history: true,
+            reductions: Some(Reductions::default()),
+            ..SearchOptions::default()
+        };
+        Search::with_options(options)
+            .iterative_deepening(&mut self.clone(), u8::MAX, budget)
+            .best()
+    }
+
+    // Empty squares within reach of a stone
+    fn candidates(&self) -> Bitboard {
+        let [x, o] = self.board.players();
+        let stones = x | o;
+        self.board.near(stones, REACH) & !stones
+    }
+}
+
+// One row per line, X for the first player, O for the second, . when empty
+impl fmt::Display for Gomoku {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.board)
+    }
+}
+
+impl GameState for Gomoku {
+    type Move = Move;
+
+    fn legal_moves(&self) -> Vec<Move> {
+        if self.is_over() {
+            return vec![];
+        }
+        if self.board.history().is_empty() {
+            let center = self.board.width() / 2;
+            return vec![Move::new(center, center)];
+        }
+        self.candidates()
+            .ones()
+            .map(|square| self.board.square_move(square))
+            .collect()
+    }
+
+    fn apply(&mut self, mv: Move) {
+        self.board.apply(mv)
+    }
+
+    fn undo(&mut self, mv: Move) {
+        self.board.undo(mv)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Squares extending the longest open lines of either side first,
+    // making threats or blocking them
+    fn move_priority(&self, mv: Move) -> i32 {
+        let [x, o] = self.board.players();
+        self.lines[self.board.square(mv) as usize]
+            .iter()
+            .map(|&i| {
+                let mask = self.board.wins()[i];
+                match ((x & mask).count_ones(), (o & mask).count_ones()) {
+                    (stones, 0) | (0, stones) => (stones * stones) as i32,
+                    _ => 0,
+                }
+            })
+            .sum()
+    }
+
+    fn move_index(&self, mv: Move) -> Option<usize> {
+        self.board.move_index(mv)
+    }
+
+    fn hash_key(&self) -> Option<u64> {
+        self.board.hash_key()
+    }
+
+    fn forcing_moves(&self) -> Vec<Move> {
+        self.board.forcing_moves()
+    }
+
+    fn threat_scores(&self) -> [Score; 2] {
+        self.board.threat_scores()
+    }
+
+    fn pass(&mut self) -> bool {
+        self.board.pass()
+    }
+
+    fn undo_pass(&mut self) {
+        self.board.undo_pass()
+    }
+
+    fn evaluate(&self) -> Score {
+        self.board.evaluate()
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::eval::THREAT_SCORE;
+use crate::search::{GameState, Score, LOSS};
+use crate::tt::mix;
+use crate::{Bitboard, BoardError, GameStatus, Move, MoveError, PlayerId, MAX_SIZE, MIN_SIZE};
+
+// Steps to the six neighbours of a cell of the rhombus
+const NEIGHBOURS: [(i32, i32); 6] = [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)];
+
+// Connected groups of cells with undoable merges: union by size without
+// path compression, so that each merge is undone by resetting one parent
+#[derive(Clone, Debug)]
+struct Groups {
+    parent: Vec<u16>,
+    size: Vec<u16>,
+    // Roots merged under another one, latest last
+    merges: Vec<u16>,
+}
+
+impl Groups {
+    fn new(nodes: usize) -> Self {
+        Groups {
+            parent: (0..nodes as u16).collect(),
+            size: vec![1; nodes],
+            merges: vec![],
+        }
+    }
+
+    fn find(&self, mut node: u16) -> u16 {
+        while self.parent[usize::from(node)] != node {
+            node = self.parent[usize::from(node)];
+        }
+        node
+    }
+
+    // Merge the groups of two nodes, returning whether they were apart
+    fn union(&mut self, a: u16, b: u16) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (small, large) = if self.size[usize::from(a)] < self.size[usize::from(b)] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        self.parent[usize::from(small)] = large;
+        self.size[usize::from(large)] += self.size[usize::from(small)];
+        self.merges.push(small);
+        true
+    }
+
+    // Undo the last merge
+    fn split(&mut self) {
+        let small = self.merges.pop().expect("A merge to undo");
+        let large = self.parent[usize::from(small)];
+        self.parent[usize::from(small)] = small;
+        self.size[usize::from(large)] -= self.size[usize::from(small)];
+    }
+}
+
+/// Hex on a rhombus of hexagonal cells: the first player connects the top
+/// and bottom edges with a chain of its stones, the second the left and
+/// right ones. The board never fills up without a winner.
+///
+/// Groups of stones are tracked with a union-find instead of line masks,
+/// each edge being one more node, so a player has won once its two edges
+/// are in the same group. Merges are undone in reverse order when moves
+/// are, keeping searches incremental. Under [`Pie`](crate::pie::Pie),
+/// Hex is played with the usual swap rule.
+#[derive(Clone, Debug)]
+pub struct Hex {
+    size: u8,
+    stones: [Bitboard; 2],
+    turn: PlayerId,
+    history: Vec<Move>,
+    groups: Groups,
+    // Merges made by each move, to undo them
+    merges: Vec<u8>,
+}
+
+impl Default for Hex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hex {
+    /// Create an empty 11×11 board with the first player to move.
+    pub fn new() -> Self {
+        Self::with_size(11).expect("11 is a valid size")
+    }
+
+    /// Create an empty `size`×`size` board.
+    pub fn with_size(size: u8) -> Result<Self, BoardError> {
+        if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+            return Err(BoardError::InvalidSize {
+                width: size,
+                height: size,
+            });
+        }
+        let cells = usize::from(size) * usize::from(size);
+        Ok(Hex {
+            size,
+            stones: [Bitboard::EMPTY; 2],
+            turn: 0,
+            history: vec![],
+            groups: Groups::new(cells + 4),
+            merges: vec![],
+        })
+    }
+
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Stones of both players on the board.
+    pub fn players(&self) -> [Bitboard; 2] {
+        self.stones
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    fn cell(&self, mv: Move) -> u32 {
+        u32::from(mv.row) * u32::from(self.size) + u32::from(mv.col)
+    }
+
+    fn cell_move(&self, cell: u32) -> Move {
+        let size = u32::from(self.size);
+        Move::new((cell / size) as u8, (cell % size) as u8)
+    }
+
+    // Cells next to a cell
+    fn neighbours(&self, cell: u32) -> impl Iterator<Item = u32> {
+        let size = i32::from(self.size);
+        let (row, col) = (cell as i32 / size, cell as i32 % size);
+        NEIGHBOURS.into_iter().filter_map(move |(dr, dc)| {
+            let (r, c) = (row + dr, col + dc);
+            ((0..size).contains(&r) && (0..size).contains(&c)).then(|| (r * size + c) as u32)
+        })
+    }
+
+    // Union-find nodes of the two edges of a player, after the cells
+    fn edges(&self, player: PlayerId) -> [u16; 2] {
+        let base = u16::from(self.size) * u16::from(self.size) + 2 * u16::from(player);
+        [base, base + 1]
+    }
+
+    // Edges of a player a cell touches
+    fn touched_edges(&self, mv: Move, player: PlayerId) -> impl Iterator<Item = u16> {
+        let [first, second] = self.edges(player);
+        let coord = if player == 0 { mv.row } else { mv.col };
+        let last = self.size - 1;
+        [(coord == 0, first), (coord == last, second)]
+            .into_iter()
+            .filter_map(|(touches, edge)| touches.then_some(edge))
+    }
+
+    /// Player whose edges are connected, if any.
+    pub fn winner(&self) -> Option<PlayerId> {
+        (0..2).find(|&player| {
+            let [first, second] = self.edges(player);
+            self.groups.find(first) == self.groups.find(second)
+        })
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.winner().is_some()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.winner()
+            .map_or(GameStatus::InProgress, GameStatus::Won)
+    }
+
+    pub fn check_move(&self, mv: Move) -> Result<(), MoveError> {
+        if mv.row >= self.size || mv.col >= self.size {
+            Err(MoveError::OutOfBounds)
+        } else if (self.stones[0] | self.stones[1]).contains(self.cell(mv)) {
+            Err(MoveError::Occupied)
+        } else if self.is_over() {
+            Err(MoveError::GameOver)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+
+    // Fewest empty cells a player still needs to connect its edges,
+    // through its own stones for free and never through the opponent's,
+    // by a breadth-first search with zero cost steps in front
+    fn distance(&self, player: PlayerId) -> Option<u32> {
+        let (own, theirs) = (
+            self.stones[usize::from(player)],
+            self.stones[usize::from(1 - player)],
+        );
+        let cells = u32::from(self.size) * u32::from(self.size);
+        let cost = |cell: u32| u32::from(!own.contains(cell));
+        // Distance from the first edge of the player along its direction
+        let coord = |cell: u32| {
+            let mv = self.cell_move(cell);
+            if player == 0 {
+                mv.row
+            } else {
+                mv.col
+            }
+        };
+        let mut distances = vec![u32::MAX; cells as usize];
+        let mut queue = VecDeque::new();
+        for cell in 0..cells {
+            if coord(cell) == 0 && !theirs.contains(cell) {
+                distances[cell as usize] = cost(cell);
+                queue.push_back(cell);
+            }
+        }
+        let mut best = None;
+        while let Some(cell) = queue.pop_front() {
+            let distance = distances[cell as usize];
+            if coord(cell) == self.size - 1 {
+                best = Some(best.map_or(distance, |best: u32| best.min(distance)));
+            }
+            for next in self.neighbours(cell) {
+                let step = distance + cost(next);
+                if !theirs.contains(next) && step < distances[next as usize] {
+                    distances[next as usize] = step;
+                    if cost(next) == 0 {
+                        queue.push_front(next);
+                    } else {
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+// One row per line, shifted right as they go down the rhombus, X for the
+// first player, O for the second, . when empty
+impl fmt::Display for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.size {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            let cells: Vec<_> = (0..self.size)
+                .map(|col| {
+                    let cell = self.cell(Move::new(row, col));
+                    if self.stones[0].contains(cell) {
+                        "X"
+                    } else if self.stones[1].contains(cell) {
+                        "O"
+                    } else {
+                        "."
+                    }
+                })
+                .collect();
+            write!(f, "{}{}", " ".repeat(usize::from(row)), cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Hex {
+    type Move = Move;
+
+    fn legal_moves(&self) -> Vec<Move> {
+        if self.is_over() {
+            return vec![];
+        }
+        let cells = u32::from(self.size) * u32::from(self.size);
+        (Bitboard::below(cells) & !(self.stones[0] | self.stones[1]))
+            .ones()
+            .map(|cell| self.cell_move(cell))
+            .collect()
+    }
+
+    fn apply(&mut self, mv: Move) {
+        let cell = self.cell(mv);
+        let player = self.turn;
+        self.stones[usize::from(player)].insert(cell);
+        let node = cell as u16;
+        let own = self.stones[usize::from(player)];
+        let mut merges = 0;
+        let friends: Vec<u16> = self
+            .neighbours(cell)
+            .filter(|&next| own.contains(next))
+            .map(|next| next as u16)
+            .chain(self.touched_edges(mv, player))
+            .collect();
+        for friend in friends {
+            merges += u8::from(self.groups.union(node, friend));
+        }
+        self.merges.push(merges);
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    fn undo(&mut self, mv: Move) {
+        for _ in 0..self.merges.pop().expect("A move to undo") {
+            self.groups.split();
+        }
+        self.turn = 1 - self.turn;
+        self.stones[usize::from(self.turn)] ^= Bitboard::bit(self.cell(mv));
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Central cells first
+    fn move_priority(&self, mv: Move) -> i32 {
+        let center = i32::from(self.size - 1);
+        -((2 * i32::from(mv.row) - center).abs() + (2 * i32::from(mv.col) - center).abs())
+    }
+
+    fn move_index(&self, mv: Move) -> Option<usize> {
+        Some(self.cell(mv) as usize)
+    }
+
+    fn hash_key(&self) -> Option<u64> {
+        let words = self.stones[0]
+            .words()
+            .into_iter()
+            .chain(self.stones[1].words());
+        Some(words.fold(mix(0, u64::from(self.turn)), mix))
+    }
+
+    // Cells the opponent still needs to connect less those the side to
+    // move needs, the last mover having won once the game is over
+    fn evaluate(&self) -> Score {
+        if self.is_over() {
+            return LOSS;
+        }
+        let cells = u32::from(self.size) * u32::from(self.size);
+        let distance = |player| self.distance(player).unwrap_or(cells) as Score;
+        (distance(1 - self.turn) - distance(self.turn)) * THREAT_SCORE
+    }
+}
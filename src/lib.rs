@@ -0,0 +1,56 @@
+//! Tic-tac-toe engine based on negamax with alpha/beta pruning.
+//!
+//! [`Game`] holds the board as bitboards and exposes move generation,
+//! win detection and the search used by the `tictactoe-rust` binary. The
+//! search itself lives in [`search`] and works on any type implementing
+//! [`GameState`], as does the Monte Carlo tree search of [`mcts`]. Move
+//! selection strategies implement [`Player`].
+
+pub mod achi;
+pub mod alphazero;
+mod bitboard;
+pub mod book;
+pub mod clock;
+pub mod connect;
+pub mod connect_four;
+pub mod eval;
+pub mod expectimax;
+pub mod features;
+mod game;
+pub mod gomoku;
+pub mod hex;
+pub mod mcts;
+mod moves;
+pub mod multiplayer;
+pub mod nim;
+pub mod notakto;
+#[cfg(feature = "onnx")]
+pub mod onnx;
+pub mod order_chaos;
+pub mod othello;
+pub mod pie;
+pub mod player;
+pub mod pns;
+pub mod qlearning;
+pub mod quantum;
+pub mod reinforce;
+pub mod search;
+pub mod square;
+pub mod tablebase;
+pub mod tss;
+pub mod tt;
+pub mod tune;
+pub mod ultimate;
+pub mod wild;
+
+pub use bitboard::Bitboard;
+pub use eval::Evaluator;
+pub use game::{
+    BoardError, BoardView, Game, GameStatus, MoveError, PlayerId, MAX_SIZE, MAX_SQUARES, MIN_SIZE,
+};
+pub use moves::{Move, ParseMoveError};
+pub use player::Player;
+pub use search::{
+    describe_score, is_decisive, Driver, GameState, Outcome, Parallel, Reductions, RootNoise,
+    Score, Search, SearchLimits, SearchOptions, SearchResult, Solution, DRAW, LOSS, WIN,
+};
@@ -1,197 +1,796 @@
-use rand::seq::SliceRandom;
-use rand::thread_rng;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, IsTerminal, StdinLock};
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
-struct Game {
-    players: [u32; 2],
-    turn: u8,
+use clap::error::ErrorKind;
+use clap::{Args, ColorChoice, CommandFactory, Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use tictactoe_rust::clock::{self, TimeControl};
+use tictactoe_rust::connect::{Connect, ConnectRules};
+use tictactoe_rust::pie::{self, Pie};
+use tictactoe_rust::player::{
+    Action, Difficulty, HumanPlayer, MctsPlayer, NegamaxPlayer, Personality, RandomPlayer,
+};
+use tictactoe_rust::{
+    describe_score, BoardView, Game, GameState, GameStatus, Move, Player, PlayerId, Search,
+};
+
+// Time the engine takes to evaluate positions for `play --eval`
+const EVAL_TIME: Duration = Duration::from_millis(200);
+
+/// Play, replay, simulate, analyze, solve and benchmark tic-tac-toe games.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Seed of the players and random starts, for reproducible runs
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+    /// Draw boards in color, the last move and winning line highlighted,
+    /// by default on terminals unless `NO_COLOR` is set
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Play one game, a human against the engine by default
+    Play(PlayArgs),
+    /// Play a match between two players and count its results
+    Simulate(SimulateArgs),
+    /// Score every legal move of a position
+    Analyze(AnalyzeArgs),
+    /// Find the exact outcome of a position and a move achieving it
+    Solve(PositionArgs),
+    /// Time searches of a position at increasing depths
+    Bench(BenchArgs),
+    /// Step through a saved game move by move
+    #[cfg(feature = "serde")]
+    Replay(ReplayArgs),
+}
+
+#[derive(Args)]
+struct BoardArgs {
+    /// Side of the square board
+    #[arg(long, default_value_t = 3)]
     size: u8,
-    wins: Vec<u32>,
-    total_evaluations: u32,
-}
-
-impl Game {
-    fn new() -> Self {
-        let mut g = Game {
-            players: [0, 0],
-            turn: 0,
-            size: 3,
-            wins: vec![],
-            total_evaluations: 0,
+    /// Stones in a row winning the game, the side of the board by default
+    #[arg(long)]
+    win_length: Option<u8>,
+}
+
+impl BoardArgs {
+    fn game(&self) -> Game {
+        let win_length = self.win_length.unwrap_or(self.size);
+        Game::with_win_length(self.size, win_length).unwrap_or_else(|e| fail(e))
+    }
+}
+
+#[derive(Args)]
+struct PositionArgs {
+    #[command(flatten)]
+    board: BoardArgs,
+    /// Moves played from the empty board, such as `b2 a1`
+    moves: Vec<Move>,
+}
+
+impl PositionArgs {
+    fn game(&self) -> Game {
+        let mut game = self.board.game();
+        game.play_moves(&self.moves).unwrap_or_else(|e| fail(e));
+        game
+    }
+}
+
+#[derive(Args)]
+struct PlayArgs {
+    #[command(flatten)]
+    board: BoardArgs,
+    /// Player of the first side
+    #[arg(long, default_value = "human")]
+    first: PlayerSpec,
+    /// Player of the second side
+    #[arg(long, default_value = "perfect")]
+    second: PlayerSpec,
+    /// Start from a random position with this many moves of each player
+    #[arg(long, value_name = "PAIRS")]
+    random_start: Option<u8>,
+    /// Strength of the engine, easy, medium, hard or perfect, replacing the
+    /// players of the sides not played by humans
+    #[arg(long, value_name = "LEVEL")]
+    difficulty: Option<Difficulty>,
+    #[command(flatten)]
+    engine: EngineArgs,
+    /// Print the engine's evaluation of the position before each move, such
+    /// as for two humans playing on the same terminal
+    #[arg(long)]
+    eval: bool,
+    /// Play Connect(m,n,k,p,q) between searches of a tenth of a second per
+    /// turn instead, from `m,n,k,p,q` or with the `serde` feature a TOML
+    /// file of the rules
+    #[arg(long, value_name = "RULES", value_parser = rules)]
+    connect: Option<ConnectRules>,
+    /// Resume a game saved by typing `:save FILE`, with its players and
+    /// settings
+    #[cfg(feature = "serde")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "size", "win_length", "first", "second", "random_start", "difficulty",
+            "blunder_rate", "personality", "eval", "connect",
+        ],
+    )]
+    resume: Option<std::path::PathBuf>,
+}
+
+#[derive(Args)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct EngineArgs {
+    /// Chance of the engine sides playing their second best move instead
+    /// of the best one, overriding that of their difficulty
+    #[arg(long, value_name = "P", value_parser = probability)]
+    blunder_rate: Option<f64>,
+    /// Style of the engine sides, aggressive, defensive or tricky, picking
+    /// among moves about as good as the best one
+    #[arg(long, value_name = "STYLE")]
+    personality: Option<Personality>,
+}
+
+#[derive(Args)]
+struct SimulateArgs {
+    #[command(flatten)]
+    board: BoardArgs,
+    /// Player of the first side
+    #[arg(long, default_value = "perfect")]
+    first: PlayerSpec,
+    /// Player of the second side
+    #[arg(long, default_value = "perfect")]
+    second: PlayerSpec,
+    /// Number of games
+    #[arg(long, default_value_t = 100)]
+    games: u32,
+    #[command(flatten)]
+    engine: EngineArgs,
+    /// Time on each player's clock at the start of a game, in milliseconds
+    #[arg(long, default_value_t = 1000, value_name = "MS")]
+    clock: u64,
+    /// Time added to a player's clock after each move, in milliseconds
+    #[arg(long, default_value_t = 100, value_name = "MS")]
+    increment: u64,
+    /// Play under the pie rule, results then counting by player whichever
+    /// side they ended up with
+    #[arg(long)]
+    pie: bool,
+    /// Start every game from a random position with this many moves of each
+    /// player, as the empty board is always drawn
+    #[arg(long, value_name = "PAIRS")]
+    random_start: Option<u8>,
+}
+
+#[derive(Args)]
+struct AnalyzeArgs {
+    #[command(flatten)]
+    position: PositionArgs,
+    /// Plies searched, to the end of the game by default
+    #[arg(long, default_value_t = u8::MAX)]
+    depth: u8,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    #[command(flatten)]
+    position: PositionArgs,
+    /// Deepest search, as many plies as there are empty squares by default
+    #[arg(long)]
+    depth: Option<u8>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Args)]
+struct ReplayArgs {
+    /// Game saved by typing `:save FILE` during `play`
+    file: std::path::PathBuf,
+    /// Print the engine's evaluation of each position
+    #[arg(long)]
+    eval: bool,
+}
+
+/// Kind of player of a side: `human`, `random`, a difficulty level such as
+/// `easy`, `negamax:DEPTH`, `timed:MS` for a search of MS milliseconds per
+/// move, or `mcts:PLAYOUTS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+enum PlayerSpec {
+    Human,
+    Random,
+    Level(Difficulty),
+    Negamax(u8),
+    Timed(u64),
+    Mcts(u32),
+}
+
+impl FromStr for PlayerSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, limit) = match s.split_once(':') {
+            Some((kind, limit)) => (kind, Some(limit)),
+            None => (s, None),
         };
-        g.init_win_mask();
-        g
-    }
-    // Generate masks for win conditions
-    fn init_win_mask(&mut self) {
-        // Horizontals
-        let mut mask: u32 = (1 << self.size) - 1;
-        for _ in 0..self.size {
-            self.wins.push(mask);
-            mask <<= self.size;
-        }
-        // Verticals
-        let mut mask: u32 = 0;
-        for _ in 0..self.size {
-            mask = (mask << self.size) | 1
-        }
-        for _ in 0..self.size {
-            self.wins.push(mask);
-            mask <<= 1
-        }
-        // Diagonals
-        let mut mask: u32 = 0;
-        for _ in 0..self.size {
-            mask = (mask << (self.size + 1)) | 1
-        }
-        self.wins.push(mask);
-        let mut mask: u32 = 0;
-        for _ in 0..self.size {
-            mask = (mask << (self.size - 1)) | 1;
-        }
-        self.wins.push(mask << (self.size - 1))
-    }
-
-    // Make a move and changes player
-    fn make_move(&mut self, square: u32) {
-        let mask = 1 << square;
-        self.players[self.turn as usize] ^= mask;
-        self.turn = 1 - self.turn;
-    }
-
-    // Reverse a move and changes player
-    fn undo_move(&mut self, square: u32) {
-        let mask = 1 << square;
-        self.turn = 1 - self.turn;
-        self.players[self.turn as usize] ^= mask
-    }
-
-    // Compute possible next moves
-    fn moves(&self) -> Vec<u32> {
-        let mut moves = vec![];
-        let board = self.players[0] | self.players[1];
-        for square in 0..self.size.pow(2) {
-            if board & (1 << square) == 0 {
-                moves.push(square.into())
-            }
+        let number = |default: u64| -> Result<u64, String> {
+            limit.map_or(Ok(default), |limit| {
+                limit
+                    .parse()
+                    .map_err(|_| format!("invalid limit {:?} for {}", limit, kind))
+            })
+        };
+        if let (Ok(level), None) = (kind.parse(), limit) {
+            return Ok(PlayerSpec::Level(level));
+        }
+        match kind {
+            "human" if limit.is_none() => Ok(PlayerSpec::Human),
+            "random" if limit.is_none() => Ok(PlayerSpec::Random),
+            "negamax" => Ok(PlayerSpec::Negamax(
+                number(u64::from(u8::MAX))?.min(u64::from(u8::MAX)) as u8,
+            )),
+            "timed" => Ok(PlayerSpec::Timed(number(100)?)),
+            "mcts" => Ok(PlayerSpec::Mcts(
+                number(1000)?.min(u64::from(u32::MAX)) as u32
+            )),
+            _ => Err(format!(
+                "unknown player {:?}, expected human, random, easy, medium, \
+                 hard, perfect, negamax:DEPTH, timed:MS or mcts:PLAYOUTS",
+                s
+            )),
         }
-        moves
     }
+}
 
-    // Check if game was won by any of the players
-    fn is_won(&self) -> bool {
-        let x = self.players[(1 - self.turn) as usize];
-        self.wins.iter().any(|mask| x & mask == *mask)
+// Printed as parsed
+impl fmt::Display for PlayerSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerSpec::Human => write!(f, "human"),
+            PlayerSpec::Random => write!(f, "random"),
+            PlayerSpec::Level(level) => write!(f, "{}", level),
+            PlayerSpec::Negamax(depth) => write!(f, "negamax:{}", depth),
+            PlayerSpec::Timed(ms) => write!(f, "timed:{}", ms),
+            PlayerSpec::Mcts(playouts) => write!(f, "mcts:{}", playouts),
+        }
     }
+}
 
-    // Check if no more move is possible
-    fn is_full(&self) -> bool {
-        let full = (1 << (self.size * self.size)) - 1;
-        self.players[0] | self.players[1] == full
+impl TryFrom<String> for PlayerSpec {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
     }
+}
 
-    // Check game over, either by full, win or both
-    fn is_over(&self) -> bool {
-        self.is_full() | self.is_won()
+impl From<PlayerSpec> for String {
+    fn from(spec: PlayerSpec) -> Self {
+        spec.to_string()
     }
+}
+
+// Human reading the standard input, shared by both sides when two humans
+// play at the same terminal as it can only be locked once
+type Human = Rc<RefCell<HumanPlayer<StdinLock<'static>>>>;
 
-    // Compute number of free lines and occupancy for player
-    fn threats(&self, turn: u8) -> f32 {
-        let player = self.players[turn as usize];
-        let opponent = self.players[(1 - turn) as usize];
-        let threats: u32 = self
-            .wins
-            .iter()
-            .filter(|mask| opponent & **mask == 0)
-            .map(|mask| (player & mask).count_ones().pow(2))
-            .sum();
-        threats as f32
+// Player of a side, counting the positions it visited
+enum Side {
+    Human(Human),
+    Random(RandomPlayer),
+    Negamax(NegamaxPlayer),
+    Mcts(MctsPlayer),
+}
+
+impl Side {
+    fn new(spec: PlayerSpec, seed: Option<u64>, human: &mut Option<Human>) -> Self {
+        let negamax = |player: NegamaxPlayer| match seed {
+            Some(seed) => Side::Negamax(player.seeded(seed)),
+            None => Side::Negamax(player),
+        };
+        match spec {
+            PlayerSpec::Human => Side::Human(Rc::clone(
+                human.get_or_insert_with(|| Rc::new(RefCell::new(HumanPlayer::stdin()))),
+            )),
+            PlayerSpec::Random => {
+                Side::Random(seed.map_or_else(RandomPlayer::new, RandomPlayer::seeded))
+            }
+            PlayerSpec::Level(level) => negamax(level.player()),
+            PlayerSpec::Negamax(depth) => negamax(NegamaxPlayer::new(depth)),
+            PlayerSpec::Timed(ms) => negamax(NegamaxPlayer::timed(Duration::from_millis(ms))),
+            PlayerSpec::Mcts(playouts) => {
+                let player = MctsPlayer::new(playouts);
+                Side::Mcts(match seed {
+                    Some(seed) => player.seeded(seed),
+                    None => player,
+                })
+            }
+        }
     }
 
-    // Score heuristic based on both sides threats
-    fn heuristic(&self) -> f32 {
-        self.threats(self.turn) - self.threats(1 - self.turn)
+    // Blunder at the rate and play in the style given, if searching with
+    // negamax
+    fn set_engine(&mut self, engine: &EngineArgs) {
+        if let Side::Negamax(player) = self {
+            player.blunder_rate = engine.blunder_rate.unwrap_or(player.blunder_rate);
+            player.personality = engine.personality.or(player.personality);
+        }
     }
 
-    // Return best move according to minimax
-    fn best_move(&mut self, alpha: f32, beta: f32, depth: u8) -> u32 {
-        self.negamax(alpha, beta, depth).0
+    // Positions searched or playouts run over all moves so far
+    fn evaluations(&self) -> u32 {
+        match self {
+            Side::Negamax(player) => player.evaluations,
+            Side::Mcts(player) => player.playouts,
+            Side::Human(_) | Side::Random(_) => 0,
+        }
+    }
+}
+
+impl<G: GameState + Clone> Player<G> for Side
+where
+    HumanPlayer<StdinLock<'static>>: Player<G>,
+{
+    fn choose_move(&mut self, game: &G) -> G::Move {
+        match self {
+            Side::Human(player) => player.borrow_mut().choose_move(game),
+            Side::Random(player) => player.choose_move(game),
+            Side::Negamax(player) => player.choose_move(game),
+            Side::Mcts(player) => player.choose_move(game),
+        }
     }
 
-    // Play randomly
-    fn random_move(&mut self) -> u32 {
-        let mut rng = thread_rng();
-        self.moves()
-            .choose(&mut rng)
-            .expect("Can't chose from 0 moves")
-            .clone()
+    fn choose_move_timed(&mut self, game: &G, remaining: Duration, increment: Duration) -> G::Move {
+        match self {
+            Side::Human(player) => player
+                .borrow_mut()
+                .choose_move_timed(game, remaining, increment),
+            Side::Random(player) => player.choose_move_timed(game, remaining, increment),
+            Side::Negamax(player) => player.choose_move_timed(game, remaining, increment),
+            Side::Mcts(player) => player.choose_move_timed(game, remaining, increment),
+        }
     }
 
-    // Evaluate positions according to the negamax algorithm
-    fn negamax(&mut self, mut alpha: f32, beta: f32, depth: u8) -> (u32, f32) {
-        if self.is_won() {
-            return (0u32, -f32::INFINITY);
-        } else if self.is_full() {
-            return (0u32, 0.0f32);
-        } else if depth == 0 {
-            return (0u32, self.heuristic());
+    fn try_choose_move(&mut self, game: &G) -> Option<G::Move> {
+        match self {
+            Side::Human(player) => player.borrow_mut().try_choose_move(game),
+            Side::Random(player) => player.try_choose_move(game),
+            Side::Negamax(player) => player.try_choose_move(game),
+            Side::Mcts(player) => player.try_choose_move(game),
         }
-        let mut best_moves = vec![];
+    }
+}
+
+// Players of both sides, the second one seeded after the first, engines
+// set up as `engine` tells
+fn sides(
+    first: PlayerSpec,
+    second: PlayerSpec,
+    seed: Option<u64>,
+    engine: &EngineArgs,
+) -> [Side; 2] {
+    let mut human = None;
+    let mut sides = [
+        Side::new(first, seed, &mut human),
+        Side::new(second, seed.map(|seed| seed.wrapping_add(1)), &mut human),
+    ];
+    sides.iter_mut().for_each(|side| side.set_engine(engine));
+    sides
+}
+
+// Parse a probability between 0 and 1
+fn probability(arg: &str) -> Result<f64, String> {
+    match arg.parse() {
+        Ok(p) if (0.0..=1.0).contains(&p) => Ok(p),
+        _ => Err(format!(
+            "expected a probability between 0 and 1, got {:?}",
+            arg
+        )),
+    }
+}
+
+// Exit with a usage error
+fn fail(error: impl fmt::Display) -> ! {
+    Cli::command().error(ErrorKind::InvalidValue, error).exit()
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let colored = match cli.color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    };
+    match cli.command {
+        Command::Play(args) => play(args, cli.seed, colored),
+        Command::Simulate(args) => simulate(args, cli.seed),
+        Command::Analyze(args) => analyze(args, colored),
+        Command::Solve(args) => solve(args, colored),
+        Command::Bench(args) => bench(args),
+        #[cfg(feature = "serde")]
+        Command::Replay(args) => replay(args, colored),
+    }
+}
+
+// Symbol of the stones of a player
+fn symbol(player: PlayerId) -> char {
+    if player == 0 {
+        'X'
+    } else {
+        'O'
+    }
+}
+
+// Board of a game labelled with coordinates, in color if asked
+fn board(game: &Game, colored: bool) -> BoardView<'_> {
+    let board = game.labelled();
+    if colored {
+        board.colored()
+    } else {
+        board
+    }
+}
+
+fn describe(status: GameStatus) -> String {
+    match status {
+        GameStatus::Won(player) => format!("{} wins", symbol(player)),
+        GameStatus::Draw => "Draw".to_string(),
+        GameStatus::InProgress => "Game in progress".to_string(),
+    }
+}
+
+// Interactive game with its settings, as saved by `:save` and restored by
+// `play --resume`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Session {
+    game: Game,
+    // Moves of the random start, that undo doesn't take back
+    start: usize,
+    players: [PlayerSpec; 2],
+    engine: EngineArgs,
+    eval: bool,
+}
+
+#[cfg(feature = "serde")]
+fn save(session: &Session, path: &Path) -> io::Result<()> {
+    let text = serde_json::to_string_pretty(session)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    std::fs::write(path, text)
+}
+
+#[cfg(not(feature = "serde"))]
+fn save(_session: &Session, _path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "saving games needs the serde feature",
+    ))
+}
 
-        let mut value = -f32::INFINITY;
-        let mut best_value = -f32::INFINITY;
-        for square in self.moves() {
-            self.total_evaluations += 1;
-            self.make_move(square);
-            let score = -self.negamax(-beta, -alpha, depth - 1).1;
-            value = value.max(score);
-            self.undo_move(square);
-            if score == best_value {
-                best_moves.push(square);
-            } else if score > best_value {
-                best_value = score;
-                best_moves = vec![square];
-                if score > beta {
-                    break;
+#[cfg(feature = "serde")]
+fn load(path: &Path) -> io::Result<Session> {
+    let text = std::fs::read_to_string(path)?;
+    serde_json::from_str(&text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+fn play(args: PlayArgs, seed: Option<u64>, colored: bool) {
+    if let Some(rules) = args.connect {
+        play_connect(rules);
+        return;
+    }
+    #[cfg(feature = "serde")]
+    if let Some(path) = &args.resume {
+        let session =
+            load(path).unwrap_or_else(|e| fail(format!("can't resume {}: {}", path.display(), e)));
+        println!("Resuming {}", path.display());
+        play_session(session, seed, colored);
+        return;
+    }
+    let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+    let game = match args.random_start {
+        Some(pairs) => args.board.game().random_start(pairs, &mut rng),
+        None => args.board.game(),
+    };
+    let players = [args.first, args.second].map(|spec| match args.difficulty {
+        Some(level) if spec != PlayerSpec::Human => PlayerSpec::Level(level),
+        _ => spec,
+    });
+    let session = Session {
+        start: game.history().len(),
+        game,
+        players,
+        engine: args.engine,
+        eval: args.eval,
+    };
+    play_session(session, seed, colored);
+}
+
+fn play_session(mut session: Session, seed: Option<u64>, colored: bool) {
+    let [first, second] = session.players;
+    let mut players = sides(first, second, seed, &session.engine);
+    for side in &players {
+        if let Side::Human(player) = side {
+            player.borrow_mut().colored = colored;
+        }
+    }
+    // The side of the human against the engine, if one of them only is
+    let human = match players
+        .each_ref()
+        .map(|side| matches!(side, Side::Human(_)))
+    {
+        [true, false] => Some(0),
+        [false, true] => Some(1),
+        _ => None,
+    };
+    if let Some(human) = human {
+        println!("You play {}, type `quit` to give up", symbol(human));
+    }
+    // Moves taken back an undo goes over, a human's and the engine's reply
+    // against the engine
+    let plies = if human.is_some() { 2 } else { 1 };
+    let mut redo = vec![];
+    while !session.game.is_over() {
+        let turn = session.game.turn();
+        if session.eval {
+            print_eval(&session.game);
+        }
+        let action = match &mut players[usize::from(turn)] {
+            Side::Human(player) => player.borrow_mut().act(&session.game),
+            side => side.try_choose_move(&session.game).map(Action::Play),
+        };
+        match action {
+            None => {
+                println!("{} gives up", symbol(turn));
+                return;
+            }
+            Some(Action::Play(mv)) => {
+                session.game.make_move(mv).expect("Illegal move chosen");
+                println!("{} plays {}", symbol(turn), mv);
+                redo.clear();
+            }
+            Some(Action::Undo) if session.game.history().len() >= session.start + plies => {
+                // Latest first, as they are replayed from the end
+                let undone: Vec<_> = (0..plies)
+                    .filter_map(|_| session.game.take_back())
+                    .collect();
+                let moves: Vec<_> = undone.iter().rev().map(Move::to_string).collect();
+                println!("Took back {}", moves.join(" "));
+                redo.extend(undone);
+            }
+            Some(Action::Redo) if redo.len() >= plies => {
+                for _ in 0..plies {
+                    let (turn, mv) = (session.game.turn(), redo.pop().expect("A move to redo"));
+                    session.game.make_move(mv).expect("Redone moves are legal");
+                    println!("{} plays {}", symbol(turn), mv);
                 }
             }
-            alpha = alpha.max(score);
+            Some(Action::Undo) => println!("No move to take back"),
+            Some(Action::Redo) => println!("No move to replay"),
+            Some(Action::Save(path)) => match save(&session, &path) {
+                Ok(()) => println!("Saved to {}", path.display()),
+                Err(e) => println!("Can't save to {}: {}", path.display(), e),
+            },
         }
-        let mut rng = thread_rng();
-        (
-            best_moves
-                .choose(&mut rng)
-                .expect("Can't chose from 0 moves")
-                .clone(),
-            value,
-        )
+    }
+    println!("{}", board(&session.game, colored));
+    println!("{}", describe(session.game.status()));
+    match (human, session.game.status()) {
+        (Some(human), GameStatus::Won(player)) if player == human => println!("You win!"),
+        (Some(_), GameStatus::Won(_)) => println!("You lose."),
+        _ => {}
     }
 }
 
-fn main() {
+// Print the evaluation of the position for the side to move, thinking
+// for `EVAL_TIME`
+fn print_eval(game: &Game) {
+    let mut search = Search::new();
+    let result = search.iterative_deepening(&mut game.clone(), u8::MAX, EVAL_TIME);
+    println!(
+        "Evaluation for {}: {}",
+        symbol(game.turn()),
+        describe_score(result.score)
+    );
+}
+
+// Show the positions of a saved game one at a time, going back and forth
+// as asked on the standard input
+#[cfg(feature = "serde")]
+fn replay(args: ReplayArgs, colored: bool) {
+    use std::io::{BufRead, Write};
+
+    let session = load(&args.file)
+        .unwrap_or_else(|e| fail(format!("can't read {}: {}", args.file.display(), e)));
+    let mut game = session.game;
+    let moves = game.history().to_vec();
+    while game.take_back().is_some() {}
+    let mut lines = io::stdin().lock().lines();
+    let mut ply: usize = 0;
+    loop {
+        println!("{}", board(&game, colored));
+        match ply.checked_sub(1) {
+            Some(last) => println!(
+                "Ply {}/{}: {} played {}",
+                ply,
+                moves.len(),
+                symbol(1 - game.turn()),
+                moves[last]
+            ),
+            None => println!("Ply 0/{}: start", moves.len()),
+        }
+        if game.is_over() {
+            println!("{}", describe(game.status()));
+        } else if args.eval {
+            print_eval(&game);
+        }
+        print!("next, prev, jump PLY or quit: ");
+        io::stdout().flush().expect("Can't flush stdout");
+        let Some(line) = lines.next() else {
+            println!();
+            return;
+        };
+        let line = line.expect("Can't read input");
+        let target = match line.trim() {
+            "" | "n" | "next" => ply + 1,
+            "p" | "prev" => ply.saturating_sub(1),
+            "q" | "quit" => return,
+            command => match command
+                .strip_prefix("jump")
+                .or_else(|| command.strip_prefix('j'))
+                .and_then(|target| target.trim().parse().ok())
+            {
+                Some(target) => target,
+                None => {
+                    println!("Unknown command {:?}", command);
+                    continue;
+                }
+            },
+        };
+        let target = target.min(moves.len());
+        while ply < target {
+            game.make_move(moves[ply]).expect("Saved moves are legal");
+            ply += 1;
+        }
+        while ply > target {
+            game.take_back();
+            ply -= 1;
+        }
+    }
+}
+
+fn simulate(args: SimulateArgs, seed: Option<u64>) {
     let mut results = [0, 0, 0];
-    let mut eval_total = 0;
-    let n_games = 100;
-    for _ in 0..n_games {
-        let mut game = Game::new();
-        while !game.is_over() {
-            let next_move = if game.turn == 0 {
-                game.best_move(-f32::INFINITY, f32::INFINITY, 6)
-            } else {
-                game.best_move(-f32::INFINITY, f32::INFINITY, 6)
-            };
-            game.make_move(next_move);
-        }
-        eval_total += game.total_evaluations;
-        if game.is_won() {
-            if game.turn == 0 {
-                results[1] += 1;
-            } else {
-                results[0] += 1;
-            }
+    let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+    let [mut first, mut second] = sides(args.first, args.second, seed, &args.engine);
+    let control = TimeControl::new(
+        Duration::from_millis(args.clock),
+        Duration::from_millis(args.increment),
+    );
+    let mut swaps = 0;
+    for _ in 0..args.games {
+        let mut start = match args.random_start {
+            Some(pairs) => args.board.game().random_start(pairs, &mut rng),
+            None => args.board.game(),
+        };
+        let status = if args.pie {
+            let mut game = Pie::new(start);
+            let status = pie::play_timed(&mut game, [&mut first, &mut second], control);
+            swaps += u32::from(game.is_swapped());
+            status
         } else {
-            results[2] += 1
+            clock::play_timed(&mut start, [&mut first, &mut second], control)
+        };
+        match status {
+            GameStatus::Won(player) => results[player as usize] += 1,
+            _ => results[2] += 1,
         }
     }
+    let eval_total = first.evaluations() + second.evaluations();
     println!("{:?}", results);
-    println!("Total evaluations per game: {:?}", eval_total / n_games);
+    if args.pie {
+        println!("Sides swapped in {} games", swaps);
+    }
+    println!(
+        "Total evaluations per game: {:?}",
+        eval_total / args.games.max(1)
+    );
+}
+
+fn analyze(args: AnalyzeArgs, colored: bool) {
+    let mut game = args.position.game();
+    println!("{}", board(&game, colored));
+    let moves = game.legal_moves().len();
+    if moves == 0 {
+        println!("{}", describe(game.status()));
+        return;
+    }
+    let mut search = Search::new();
+    for line in search.multipv(&mut game, args.depth, moves) {
+        let pv: Vec<_> = line.pv.iter().map(Move::to_string).collect();
+        println!("{:<12} {}", describe_score(line.score), pv.join(" "));
+    }
+    println!("Evaluations: {}", search.evaluations);
+}
+
+fn solve(args: PositionArgs, colored: bool) {
+    let mut game = args.game();
+    println!("{}", board(&game, colored));
+    let start = Instant::now();
+    let mut search = Search::new();
+    let solution = search.solve(&mut game);
+    println!("{:?} for {}", solution.outcome, symbol(game.turn()));
+    if let Some(best) = solution.best {
+        println!("Best move: {}", best);
+    }
+    println!(
+        "Evaluations: {} in {:.3}s",
+        search.evaluations,
+        start.elapsed().as_secs_f64()
+    );
+}
+
+fn bench(args: BenchArgs) {
+    let mut game = args.position.game();
+    let empty = game.legal_moves().len() as u8;
+    let depth = args.depth.unwrap_or(empty).max(1);
+    println!("depth    score      nodes    time (ms)    nodes/s");
+    for depth in 1..=depth {
+        let mut search = Search::new();
+        let result = search.search(&mut game, depth);
+        let elapsed = result.stats.elapsed.as_secs_f64();
+        println!(
+            "{:>5} {:>8} {:>10} {:>12.1} {:>10.0}",
+            depth,
+            result.score,
+            result.stats.nodes,
+            elapsed * 1000.0,
+            f64::from(result.stats.nodes) / elapsed.max(1e-9)
+        );
+    }
+}
+
+fn rules(arg: &str) -> Result<ConnectRules, String> {
+    #[cfg(feature = "serde")]
+    if !arg.contains(',') {
+        return ConnectRules::load(arg).map_err(|e| e.to_string());
+    }
+    arg.parse()
+        .map_err(|e: tictactoe_rust::connect::ParseRulesError| e.to_string())
+}
+
+// One game between searches of a tenth of a second per turn, considering
+// squares near stones only
+fn play_connect(rules: ConnectRules) {
+    let mut game = Connect::new(rules)
+        .unwrap_or_else(|e| fail(e))
+        .with_reach(2);
+    let mut evaluations = 0;
+    while !game.is_over() {
+        let mut search = Search::new();
+        let stones = search
+            .iterative_deepening(&mut game.clone(), u8::MAX, Duration::from_millis(100))
+            .best()
+            .expect("Game isn't over");
+        evaluations += search.evaluations;
+        game.apply(stones);
+    }
+    println!("{}", game);
+    println!("{:?}", game.status());
+    println!("Total evaluations: {}", evaluations);
 }
@@ -0,0 +1,428 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::search::{is_decisive, GameState, Outcome, RootNoise, Search, SearchOptions, LOSS, WIN};
+
+/// Default number of playouts of a [`Mcts`] per move.
+pub const ITERATIONS: u32 = 10_000;
+
+// Heuristic score worth about three chances out of four of winning
+const SCORE_SCALE: f64 = 50.0;
+
+/// Way moves are chosen in playouts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rollout {
+    /// Uniformly at random.
+    Random,
+    /// Best of two random moves by [`GameState::move_priority`].
+    Heuristic,
+    /// A winning move if any, else a random one not letting the opponent
+    /// win on the next move if any, else at random. Much slower, checking
+    /// every reply to every move.
+    Tactical,
+}
+
+/// Policy and value function guiding a [`Mcts`] the AlphaZero way, see
+/// [`Mcts::guided_visits`].
+pub trait Oracle<G: GameState> {
+    /// Prior probabilities of the legal moves of a position whose game
+    /// isn't over, in the order of [`GameState::legal_moves`], and winning
+    /// chances of its side to move, 1 for a sure win and 0 for a sure loss.
+    fn predict(&self, game: &G) -> (Vec<f64>, f64);
+}
+
+/// Tunable behaviour of a [`Mcts`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MctsOptions {
+    /// Weight of the exploration term of UCT, √2 in theory, lower values
+    /// searching the best moves deeper. Also the weight of the priors in
+    /// guided searches.
+    pub exploration: f64,
+    pub rollout: Rollout,
+    /// Score leaves by a negamax search to this depth instead of playing
+    /// games from them, heuristic scores being mapped to winning chances.
+    pub leaf_depth: Option<u8>,
+    /// Seed of the playouts and move order, drawn from the system when
+    /// `None`.
+    pub seed: Option<u64>,
+}
+
+impl Default for MctsOptions {
+    fn default() -> Self {
+        MctsOptions {
+            exploration: std::f64::consts::SQRT_2,
+            rollout: Rollout::Random,
+            leaf_depth: None,
+            seed: None,
+        }
+    }
+}
+
+/// Monte Carlo tree search with the UCT selection rule, scoring positions
+/// by the outcomes of games played from them.
+#[derive(Clone, Debug)]
+pub struct Mcts {
+    /// Playouts per search, each one growing the tree by a node.
+    pub iterations: u32,
+    pub options: MctsOptions,
+    /// Number of playouts or leaf searches run so far.
+    pub playouts: u32,
+    rng: StdRng,
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Self::new(ITERATIONS)
+    }
+}
+
+// Tree node, scored for the side that played the move leading to it
+struct Node<M> {
+    // Move leading to the node, None at the root
+    mv: Option<M>,
+    parent: usize,
+    children: Vec<usize>,
+    // Moves without a child yet, in random order
+    untried: Vec<M>,
+    visits: u32,
+    // Sum of the playout rewards, 1 for a win and 0.5 for a draw
+    reward: f64,
+    // Probability of the move given by an oracle, in guided searches
+    prior: f64,
+}
+
+impl<M> Node<M> {
+    fn new<G: GameState<Move = M>>(
+        game: &G,
+        mv: Option<M>,
+        parent: usize,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let mut untried = if game.is_terminal() {
+            vec![]
+        } else {
+            game.legal_moves()
+        };
+        untried.shuffle(rng);
+        Node {
+            mv,
+            parent,
+            children: vec![],
+            untried,
+            visits: 0,
+            reward: 0.0,
+            prior: 0.0,
+        }
+    }
+
+    // Node of a guided search, expanded all at once when first visited
+    fn guided(mv: Option<M>, parent: usize, prior: f64) -> Self {
+        Node {
+            mv,
+            parent,
+            children: vec![],
+            untried: vec![],
+            visits: 0,
+            reward: 0.0,
+            prior,
+        }
+    }
+
+    // Upper confidence bound of the node, whose parent has been visited
+    // `total` times
+    fn uct(&self, total: u32, exploration: f64) -> f64 {
+        let visits = f64::from(self.visits);
+        self.reward / visits + exploration * (f64::from(total).ln() / visits).sqrt()
+    }
+
+    // PUCT score of the node, exploring moves in proportion to their prior,
+    // unvisited ones counting as draws
+    fn puct(&self, total: u32, exploration: f64) -> f64 {
+        let visits = f64::from(self.visits);
+        let value = if self.visits == 0 {
+            0.5
+        } else {
+            self.reward / visits
+        };
+        value + exploration * self.prior * f64::from(total.max(1)).sqrt() / (1.0 + visits)
+    }
+}
+
+impl Mcts {
+    pub fn new(iterations: u32) -> Self {
+        Self::with_options(iterations, MctsOptions::default())
+    }
+
+    pub fn with_options(iterations: u32, options: MctsOptions) -> Self {
+        Mcts {
+            iterations,
+            options,
+            playouts: 0,
+            rng: options
+                .seed
+                .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+        }
+    }
+
+    /// Most visited move of the side to move after `iterations` playouts,
+    /// `None` when the game is over.
+    pub fn best_move<G: GameState>(&mut self, game: &mut G) -> Option<G::Move> {
+        let mut tree = vec![Node::new(game, None, 0, &mut self.rng)];
+        let mut search = Search::with_options(SearchOptions {
+            seed: Some(self.rng.gen()),
+            ..SearchOptions::default()
+        });
+        for _ in 0..self.iterations.max(1) {
+            let mut path = vec![];
+            // Descend through fully expanded nodes
+            let mut node = 0;
+            while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+                let total = tree[node].visits;
+                let uct = |child: usize| tree[child].uct(total, self.options.exploration);
+                node = *tree[node]
+                    .children
+                    .iter()
+                    .max_by(|&&a, &&b| uct(a).total_cmp(&uct(b)))?;
+                let mv = tree[node].mv?;
+                game.apply(mv);
+                path.push(mv);
+            }
+            // Grow the tree by one of the moves left
+            if let Some(mv) = tree[node].untried.pop() {
+                game.apply(mv);
+                path.push(mv);
+                let child = tree.len();
+                tree.push(Node::new(game, Some(mv), node, &mut self.rng));
+                tree[node].children.push(child);
+                node = child;
+            }
+            let reward = match self.options.leaf_depth {
+                Some(depth) => self.search_leaf(game, &mut search, depth),
+                None => self.playout(game),
+            };
+            let mut reward = 1.0 - reward;
+            // Back the reward up, switching sides at each level
+            loop {
+                tree[node].visits += 1;
+                tree[node].reward += reward;
+                reward = 1.0 - reward;
+                if node == 0 {
+                    break;
+                }
+                node = tree[node].parent;
+            }
+            for mv in path.into_iter().rev() {
+                game.undo(mv);
+            }
+        }
+        let best = *tree[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| tree[child].visits)?;
+        tree[best].mv
+    }
+
+    /// Visits of the moves of the side to move after `iterations`
+    /// simulations guided by an oracle, empty when the game is over. Moves
+    /// are selected by PUCT, in proportion to their prior, and leaves are
+    /// scored by the oracle rather than by playouts. `noise` is mixed into
+    /// the priors at the root, for exploration in self-play.
+    pub fn guided_visits<G: GameState, O: Oracle<G>>(
+        &mut self,
+        game: &mut G,
+        oracle: &O,
+        noise: Option<RootNoise>,
+    ) -> Vec<(G::Move, u32)> {
+        if game.is_terminal() {
+            return vec![];
+        }
+        let mut tree = vec![Node::guided(None, 0, 1.0)];
+        expand(&mut tree, 0, game, oracle);
+        if let Some(noise) = noise {
+            let mut priors: Vec<_> = tree[0]
+                .children
+                .iter()
+                .map(|&child| (child, tree[child].prior))
+                .collect();
+            noise.apply(&mut priors, &mut self.rng);
+            for (child, prior) in priors {
+                tree[child].prior = prior;
+            }
+        }
+        for _ in 0..self.iterations.max(1) {
+            let mut path = vec![];
+            let mut node = 0;
+            while !tree[node].children.is_empty() {
+                let total = tree[node].visits;
+                let puct = |child: usize| tree[child].puct(total, self.options.exploration);
+                node = *tree[node]
+                    .children
+                    .iter()
+                    .max_by(|&&a, &&b| puct(a).total_cmp(&puct(b)))
+                    .expect("Children to select");
+                let mv = tree[node].mv.expect("Children have a move");
+                game.apply(mv);
+                path.push(mv);
+            }
+            self.playouts += 1;
+            let mut reward = 1.0 - expand(&mut tree, node, game, oracle);
+            loop {
+                tree[node].visits += 1;
+                tree[node].reward += reward;
+                reward = 1.0 - reward;
+                if node == 0 {
+                    break;
+                }
+                node = tree[node].parent;
+            }
+            for mv in path.into_iter().rev() {
+                game.undo(mv);
+            }
+        }
+        tree[0]
+            .children
+            .iter()
+            .map(|&child| {
+                (
+                    tree[child].mv.expect("Children have a move"),
+                    tree[child].visits,
+                )
+            })
+            .collect()
+    }
+
+    /// Most visited move of a guided search, see [`Mcts::guided_visits`].
+    pub fn best_move_guided<G: GameState, O: Oracle<G>>(
+        &mut self,
+        game: &mut G,
+        oracle: &O,
+    ) -> Option<G::Move> {
+        self.guided_visits(game, oracle, None)
+            .into_iter()
+            .max_by_key(|&(_, visits)| visits)
+            .map(|(mv, _)| mv)
+    }
+
+    // Winning chances of the side to move given by a negamax search
+    fn search_leaf<G: GameState>(
+        &mut self,
+        game: &mut G,
+        search: &mut Search<G>,
+        depth: u8,
+    ) -> f64 {
+        self.playouts += 1;
+        let score = search.negamax(game, LOSS, WIN, depth).score;
+        if is_decisive(score) {
+            if score > 0 {
+                1.0
+            } else {
+                0.0
+            }
+        } else {
+            1.0 / (1.0 + (-f64::from(score) / SCORE_SCALE).exp())
+        }
+    }
+
+    // Reward of a game played from the position for the side to move,
+    // leaving the position unchanged
+    fn playout<G: GameState>(&mut self, game: &mut G) -> f64 {
+        self.playouts += 1;
+        let rng = &mut self.rng;
+        let mut played = vec![];
+        while !game.is_terminal() {
+            let moves = game.legal_moves();
+            let mv = match self.options.rollout {
+                Rollout::Random => moves[rng.gen_range(0..moves.len())],
+                Rollout::Heuristic => {
+                    let a = moves[rng.gen_range(0..moves.len())];
+                    let b = moves[rng.gen_range(0..moves.len())];
+                    if game.move_priority(b) > game.move_priority(a) {
+                        b
+                    } else {
+                        a
+                    }
+                }
+                Rollout::Tactical => tactical_move(game, &moves, rng)
+                    .unwrap_or_else(|| moves[rng.gen_range(0..moves.len())]),
+            };
+            game.apply(mv);
+            played.push(mv);
+        }
+        // The outcome is the one of the side to move at the end
+        let reward = terminal_reward(game);
+        let reward = if played.len() % 2 == 0 {
+            reward
+        } else {
+            1.0 - reward
+        };
+        for mv in played.into_iter().rev() {
+            game.undo(mv);
+        }
+        reward
+    }
+}
+
+// Reward of the side to move of a finished game
+fn terminal_reward<G: GameState>(game: &G) -> f64 {
+    match Outcome::from_score(game.evaluate()) {
+        Outcome::Win => 1.0,
+        Outcome::Draw => 0.5,
+        Outcome::Loss => 0.0,
+    }
+}
+
+// Add the children of a leaf of a guided search with their priors,
+// returning the winning chances of its side to move
+fn expand<G: GameState, O: Oracle<G>>(
+    tree: &mut Vec<Node<G::Move>>,
+    node: usize,
+    game: &G,
+    oracle: &O,
+) -> f64 {
+    if game.is_terminal() {
+        return terminal_reward(game);
+    }
+    let (priors, value) = oracle.predict(game);
+    for (mv, prior) in game.legal_moves().into_iter().zip(priors) {
+        let child = tree.len();
+        tree.push(Node::guided(Some(mv), node, prior));
+        tree[node].children.push(child);
+    }
+    value
+}
+
+// Winning move, else random move after which the opponent can't win at
+// once, `None` when no move is safe
+fn tactical_move<G: GameState>(
+    game: &mut G,
+    moves: &[G::Move],
+    rng: &mut impl Rng,
+) -> Option<G::Move> {
+    if let Some(&mv) = moves.iter().find(|&&mv| wins(game, mv)) {
+        return Some(mv);
+    }
+    let safe: Vec<_> = moves
+        .iter()
+        .copied()
+        .filter(|&mv| {
+            game.apply(mv);
+            let lost = !game.is_terminal()
+                && game
+                    .legal_moves()
+                    .into_iter()
+                    .any(|reply| wins(game, reply));
+            game.undo(mv);
+            !lost
+        })
+        .collect();
+    safe.choose(rng).copied()
+}
+
+// Whether playing `mv` wins the game at once
+fn wins<G: GameState>(game: &mut G, mv: G::Move) -> bool {
+    game.apply(mv);
+    let won = game.is_terminal() && Outcome::from_score(game.evaluate()) == Outcome::Loss;
+    game.undo(mv);
+    won
+}
@@ -0,0 +1,135 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Square to play, given by its zero based row and column. Squares are
+/// printed in algebraic notation, a letter for the column and a number
+/// for the row counted from the top, `a1` being the top left corner and
+/// `b2` the center of the 3×3 board. With the `serde` feature they are
+/// stored the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "MoveRepr", into = "String")
+)]
+pub struct Move {
+    pub row: u8,
+    pub col: u8,
+}
+
+impl Move {
+    pub fn new(row: u8, col: u8) -> Self {
+        Move { row, col }
+    }
+}
+
+impl From<(u8, u8)> for Move {
+    fn from((row, col): (u8, u8)) -> Self {
+        Move::new(row, col)
+    }
+}
+
+impl From<Move> for (u8, u8) {
+    fn from(mv: Move) -> Self {
+        (mv.row, mv.col)
+    }
+}
+
+// Number of column letters
+const LETTERS: u8 = 26;
+
+// Printed as `b2`, columns past `z` as `row,col`
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.col < LETTERS {
+            write!(
+                f,
+                "{}{}",
+                char::from(b'a' + self.col),
+                u16::from(self.row) + 1
+            )
+        } else {
+            write!(f, "{},{}", self.row, self.col)
+        }
+    }
+}
+
+/// Error returned when a string is neither a square such as `b2` nor a
+/// `row,col` coordinate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseMoveError(String);
+
+impl fmt::Display for ParseMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected a square such as `b2` or `row,col`, got {:?}",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseMoveError {}
+
+// Parse two numbers separated by a comma, spaces or both
+pub(crate) fn parse_pair(s: &str) -> Result<(u8, u8), ParseMoveError> {
+    let err = || ParseMoveError(s.to_string());
+    let mut parts = s
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty());
+    let first = parts.next().ok_or_else(err)?.parse().map_err(|_| err())?;
+    let second = parts.next().ok_or_else(err)?.parse().map_err(|_| err())?;
+    if parts.next().is_some() {
+        return Err(err());
+    }
+    Ok((first, second))
+}
+
+// Parses `b2` in either case, or `row,col`, `row col` or `row, col`
+impl FromStr for Move {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let square = s.trim();
+        let mut chars = square.chars();
+        match chars.next() {
+            Some(letter) if letter.is_ascii_alphabetic() => {
+                let col = letter.to_ascii_lowercase() as u8 - b'a';
+                match chars.as_str().parse::<u8>() {
+                    Ok(row) if row > 0 => Ok(Move::new(row - 1, col)),
+                    _ => Err(ParseMoveError(s.to_string())),
+                }
+            }
+            _ => parse_pair(s).map(Move::from),
+        }
+    }
+}
+
+// Serialized form of a move, a square as typed, or the row and column
+// fields of files written before squares were
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum MoveRepr {
+    Square(String),
+    Fields { row: u8, col: u8 },
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<MoveRepr> for Move {
+    type Error = ParseMoveError;
+
+    fn try_from(repr: MoveRepr) -> Result<Self, Self::Error> {
+        match repr {
+            MoveRepr::Square(square) => square.parse(),
+            MoveRepr::Fields { row, col } => Ok(Move::new(row, col)),
+        }
+    }
+}
+
+impl From<Move> for String {
+    fn from(mv: Move) -> Self {
+        mv.to_string()
+    }
+}
@@ -0,0 +1,358 @@
+use std::fmt;
+
+use crate::eval::THREAT_SCORE;
+use crate::search::{Score, DRAW, LOSS, WIN};
+use crate::{Bitboard, BoardError, Game, GameStatus, Move, MoveError, PlayerId};
+
+/// Most players sharing a board.
+pub const MAX_PLAYERS: u8 = 4;
+
+// Symbols of the players in turn order
+const SYMBOLS: [char; MAX_PLAYERS as usize] = ['X', 'O', 'Y', 'Z'];
+
+/// Tic-tac-toe between more than two players, taking turns in order
+/// until one of them completes a line or the board is full.
+///
+/// The board is an empty [`Game`] giving the geometry, win masks and
+/// blocked squares, each player having its own stones. Two-player negamax
+/// doesn't apply, one player's loss not being a single opponent's win, so
+/// [`MultiSearch`] searches these games with max^n or paranoid searches.
+#[derive(Clone, Debug)]
+pub struct MultiGame {
+    board: Game,
+    // Squares that can be played, blocked ones left out
+    playable: Bitboard,
+    stones: Vec<Bitboard>,
+    turn: PlayerId,
+    history: Vec<Move>,
+}
+
+impl Default for MultiGame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiGame {
+    /// Create an empty 5×5 board won by four in a row between three
+    /// players.
+    pub fn new() -> Self {
+        let board = Game::with_win_length(5, 4).expect("5×5 is a valid size");
+        Self::with_board(board, 3).expect("3 players fit a board")
+    }
+
+    /// Play between `players` players on the board of a game, any stones
+    /// already on it left out.
+    pub fn with_board(mut board: Game, players: u8) -> Result<Self, BoardError> {
+        if !(2..=MAX_PLAYERS).contains(&players) {
+            return Err(BoardError::InvalidPlayers(players));
+        }
+        board.set_players([Bitboard::EMPTY; 2]);
+        let playable = board
+            .moves()
+            .into_iter()
+            .fold(Bitboard::EMPTY, |squares, mv| {
+                squares | Bitboard::bit(board.square(mv))
+            });
+        Ok(MultiGame {
+            board,
+            playable,
+            stones: vec![Bitboard::EMPTY; usize::from(players)],
+            turn: 0,
+            history: vec![],
+        })
+    }
+
+    /// Number of players.
+    pub fn players(&self) -> u8 {
+        self.stones.len() as u8
+    }
+
+    /// Player to move, 0 for the first player and so on in turn order.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Stones of a player.
+    pub fn stones(&self, player: PlayerId) -> Bitboard {
+        self.stones[usize::from(player)]
+    }
+
+    /// Empty board the game is played on.
+    pub fn board(&self) -> &Game {
+        &self.board
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// Player who moved last, the one who may have completed a line
+    pub fn last_player(&self) -> PlayerId {
+        (self.turn + self.players() - 1) % self.players()
+    }
+
+    // Stones of every player
+    fn taken(&self) -> Bitboard {
+        self.stones
+            .iter()
+            .fold(Bitboard::EMPTY, |taken, &stones| taken | stones)
+    }
+
+    pub fn check_move(&self, mv: Move) -> Result<(), MoveError> {
+        self.board.check_move(mv)?;
+        if self.taken().contains(self.board.square(mv)) {
+            Err(MoveError::Occupied)
+        } else if self.is_over() {
+            Err(MoveError::GameOver)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+
+    // Play a move without legality checks, passing the turn to the next
+    // player
+    fn apply(&mut self, mv: Move) {
+        self.stones[usize::from(self.turn)].insert(self.board.square(mv));
+        self.turn = (self.turn + 1) % self.players();
+        self.history.push(mv);
+    }
+
+    /// Take back the last move, given as `mv`.
+    pub fn undo_move(&mut self, mv: Move) {
+        self.turn = self.last_player();
+        self.stones[usize::from(self.turn)] ^= Bitboard::bit(self.board.square(mv));
+        self.history.pop();
+    }
+
+    /// Empty squares, none once the game is over.
+    pub fn moves(&self) -> Vec<Move> {
+        if self.is_over() {
+            return vec![];
+        }
+        (self.playable & !self.taken())
+            .ones()
+            .map(|square| self.board.square_move(square))
+            .collect()
+    }
+
+    /// Check if the player who moved last completed a line.
+    pub fn has_line(&self) -> bool {
+        let stones = self.stones(self.last_player());
+        self.board.wins().iter().any(|&mask| stones & mask == mask)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.taken() == self.playable
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.has_line() || self.is_full()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.has_line() {
+            GameStatus::Won(self.last_player())
+        } else if self.is_full() {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// Score of the position for each player, in turn order: [`WIN`] for
+    /// the winner, sooner wins scoring higher, and [`LOSS`] for the
+    /// others, [`DRAW`] for all on a full board. Otherwise players score
+    /// the lines still open to them by the square of their stones on
+    /// them, less those of the best placed other player.
+    pub fn scores(&self) -> Vec<Score> {
+        let plies = self.history.len() as Score;
+        match self.status() {
+            GameStatus::Won(winner) => (0..self.players())
+                .map(|player| {
+                    if player == winner {
+                        WIN - plies
+                    } else {
+                        LOSS + plies
+                    }
+                })
+                .collect(),
+            GameStatus::Draw => vec![DRAW; self.stones.len()],
+            GameStatus::InProgress => {
+                let taken = self.taken();
+                let mut open = vec![0; self.stones.len()];
+                for &mask in self.board.wins() {
+                    for (player, &stones) in self.stones.iter().enumerate() {
+                        let ours = (stones & mask).count_ones();
+                        if ours > 0 && (taken & !stones & mask).is_empty() {
+                            open[player] += (ours * ours) as Score * THREAT_SCORE;
+                        }
+                    }
+                }
+                (0..open.len())
+                    .map(|player| {
+                        let best_other = (0..open.len())
+                            .filter(|&other| other != player)
+                            .map(|other| open[other])
+                            .max()
+                            .unwrap_or(0);
+                        open[player] - best_other
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// One row per line, X, O, Y then Z for the players in turn order, # when
+// blocked, . when empty
+impl fmt::Display for MultiGame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = (self.board.width(), self.board.height());
+        for row in 0..height {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            let cells: Vec<_> = (0..width)
+                .map(|col| {
+                    let square = self.board.square(Move::new(row, col));
+                    if self.board.blocked().contains(square) {
+                        return '#';
+                    }
+                    (0..self.players())
+                        .find(|&player| self.stones(player).contains(square))
+                        .map_or('.', |player| SYMBOLS[usize::from(player)])
+                })
+                .map(String::from)
+                .collect();
+            write!(f, "{}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Search of games between more than two players, each assumed to play
+/// for itself or all of them against the one to move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MultiMode {
+    /// Max^n: every player maximizes its own score, positions being scored
+    /// for all players at once. Nothing can be pruned, but players don't
+    /// gang up against each other.
+    #[default]
+    MaxN,
+    /// Paranoid: the other players form a coalition minimizing the score
+    /// of the player to move at the root, turning the game into a
+    /// two-sided one searched with alpha/beta pruning.
+    Paranoid,
+}
+
+/// Depth-limited search of a [`MultiGame`].
+#[derive(Clone, Debug, Default)]
+pub struct MultiSearch {
+    pub mode: MultiMode,
+    /// Number of positions scored so far.
+    pub evaluations: u64,
+}
+
+impl MultiSearch {
+    pub fn new(mode: MultiMode) -> Self {
+        MultiSearch {
+            mode,
+            evaluations: 0,
+        }
+    }
+
+    /// Best move of the player to move searching `depth` plies ahead, with
+    /// the scores of every player at the end of the line expected after
+    /// it. The move is `None` once the game is over.
+    pub fn search(&mut self, game: &mut MultiGame, depth: u8) -> (Vec<Score>, Option<Move>) {
+        match self.mode {
+            MultiMode::MaxN => self.maxn(game, depth),
+            MultiMode::Paranoid => {
+                let root = usize::from(game.turn());
+                self.paranoid(game, root, depth, LOSS - 1, WIN + 1)
+            }
+        }
+    }
+
+    /// Best move of the player to move searching `depth` plies ahead.
+    pub fn best_move(&mut self, game: &mut MultiGame, depth: u8) -> Option<Move> {
+        self.search(game, depth).1
+    }
+
+    fn maxn(&mut self, game: &mut MultiGame, depth: u8) -> (Vec<Score>, Option<Move>) {
+        let moves = game.moves();
+        if depth == 0 || moves.is_empty() {
+            self.evaluations += 1;
+            return (game.scores(), None);
+        }
+        let turn = usize::from(game.turn());
+        let mut best: Option<(Vec<Score>, Move)> = None;
+        for mv in moves {
+            game.apply(mv);
+            let (scores, _) = self.maxn(game, depth - 1);
+            game.undo_move(mv);
+            if best
+                .as_ref()
+                .is_none_or(|(best, _)| scores[turn] > best[turn])
+            {
+                best = Some((scores, mv));
+            }
+        }
+        let (scores, mv) = best.expect("Some move was searched");
+        (scores, Some(mv))
+    }
+
+    // Alpha/beta search of the score of `root`, every other player playing
+    // against it, along with the scores of all players at the leaf it
+    // comes from
+    fn paranoid(
+        &mut self,
+        game: &mut MultiGame,
+        root: usize,
+        depth: u8,
+        mut alpha: Score,
+        mut beta: Score,
+    ) -> (Vec<Score>, Option<Move>) {
+        let moves = game.moves();
+        if depth == 0 || moves.is_empty() {
+            self.evaluations += 1;
+            return (game.scores(), None);
+        }
+        let maximizing = usize::from(game.turn()) == root;
+        let mut best: Option<(Vec<Score>, Move)> = None;
+        for mv in moves {
+            game.apply(mv);
+            let (scores, _) = self.paranoid(game, root, depth - 1, alpha, beta);
+            game.undo_move(mv);
+            let score = scores[root];
+            let better = best.as_ref().is_none_or(|(best, _)| {
+                if maximizing {
+                    score > best[root]
+                } else {
+                    score < best[root]
+                }
+            });
+            if better {
+                best = Some((scores, mv));
+            }
+            if maximizing {
+                alpha = alpha.max(score);
+            } else {
+                beta = beta.min(score);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        let (scores, mv) = best.expect("Some move was searched");
+        (scores, Some(mv))
+    }
+}
@@ -0,0 +1,243 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::moves::parse_pair;
+use crate::search::{GameState, Score, DRAW, LOSS, WIN};
+use crate::tt::mix;
+use crate::{GameStatus, MoveError, ParseMoveError, PlayerId};
+
+/// Move of [`Nim`]: objects taken from one heap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NimMove {
+    pub heap: u8,
+    pub take: u8,
+}
+
+impl NimMove {
+    pub fn new(heap: u8, take: u8) -> Self {
+        NimMove { heap, take }
+    }
+}
+
+// Printed as `heap,take`
+impl fmt::Display for NimMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.heap, self.take)
+    }
+}
+
+// Parses `heap,take` like the coordinates of squares
+impl FromStr for NimMove {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (heap, take) = parse_pair(s)?;
+        Ok(NimMove::new(heap, take))
+    }
+}
+
+// Whether the player to move wins heaps with perfect play: by a nonzero
+// xor of the heaps, unless misère heaps are all of one object at most,
+// where an even number of them wins
+fn wins(heaps: &[u8], misere: bool) -> bool {
+    if misere && heaps.iter().all(|&heap| heap <= 1) {
+        heaps.iter().filter(|&&heap| heap == 1).count() % 2 == 0
+    } else {
+        heaps.iter().fold(0, |sum, &heap| sum ^ heap) != 0
+    }
+}
+
+/// Nim: players take turns removing objects from a single heap, and the
+/// player taking the last one wins, or loses under misère play.
+///
+/// Being impartial, positions are equivalent whoever is to move and heaps
+/// are interchangeable. Searches find the outcome move by move while
+/// [`Nim::is_winning`] knows it in closed form, from the xor of the heaps.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Nim {
+    heaps: Vec<u8>,
+    misere: bool,
+    turn: PlayerId,
+    history: Vec<NimMove>,
+}
+
+impl Default for Nim {
+    fn default() -> Self {
+        Self::new(&[3, 4, 5])
+    }
+}
+
+impl Nim {
+    /// Create a game on heaps of the given sizes with the first player to
+    /// move, won by taking the last object.
+    pub fn new(heaps: &[u8]) -> Self {
+        Nim {
+            heaps: heaps.to_vec(),
+            misere: false,
+            turn: 0,
+            history: vec![],
+        }
+    }
+
+    /// Create a game on heaps of the given sizes lost by taking the last
+    /// object.
+    pub fn misere(heaps: &[u8]) -> Self {
+        Nim {
+            misere: true,
+            ..Self::new(heaps)
+        }
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Objects left in each heap.
+    pub fn heaps(&self) -> &[u8] {
+        &self.heaps
+    }
+
+    /// Whether taking the last object loses.
+    pub fn is_misere(&self) -> bool {
+        self.misere
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[NimMove] {
+        &self.history
+    }
+
+    /// Xor of the heaps, the Grundy value of the position under normal
+    /// play.
+    pub fn nim_sum(&self) -> u8 {
+        self.heaps.iter().fold(0, |sum, &heap| sum ^ heap)
+    }
+
+    /// Whether the player to move wins with perfect play.
+    pub fn is_winning(&self) -> bool {
+        wins(&self.heaps, self.misere)
+    }
+
+    /// A move leaving the opponent lost, when the player to move wins.
+    pub fn winning_move(&self) -> Option<NimMove> {
+        let mut heaps = self.heaps.clone();
+        for heap in 0..heaps.len() {
+            let size = heaps[heap];
+            for take in 1..=size {
+                heaps[heap] = size - take;
+                if !wins(&heaps, self.misere) {
+                    return Some(NimMove::new(heap as u8, take));
+                }
+            }
+            heaps[heap] = size;
+        }
+        None
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.heaps.iter().all(|&heap| heap == 0)
+    }
+
+    /// The player who took the last object won once every heap is empty,
+    /// or lost under misère play.
+    pub fn status(&self) -> GameStatus {
+        match (self.is_over(), self.misere) {
+            (false, _) => GameStatus::InProgress,
+            (true, false) => GameStatus::Won(1 - self.turn),
+            (true, true) => GameStatus::Won(self.turn),
+        }
+    }
+
+    pub fn check_move(&self, mv: NimMove) -> Result<(), MoveError> {
+        let Some(&heap) = self.heaps.get(usize::from(mv.heap)) else {
+            return Err(MoveError::OutOfBounds);
+        };
+        if self.is_over() {
+            Err(MoveError::GameOver)
+        } else if mv.take == 0 || mv.take > heap {
+            Err(MoveError::InvalidTake)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, mv: NimMove) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+}
+
+// One heap per line, its index then a bar by object
+impl fmt::Display for Nim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, &heap) in self.heaps.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}: {}", i, "|".repeat(usize::from(heap)))?;
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Nim {
+    type Move = NimMove;
+
+    fn legal_moves(&self) -> Vec<NimMove> {
+        let mut moves = vec![];
+        for (heap, &size) in self.heaps.iter().enumerate() {
+            for take in 1..=size {
+                moves.push(NimMove::new(heap as u8, take));
+            }
+        }
+        moves
+    }
+
+    fn apply(&mut self, mv: NimMove) {
+        self.heaps[usize::from(mv.heap)] -= mv.take;
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    fn undo(&mut self, mv: NimMove) {
+        self.heaps[usize::from(mv.heap)] += mv.take;
+        self.turn = 1 - self.turn;
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Taking the last object of a heap first, then by objects taken
+    fn move_priority(&self, mv: NimMove) -> i32 {
+        let empties = self.heaps[usize::from(mv.heap)] == mv.take;
+        i32::from(empties) * 256 + i32::from(mv.take)
+    }
+
+    // Nonempty heaps sorted, the side to move left out
+    fn hash_key(&self) -> Option<u64> {
+        let mut heaps: Vec<_> = self.heaps.iter().filter(|&&heap| heap > 0).collect();
+        heaps.sort_unstable();
+        let start = mix(u64::from(self.misere), heaps.len() as u64);
+        Some(
+            heaps
+                .into_iter()
+                .map(|&heap| u64::from(heap))
+                .fold(start, mix),
+        )
+    }
+
+    // The player who took the last object won, or lost under misère play,
+    // quiet positions being even as far as a static evaluation tells
+    fn evaluate(&self) -> Score {
+        match (self.is_over(), self.misere) {
+            (false, _) => DRAW,
+            (true, false) => LOSS,
+            (true, true) => WIN,
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+
+use crate::search::{GameState, Score, DRAW, WIN};
+use crate::tt::mix;
+use crate::ultimate::has_line;
+use crate::{GameStatus, Move, MoveError, PlayerId};
+
+// Side of each board
+const SIDE: u8 = 3;
+
+// Image of a square of a board under each of its eight symmetries
+fn transform(square: u8, symmetry: u8) -> u8 {
+    let (row, col) = (square / SIDE, square % SIDE);
+    let (row, col) = if symmetry & 4 != 0 {
+        (col, row)
+    } else {
+        (row, col)
+    };
+    let row = if symmetry & 2 != 0 {
+        SIDE - 1 - row
+    } else {
+        row
+    };
+    let col = if symmetry & 1 != 0 {
+        SIDE - 1 - col
+    } else {
+        col
+    };
+    row * SIDE + col
+}
+
+// Board in the orientation sorting first among its rotations and
+// reflections
+fn canonical(board: u16) -> u16 {
+    (0..8)
+        .map(|symmetry| {
+            (0..SIDE * SIDE)
+                .filter(|&square| board & (1 << square) != 0)
+                .fold(0, |image, square| image | 1 << transform(square, symmetry))
+        })
+        .min()
+        .expect("Boards have symmetries")
+}
+
+/// Notakto: both players place X stones on several 3×3 boards, a board
+/// being dead once it holds three in a row, and the player completing a
+/// line on the last live board loses.
+///
+/// Boards are stacked along the rows of moves: board `b` holds rows
+/// `3 * b` to `3 * b + 2`. Being impartial, positions are equivalent
+/// whoever is to move, and boards are interchangeable.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Notakto {
+    // Stones of each board
+    boards: Vec<u16>,
+    turn: PlayerId,
+    history: Vec<Move>,
+}
+
+impl Default for Notakto {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl Notakto {
+    /// Create a game on `boards` empty boards, one at least.
+    pub fn new(boards: u8) -> Self {
+        assert!(boards > 0, "Notakto needs a board");
+        Notakto {
+            boards: vec![0; usize::from(boards)],
+            turn: 0,
+            history: vec![],
+        }
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// Number of boards, dead or alive.
+    pub fn boards(&self) -> u8 {
+        self.boards.len() as u8
+    }
+
+    /// Whether a board has no line yet.
+    pub fn is_alive(&self, board: u8) -> bool {
+        !has_line(self.boards[usize::from(board)])
+    }
+
+    // Board and square within it of a move
+    fn cell(mv: Move) -> (usize, u8) {
+        (usize::from(mv.row / SIDE), mv.row % SIDE * SIDE + mv.col)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.boards.iter().all(|&board| has_line(board))
+    }
+
+    /// The player who moved last lost once every board is dead.
+    pub fn status(&self) -> GameStatus {
+        if self.is_over() {
+            GameStatus::Won(self.turn)
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// Check that a move can be played in the current position, on an
+    /// empty square of a live board.
+    pub fn check_move(&self, mv: Move) -> Result<(), MoveError> {
+        let (board, square) = Self::cell(mv);
+        if board >= self.boards.len() || mv.col >= SIDE {
+            Err(MoveError::OutOfBounds)
+        } else if self.is_over() {
+            Err(MoveError::GameOver)
+        } else if self.boards[board] & (1 << square) != 0 {
+            Err(MoveError::Occupied)
+        } else if has_line(self.boards[board]) {
+            Err(MoveError::WrongBoard)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+}
+
+// Boards side by side, X for stones, . when empty, dead boards marked below
+impl fmt::Display for Notakto {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..SIDE {
+            for (i, &board) in self.boards.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "   ")?;
+                }
+                for col in 0..SIDE {
+                    if col > 0 {
+                        write!(f, " ")?;
+                    }
+                    let stone = board & (1 << (row * SIDE + col)) != 0;
+                    write!(f, "{}", if stone { 'X' } else { '.' })?;
+                }
+            }
+            writeln!(f)?;
+        }
+        for (i, &board) in self.boards.iter().enumerate() {
+            if i > 0 {
+                write!(f, "   ")?;
+            }
+            write!(f, "{}", if has_line(board) { "dead " } else { "     " })?;
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Notakto {
+    type Move = Move;
+
+    fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = vec![];
+        for (board, &stones) in self.boards.iter().enumerate() {
+            if has_line(stones) {
+                continue;
+            }
+            for square in (0..SIDE * SIDE).filter(|square| stones & (1 << square) == 0) {
+                let row = board as u8 * SIDE + square / SIDE;
+                moves.push(Move::new(row, square % SIDE));
+            }
+        }
+        moves
+    }
+
+    fn apply(&mut self, mv: Move) {
+        let (board, square) = Self::cell(mv);
+        self.boards[board] |= 1 << square;
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    fn undo(&mut self, mv: Move) {
+        let (board, square) = Self::cell(mv);
+        self.boards[board] &= !(1 << square);
+        self.turn = 1 - self.turn;
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Moves killing the last live board lose at once, searched last
+    fn move_priority(&self, mv: Move) -> i32 {
+        let (board, square) = Self::cell(mv);
+        let alive = self
+            .boards
+            .iter()
+            .filter(|&&stones| !has_line(stones))
+            .count();
+        if alive == 1 && has_line(self.boards[board] | 1 << square) {
+            -1
+        } else {
+            0
+        }
+    }
+
+    fn move_index(&self, mv: Move) -> Option<usize> {
+        let (board, square) = Self::cell(mv);
+        Some(board * usize::from(SIDE * SIDE) + usize::from(square))
+    }
+
+    // Live boards in canonical orientation and sorted, dead boards all
+    // being alike, and the side to move left out
+    fn hash_key(&self) -> Option<u64> {
+        let mut boards: Vec<_> = self
+            .boards
+            .iter()
+            .filter(|&&board| !has_line(board))
+            .map(|&board| canonical(board))
+            .collect();
+        boards.sort_unstable();
+        let start = mix(0, boards.len() as u64);
+        Some(boards.into_iter().map(u64::from).fold(start, mix))
+    }
+
+    // The player who killed the last board lost, quiet positions being
+    // even as far as a static evaluation tells
+    fn evaluate(&self) -> Score {
+        if self.is_over() {
+            WIN
+        } else {
+            DRAW
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use tract_onnx::prelude::*;
+
+use crate::eval::Evaluator;
+use crate::mcts::Oracle;
+use crate::search::{GameState, Score};
+use crate::Game;
+
+// Evaluation of a sure win, well below the decisive range
+const VALUE_SCALE: f32 = 1000.0;
+
+/// Neural network loaded from an ONNX file, predicting policy and value for
+/// boards of one size.
+///
+/// The model takes a `[1, 2, height, width]` f32 tensor, the stones of the
+/// side to move in plane 0 and those of the opponent in plane 1, 1 for a
+/// stone and 0 otherwise. Its first output holds `[1, height * width]`
+/// move logits, squares in row-major order, and its second one a `[1, 1]`
+/// value in [-1, 1] for the side to move. As an [`Oracle`], it guides
+/// searches with the softmax of the logits of the legal moves; as an
+/// [`Evaluator`], it scores positions by their value.
+#[derive(Clone, Debug)]
+pub struct OnnxModel {
+    model: Arc<TypedRunnableModel>,
+    width: u8,
+    height: u8,
+}
+
+impl OnnxModel {
+    /// Load and optimize a model for boards of `width` by `height`
+    /// squares, ONNX errors being reported as invalid data.
+    pub fn load(path: impl AsRef<Path>, width: u8, height: u8) -> io::Result<Self> {
+        let invalid = |e: TractError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let shape = [1, 2, usize::from(height), usize::from(width)];
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .and_then(|model| model.with_input_fact(0, f32::fact(shape).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(invalid)?;
+        Ok(OnnxModel {
+            model,
+            width,
+            height,
+        })
+    }
+
+    // Input tensor of a position
+    fn input(&self, game: &Game) -> Tensor {
+        assert!(
+            game.width() == self.width && game.height() == self.height,
+            "Board doesn't match the model"
+        );
+        let [x, o] = game.players();
+        let planes = if game.turn() == 0 { [x, o] } else { [o, x] };
+        let squares = game.squares();
+        let data: Vec<f32> = planes
+            .iter()
+            .flat_map(|&stones| {
+                (0..squares).map(move |square| f32::from(u8::from(stones.contains(square))))
+            })
+            .collect();
+        let shape = [1, 2, usize::from(self.height), usize::from(self.width)];
+        Tensor::from_shape(&shape, &data).expect("Data fills the shape")
+    }
+
+    /// Move logits, indexed by square, and value of a position.
+    ///
+    /// # Panics
+    ///
+    /// If the board isn't the size of the model, or the model fails to run
+    /// or doesn't have the outputs described in [`OnnxModel`].
+    pub fn infer(&self, game: &Game) -> (Vec<f32>, f32) {
+        let outputs = self
+            .model
+            .run(tvec!(self.input(game).into()))
+            .expect("Model runs on its input shape");
+        let floats = |output: &Tensor| -> TractResult<Vec<f32>> {
+            Ok(output.try_as_plain_ram()?.as_slice::<f32>()?.to_vec())
+        };
+        let logits = floats(&outputs[0]).expect("Policy output is f32");
+        let value = floats(&outputs[1]).expect("Value output is f32")[0];
+        (logits, value)
+    }
+}
+
+impl Oracle<Game> for OnnxModel {
+    fn predict(&self, game: &Game) -> (Vec<f64>, f64) {
+        let (logits, value) = self.infer(game);
+        let logits: Vec<_> = game
+            .legal_moves()
+            .into_iter()
+            .map(|mv| f64::from(logits[game.square(mv) as usize]))
+            .collect();
+        // Shifted by the highest logit not to overflow
+        let highest = logits.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<_> = logits.iter().map(|l| (l - highest).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        let priors = weights.into_iter().map(|weight| weight / total).collect();
+        (priors, (f64::from(value.clamp(-1.0, 1.0)) + 1.0) / 2.0)
+    }
+}
+
+impl Evaluator<Game> for OnnxModel {
+    fn evaluate(&self, game: &Game) -> Score {
+        let (_, value) = self.infer(game);
+        (value.clamp(-1.0, 1.0) * VALUE_SCALE) as Score
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+
+use crate::search::{GameState, Score, LOSS, WIN};
+use crate::tt::mix;
+use crate::wild::WildMove;
+use crate::{Bitboard, BoardError, Game, GameStatus, MoveError, PlayerId};
+
+/// Player aiming for a line of either symbol, moving first.
+pub const ORDER: PlayerId = 0;
+/// Player aiming to fill the board without a line.
+pub const CHAOS: PlayerId = 1;
+
+/// Order and Chaos: both players place either symbol, X or O, on a 6×6
+/// board. Order wins by making five in a row of one symbol, whoever placed
+/// its stones, and Chaos wins by filling the board without one, so there
+/// are no draws.
+///
+/// Like [`Wild`](crate::wild::Wild), the board is a [`Game`] whose two
+/// sides are the symbols.
+#[derive(Clone, Debug)]
+pub struct OrderChaos {
+    board: Game,
+    turn: PlayerId,
+    history: Vec<WildMove>,
+}
+
+impl Default for OrderChaos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderChaos {
+    /// Create an empty 6×6 board with Order to move.
+    pub fn new() -> Self {
+        Self::with_dimensions(6, 6, 5).expect("6×6 is a valid size")
+    }
+
+    /// Create an empty board of `width` columns and `height` rows where
+    /// Order needs `win_length` symbols in a row.
+    pub fn with_dimensions(width: u8, height: u8, win_length: u8) -> Result<Self, BoardError> {
+        Ok(OrderChaos {
+            board: Game::with_dimensions(width, height, win_length)?,
+            turn: ORDER,
+            history: vec![],
+        })
+    }
+
+    /// Player to move, [`ORDER`] or [`CHAOS`].
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[WildMove] {
+        &self.history
+    }
+
+    /// Bitboards of both symbols, bit `square` set for each square holding
+    /// the symbol.
+    pub fn symbols(&self) -> [Bitboard; 2] {
+        self.board.players()
+    }
+
+    /// Board of the symbols, X being its first side and O its second one.
+    pub fn board(&self) -> &Game {
+        &self.board
+    }
+
+    /// Whether a line of one symbol was made, winning the game for Order.
+    pub fn has_line(&self) -> bool {
+        let [x, o] = self.symbols();
+        self.board
+            .wins()
+            .iter()
+            .any(|&mask| x & mask == mask || o & mask == mask)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.has_line() || self.board.is_full()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.has_line() {
+            GameStatus::Won(ORDER)
+        } else if self.board.is_full() {
+            GameStatus::Won(CHAOS)
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// Check that a move can be played in the current position.
+    pub fn check_move(&self, mv: WildMove) -> Result<(), MoveError> {
+        let square = mv.square;
+        let [x, o] = self.symbols();
+        let width = self.board.width();
+        if square.row >= self.board.height() || square.col >= width || mv.symbol > 1 {
+            Err(MoveError::OutOfBounds)
+        } else if (x | o).contains(self.board.square(square)) {
+            Err(MoveError::Occupied)
+        } else if self.is_over() {
+            Err(MoveError::GameOver)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, mv: WildMove) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+
+    // Flip the symbol of a move on its square
+    fn toggle(&mut self, mv: WildMove) {
+        let mut symbols = self.symbols();
+        symbols[usize::from(mv.symbol)] ^= Bitboard::bit(self.board.square(mv.square));
+        self.board.set_players(symbols);
+    }
+
+    // Lines of one symbol only through a square, with the number of
+    // stones on them and the symbol, empty lines excluded
+    fn lines_through(&self, square: u32) -> impl Iterator<Item = (u32, u8)> + '_ {
+        let [x, o] = self.symbols();
+        self.board
+            .wins()
+            .iter()
+            .filter(move |mask| mask.contains(square))
+            .filter_map(
+                move |&mask| match ((x & mask).count_ones(), (o & mask).count_ones()) {
+                    (0, 0) => None,
+                    (stones, 0) => Some((stones, 0)),
+                    (0, stones) => Some((stones, 1)),
+                    _ => None,
+                },
+            )
+    }
+}
+
+// One row per line, X and O for the symbols, . when empty
+impl fmt::Display for OrderChaos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.board)
+    }
+}
+
+impl GameState for OrderChaos {
+    type Move = WildMove;
+
+    fn legal_moves(&self) -> Vec<WildMove> {
+        if self.is_over() {
+            return vec![];
+        }
+        let squares = self.board.moves();
+        let mut moves = Vec::with_capacity(2 * squares.len());
+        for symbol in 0..2 {
+            moves.extend(squares.iter().map(|&square| WildMove::new(square, symbol)));
+        }
+        moves
+    }
+
+    fn apply(&mut self, mv: WildMove) {
+        self.toggle(mv);
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    fn undo(&mut self, mv: WildMove) {
+        self.toggle(mv);
+        self.turn = 1 - self.turn;
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Order extends the longest lines of the symbol it places, Chaos
+    // spoils the longest lines of the other symbol
+    fn move_priority(&self, mv: WildMove) -> i32 {
+        self.lines_through(self.board.square(mv.square))
+            .map(|(stones, symbol)| match (self.turn, symbol == mv.symbol) {
+                (ORDER, true) | (CHAOS, false) => (stones * stones) as i32,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    fn move_index(&self, mv: WildMove) -> Option<usize> {
+        Some(2 * self.board.square(mv.square) as usize + usize::from(mv.symbol))
+    }
+
+    // Keyed like wild tic-tac-toe, the symbols serving both players and
+    // the side to move following from the number of moves
+    fn hash_key(&self) -> Option<u64> {
+        let [x, o] = self.board.canonical();
+        let [x, o] = [x, o].min([o, x]);
+        let words = x.words().into_iter().chain(o.words());
+        Some(words.fold(0, mix))
+    }
+
+    // Order completing a line, or Chaos blocking the squares where it
+    // would, with the other symbol
+    fn forcing_moves(&self) -> Vec<WildMove> {
+        if self.is_over() {
+            return vec![];
+        }
+        let mut moves = vec![];
+        for symbol in 0..2 {
+            let squares = self.board.winning_squares(symbol);
+            let placed = if self.turn == ORDER {
+                symbol
+            } else {
+                1 - symbol
+            };
+            moves.extend(
+                squares
+                    .ones()
+                    .map(|square| WildMove::new(self.board.square_move(square), placed)),
+            );
+        }
+        moves
+    }
+
+    // Lines are Order's wins and full boards Chaos's. In between, lines of
+    // one symbol only favour Order by the square of their stones
+    fn evaluate(&self) -> Score {
+        let sign = if self.turn == ORDER { 1 } else { -1 };
+        if self.has_line() {
+            return sign * WIN;
+        }
+        if self.board.is_full() {
+            return sign * LOSS;
+        }
+        let [x, o] = self.symbols();
+        let order: Score = self
+            .board
+            .wins()
+            .iter()
+            .map(
+                |&mask| match ((x & mask).count_ones(), (o & mask).count_ones()) {
+                    (stones, 0) | (0, stones) => (stones * stones) as Score,
+                    _ => 0,
+                },
+            )
+            .sum();
+        sign * order
+    }
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::search::{GameState, Score, DRAW, LOSS, WIN};
+use crate::tt::mix;
+use crate::{
+    Bitboard, BoardError, GameStatus, Move, MoveError, ParseMoveError, PlayerId, MAX_SIZE,
+};
+
+// Steps to the eight squares around a square
+const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// Score of each legal move the side to move has over the opponent, in
+/// [`Othello`] positions still being played.
+pub const MOBILITY_SCORE: Score = 2;
+
+/// Move of [`Othello`]: a disc placed on the board, or a pass when no
+/// placement flips any disc.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OthelloMove {
+    Place(Move),
+    Pass,
+}
+
+// Printed as the square placed on, or `pass`
+impl fmt::Display for OthelloMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OthelloMove::Place(mv) => write!(f, "{}", mv),
+            OthelloMove::Pass => write!(f, "pass"),
+        }
+    }
+}
+
+// Parses a square to place on, or `pass`
+impl FromStr for OthelloMove {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("pass") {
+            Ok(OthelloMove::Pass)
+        } else {
+            s.parse().map(OthelloMove::Place)
+        }
+    }
+}
+
+/// Othello, or Reversi: each disc placed must outflank a line of the
+/// opponent's discs between it and another disc of the player, which are
+/// all flipped to its color. A player without such a placement passes,
+/// and once neither has one the player with more discs wins.
+///
+/// Discs flipped by each move are kept to undo it, and positions still
+/// being played are scored by the difference in discs and in legal moves
+/// between the players.
+#[derive(Clone, Debug)]
+pub struct Othello {
+    size: u8,
+    stones: [Bitboard; 2],
+    turn: PlayerId,
+    history: Vec<OthelloMove>,
+    // Discs flipped by each move, empty for passes
+    flipped: Vec<Bitboard>,
+}
+
+impl Default for Othello {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Othello {
+    /// Create the 8×8 starting position with the first player, black, to
+    /// move.
+    pub fn new() -> Self {
+        Self::with_size(8).expect("8 is a valid size")
+    }
+
+    /// Create the starting position on an even `size`×`size` board, two
+    /// discs of each player crossed in the center.
+    pub fn with_size(size: u8) -> Result<Self, BoardError> {
+        if !(4..=MAX_SIZE).contains(&size) || size % 2 == 1 {
+            return Err(BoardError::InvalidSize {
+                width: size,
+                height: size,
+            });
+        }
+        let mut game = Othello {
+            size,
+            stones: [Bitboard::EMPTY; 2],
+            turn: 0,
+            history: vec![],
+            flipped: vec![],
+        };
+        let center = size / 2;
+        for (row, col, player) in [
+            (center - 1, center, 0),
+            (center, center - 1, 0),
+            (center - 1, center - 1, 1),
+            (center, center, 1),
+        ] {
+            let square = game.square(Move::new(row, col));
+            game.stones[player].insert(square);
+        }
+        Ok(game)
+    }
+
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    /// Player to move, 0 for black and 1 for white.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Discs of both players on the board.
+    pub fn players(&self) -> [Bitboard; 2] {
+        self.stones
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[OthelloMove] {
+        &self.history
+    }
+
+    /// Number of discs of each player.
+    pub fn discs(&self) -> [u32; 2] {
+        self.stones.map(Bitboard::count_ones)
+    }
+
+    fn square(&self, mv: Move) -> u32 {
+        u32::from(mv.row) * u32::from(self.size) + u32::from(mv.col)
+    }
+
+    fn square_move(&self, square: u32) -> Move {
+        let size = u32::from(self.size);
+        Move::new((square / size) as u8, (square % size) as u8)
+    }
+
+    // Discs of the opponent a player would flip by placing on an empty
+    // square
+    fn flips(&self, square: u32, player: PlayerId) -> Bitboard {
+        let (own, theirs) = (
+            self.stones[usize::from(player)],
+            self.stones[usize::from(1 - player)],
+        );
+        let size = i32::from(self.size);
+        let (row, col) = (square as i32 / size, square as i32 % size);
+        let mut flips = Bitboard::EMPTY;
+        for (dr, dc) in DIRECTIONS {
+            let mut line = Bitboard::EMPTY;
+            let (mut r, mut c) = (row + dr, col + dc);
+            while (0..size).contains(&r) && (0..size).contains(&c) {
+                let next = (r * size + c) as u32;
+                if theirs.contains(next) {
+                    line.insert(next);
+                } else {
+                    if own.contains(next) {
+                        flips |= line;
+                    }
+                    break;
+                }
+                r += dr;
+                c += dc;
+            }
+        }
+        flips
+    }
+
+    // Squares a player can place on
+    fn placements(&self, player: PlayerId) -> Bitboard {
+        let cells = u32::from(self.size) * u32::from(self.size);
+        (Bitboard::below(cells) & !(self.stones[0] | self.stones[1]))
+            .ones()
+            .filter(|&square| !self.flips(square, player).is_empty())
+            .fold(Bitboard::EMPTY, |squares, square| {
+                squares | Bitboard::bit(square)
+            })
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.placements(0).is_empty() && self.placements(1).is_empty()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if !self.is_over() {
+            return GameStatus::InProgress;
+        }
+        let [black, white] = self.discs();
+        match black.cmp(&white) {
+            std::cmp::Ordering::Greater => GameStatus::Won(0),
+            std::cmp::Ordering::Less => GameStatus::Won(1),
+            std::cmp::Ordering::Equal => GameStatus::Draw,
+        }
+    }
+
+    pub fn check_move(&self, mv: OthelloMove) -> Result<(), MoveError> {
+        if self.is_over() {
+            return Err(MoveError::GameOver);
+        }
+        let placements = self.placements(self.turn);
+        match mv {
+            OthelloMove::Pass if placements.is_empty() => Ok(()),
+            OthelloMove::Pass => Err(MoveError::CannotPass),
+            OthelloMove::Place(mv) => {
+                if mv.row >= self.size || mv.col >= self.size {
+                    Err(MoveError::OutOfBounds)
+                } else if (self.stones[0] | self.stones[1]).contains(self.square(mv)) {
+                    Err(MoveError::Occupied)
+                } else if !placements.contains(self.square(mv)) {
+                    Err(MoveError::NoFlip)
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    pub fn make_move(&mut self, mv: OthelloMove) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+}
+
+// One row per line, X for black, O for white, . when empty
+impl fmt::Display for Othello {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.size {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            let cells: Vec<_> = (0..self.size)
+                .map(|col| {
+                    let square = self.square(Move::new(row, col));
+                    if self.stones[0].contains(square) {
+                        "X"
+                    } else if self.stones[1].contains(square) {
+                        "O"
+                    } else {
+                        "."
+                    }
+                })
+                .collect();
+            write!(f, "{}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Othello {
+    type Move = OthelloMove;
+
+    fn legal_moves(&self) -> Vec<OthelloMove> {
+        let placements = self.placements(self.turn);
+        if !placements.is_empty() {
+            placements
+                .ones()
+                .map(|square| OthelloMove::Place(self.square_move(square)))
+                .collect()
+        } else if self.placements(1 - self.turn).is_empty() {
+            vec![]
+        } else {
+            vec![OthelloMove::Pass]
+        }
+    }
+
+    fn apply(&mut self, mv: OthelloMove) {
+        let flipped = match mv {
+            OthelloMove::Place(mv) => {
+                let square = self.square(mv);
+                let flipped = self.flips(square, self.turn);
+                self.stones[usize::from(self.turn)] |= flipped | Bitboard::bit(square);
+                self.stones[usize::from(1 - self.turn)] ^= flipped;
+                flipped
+            }
+            OthelloMove::Pass => Bitboard::EMPTY,
+        };
+        self.flipped.push(flipped);
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    fn undo(&mut self, mv: OthelloMove) {
+        self.turn = 1 - self.turn;
+        let flipped = self.flipped.pop().expect("A move to undo");
+        if let OthelloMove::Place(mv) = mv {
+            self.stones[usize::from(self.turn)] ^= flipped | Bitboard::bit(self.square(mv));
+            self.stones[usize::from(1 - self.turn)] |= flipped;
+        }
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Corners first, as they are never flipped back, and squares next to
+    // them last, as they give them away
+    fn move_priority(&self, mv: OthelloMove) -> i32 {
+        let OthelloMove::Place(mv) = mv else {
+            return 0;
+        };
+        let last = self.size - 1;
+        let edge = |coord: u8| match coord {
+            0 => 0,
+            1 => 1,
+            coord if coord == last => 0,
+            coord if coord == last - 1 => 1,
+            _ => 2,
+        };
+        match (edge(mv.row), edge(mv.col)) {
+            (0, 0) => 3,
+            (0, 1) | (1, 0) | (1, 1) => -1,
+            (0, _) | (_, 0) => 1,
+            _ => 0,
+        }
+    }
+
+    // Squares, then the pass after them
+    fn move_index(&self, mv: OthelloMove) -> Option<usize> {
+        Some(match mv {
+            OthelloMove::Place(mv) => self.square(mv) as usize,
+            OthelloMove::Pass => usize::from(self.size) * usize::from(self.size),
+        })
+    }
+
+    fn hash_key(&self) -> Option<u64> {
+        let words = self.stones[0]
+            .words()
+            .into_iter()
+            .chain(self.stones[1].words());
+        Some(words.fold(mix(0, u64::from(self.turn)), mix))
+    }
+
+    fn evaluate(&self) -> Score {
+        let [player, opponent] = [self.turn, 1 - self.turn];
+        let discs = self.discs();
+        let discs = discs[usize::from(player)] as Score - discs[usize::from(opponent)] as Score;
+        let mobility = |player| self.placements(player).count_ones() as Score;
+        let (ours, theirs) = (mobility(player), mobility(opponent));
+        if ours == 0 && theirs == 0 {
+            return match discs.signum() {
+                1 => WIN,
+                -1 => LOSS,
+                _ => DRAW,
+            };
+        }
+        discs + (ours - theirs) * MOBILITY_SCORE
+    }
+}
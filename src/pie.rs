@@ -0,0 +1,254 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::clock::{Clock, TimeControl};
+use crate::player::Player;
+use crate::search::{GameState, Score, DRAW};
+use crate::tt::mix;
+use crate::{Game, GameStatus, PlayerId};
+
+/// Action of a game under the pie rule, a move of the underlying game or
+/// the swap of sides offered to the second player once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PieMove<M> {
+    Place(M),
+    Swap,
+}
+
+// Printed as the move of the underlying game, or `swap`
+impl<M: fmt::Display> fmt::Display for PieMove<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PieMove::Place(mv) => write!(f, "{}", mv),
+            PieMove::Swap => write!(f, "swap"),
+        }
+    }
+}
+
+// Parses `swap`, or a move of the underlying game
+impl<M: FromStr> FromStr for PieMove<M> {
+    type Err = M::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("swap") {
+            Ok(PieMove::Swap)
+        } else {
+            s.parse().map(PieMove::Place)
+        }
+    }
+}
+
+/// Any game under the pie rule: once the first player moved, the second
+/// one may take that move as its own and swap sides, the first player then
+/// moving for the second side. The first player is so better off opening
+/// with a move leaving the game as even as possible.
+///
+/// Players are the people at the board, player 0 moving first, and sides
+/// the colours of the underlying game. The player to move always holds
+/// the side to move, so searches play the swap like any other move.
+#[derive(Clone, Debug)]
+pub struct Pie<G> {
+    game: G,
+    turn: PlayerId,
+    // Actions played, swaps included
+    plies: u32,
+    swapped: bool,
+}
+
+impl<G: Default> Default for Pie<G> {
+    fn default() -> Self {
+        Self::new(G::default())
+    }
+}
+
+impl<G> Pie<G> {
+    /// Play a game under the pie rule from its starting position.
+    pub fn new(game: G) -> Self {
+        Pie {
+            game,
+            turn: 0,
+            plies: 0,
+            swapped: false,
+        }
+    }
+
+    /// Player to move, 0 for the player who moved first and 1 for the
+    /// other one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Position of the underlying game.
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    /// Whether the second player swapped sides.
+    pub fn is_swapped(&self) -> bool {
+        self.swapped
+    }
+
+    /// Whether the player to move may swap sides.
+    pub fn can_swap(&self) -> bool {
+        self.plies == 1 && !self.swapped
+    }
+
+    /// Side of the underlying game a player holds.
+    pub fn side(&self, player: PlayerId) -> PlayerId {
+        if self.swapped {
+            1 - player
+        } else {
+            player
+        }
+    }
+}
+
+impl<G: GameState> Pie<G> {
+    pub fn is_over(&self) -> bool {
+        self.game.is_terminal()
+    }
+
+    /// Outcome of the game by player rather than by side, from the exact
+    /// score of the final position.
+    pub fn status(&self) -> GameStatus {
+        if !self.is_over() {
+            return GameStatus::InProgress;
+        }
+        match self.game.evaluate().cmp(&DRAW) {
+            std::cmp::Ordering::Less => GameStatus::Won(1 - self.turn),
+            std::cmp::Ordering::Greater => GameStatus::Won(self.turn),
+            std::cmp::Ordering::Equal => GameStatus::Draw,
+        }
+    }
+
+    /// Play an action, rejecting those not in [`GameState::legal_moves`].
+    pub fn make_move(&mut self, mv: PieMove<G::Move>) -> Result<(), IllegalPieMove> {
+        if !self.legal_moves().contains(&mv) {
+            return Err(IllegalPieMove);
+        }
+        self.apply(mv);
+        Ok(())
+    }
+}
+
+/// Error returned by [`Pie::make_move`] for an action that can't be played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IllegalPieMove;
+
+impl fmt::Display for IllegalPieMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "move can't be played, or sides can't be swapped")
+    }
+}
+
+impl std::error::Error for IllegalPieMove {}
+
+// The underlying game, with a line saying whether sides were swapped
+impl<G: fmt::Display> fmt::Display for Pie<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.game)?;
+        if self.swapped {
+            write!(f, "\nsides swapped")?;
+        }
+        Ok(())
+    }
+}
+
+impl<G: GameState> GameState for Pie<G> {
+    type Move = PieMove<G::Move>;
+
+    fn legal_moves(&self) -> Vec<PieMove<G::Move>> {
+        let mut moves: Vec<_> = self
+            .game
+            .legal_moves()
+            .into_iter()
+            .map(PieMove::Place)
+            .collect();
+        if self.can_swap() && !moves.is_empty() {
+            moves.push(PieMove::Swap);
+        }
+        moves
+    }
+
+    fn apply(&mut self, mv: PieMove<G::Move>) {
+        match mv {
+            PieMove::Place(mv) => self.game.apply(mv),
+            PieMove::Swap => self.swapped = true,
+        }
+        self.turn = 1 - self.turn;
+        self.plies += 1;
+    }
+
+    fn undo(&mut self, mv: PieMove<G::Move>) {
+        match mv {
+            PieMove::Place(mv) => self.game.undo(mv),
+            PieMove::Swap => self.swapped = false,
+        }
+        self.turn = 1 - self.turn;
+        self.plies -= 1;
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    fn move_priority(&self, mv: PieMove<G::Move>) -> i32 {
+        match mv {
+            PieMove::Place(mv) => self.game.move_priority(mv),
+            PieMove::Swap => 0,
+        }
+    }
+
+    // The swap comes before the squares of the underlying game
+    fn move_index(&self, mv: PieMove<G::Move>) -> Option<usize> {
+        match mv {
+            PieMove::Place(mv) => self.game.move_index(mv).map(|index| index + 1),
+            PieMove::Swap => Some(0),
+        }
+    }
+
+    // Positions are worth the same whoever holds which side, but not
+    // whether the swap is still on offer
+    fn hash_key(&self) -> Option<u64> {
+        let key = self.game.hash_key()?;
+        Some(mix(key, u64::from(self.can_swap())))
+    }
+
+    fn forcing_moves(&self) -> Vec<PieMove<G::Move>> {
+        self.game
+            .forcing_moves()
+            .into_iter()
+            .map(PieMove::Place)
+            .collect()
+    }
+
+    fn evaluate(&self) -> Score {
+        self.game.evaluate()
+    }
+}
+
+/// Play a game under the pie rule to the end between two players under a
+/// time control, like [`clock::play_timed`](crate::clock::play_timed). The
+/// outcome is by player, whichever side each one ended up with.
+pub fn play_timed(
+    game: &mut Pie<Game>,
+    players: [&mut dyn Player<Pie<Game>>; 2],
+    control: TimeControl,
+) -> GameStatus {
+    let mut clock = Clock::new(control);
+    while !game.is_over() {
+        let turn = game.turn();
+        clock.start(turn);
+        let mv = players[turn as usize].choose_move_timed(
+            game,
+            clock.remaining(turn),
+            control.increment,
+        );
+        if !clock.stop() {
+            return GameStatus::Won(1 - turn);
+        }
+        game.make_move(mv).expect("Illegal move chosen");
+    }
+    game.status()
+}
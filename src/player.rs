@@ -0,0 +1,924 @@
+use std::fmt;
+use std::io::{self, BufRead, StdinLock, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::book::Book;
+use crate::clock;
+use crate::eval::THREAT_SCORE;
+use crate::expectimax::{Expectimax, Opponent};
+use crate::mcts::{Mcts, MctsOptions, Oracle};
+use crate::pie::{Pie, PieMove};
+use crate::pns::DfPn;
+use crate::qlearning::QTable;
+use crate::reinforce::Policy;
+use crate::search::{
+    describe_score, is_decisive, GameState, Outcome, RootNoise, Score, Search, SearchLimits,
+    SearchOptions, SearchResult, DRAW,
+};
+use crate::tablebase::Tablebase;
+use crate::{BoardView, Game, Move};
+
+/// Strategy picking the next move of a game.
+pub trait Player<G: GameState = Game> {
+    /// Choose a move for the side to move. The game must not be over.
+    fn choose_move(&mut self, game: &G) -> G::Move;
+
+    /// Choose a move with `remaining` time left on the clock, `increment`
+    /// being added after the move. Players without time management ignore
+    /// the clock.
+    fn choose_move_timed(
+        &mut self,
+        game: &G,
+        _remaining: Duration,
+        _increment: Duration,
+    ) -> G::Move {
+        self.choose_move(game)
+    }
+
+    /// Choose a move, or none when the player gives up the game, as humans
+    /// may. Other players always choose one.
+    fn try_choose_move(&mut self, game: &G) -> Option<G::Move> {
+        Some(self.choose_move(game))
+    }
+}
+
+/// Plays the best move found by a negamax search, either at a fixed depth
+/// or deepening iteratively within a time or node budget.
+#[derive(Debug)]
+pub struct NegamaxPlayer {
+    pub depth: u8,
+    /// Time allowed per move, the search then stops at `depth` at most.
+    pub time: Option<Duration>,
+    /// Positions visited per move, the search then stops at `depth` at
+    /// most.
+    pub max_nodes: Option<u32>,
+    pub options: SearchOptions,
+    /// Nodes of a df-pn search looking for a forced win before searching,
+    /// its winning move being played when found.
+    pub proof_nodes: Option<usize>,
+    /// Draw moves from a softmax of their scores at this temperature, see
+    /// [`Search::root_policy`], rather than playing the best one. Every
+    /// move is then searched to `depth`, without time nor node limits.
+    pub temperature: Option<f64>,
+    /// Dirichlet noise mixed into the move probabilities, drawing moves
+    /// like `temperature` does, at temperature 0 when not set.
+    pub noise: Option<RootNoise>,
+    /// Chance of playing the second best move of a search to `depth`
+    /// rather than the best one, for players meant to be beaten.
+    pub blunder_rate: f64,
+    /// Style picking among the moves scoring about as well as the best
+    /// one, see [`Personality`]. Every move is then searched to `depth`,
+    /// without time nor node limits, nor temperature.
+    pub personality: Option<Personality>,
+    /// Number of positions visited over all searches of this player.
+    pub evaluations: u32,
+    rng: StdRng,
+}
+
+impl NegamaxPlayer {
+    pub fn new(depth: u8) -> Self {
+        NegamaxPlayer {
+            depth,
+            time: None,
+            max_nodes: None,
+            options: SearchOptions::default(),
+            proof_nodes: None,
+            temperature: None,
+            noise: None,
+            blunder_rate: 0.0,
+            personality: None,
+            evaluations: 0,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Same player drawing its random choices from `seed`, the seeds of
+    /// its searches included.
+    pub fn seeded(self, seed: u64) -> Self {
+        NegamaxPlayer {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Player searching to the end of the game, playing perfectly.
+    pub fn perfect() -> Self {
+        Self::new(u8::MAX)
+    }
+
+    /// Player thinking for `time` per move, without depth limit.
+    pub fn timed(time: Duration) -> Self {
+        NegamaxPlayer {
+            time: Some(time),
+            ..Self::new(u8::MAX)
+        }
+    }
+
+    /// Player visiting `nodes` positions per move, without depth limit.
+    pub fn with_nodes(nodes: u32) -> Self {
+        NegamaxPlayer {
+            max_nodes: Some(nodes),
+            ..Self::new(u8::MAX)
+        }
+    }
+}
+
+impl<G: GameState + Clone> Player<G> for NegamaxPlayer {
+    fn choose_move(&mut self, game: &G) -> G::Move {
+        let time = self.time;
+        self.choose_move_within(game, time)
+    }
+
+    // Think for a share of the clock, within the time per move if set
+    fn choose_move_timed(&mut self, game: &G, remaining: Duration, increment: Duration) -> G::Move {
+        let moves = game.legal_moves().len().div_ceil(2) as u32;
+        let share = clock::allocate(remaining, increment, moves);
+        let time = self.time.map_or(share, |time| time.min(share));
+        self.choose_move_within(game, Some(time))
+    }
+}
+
+impl NegamaxPlayer {
+    fn choose_move_within<G: GameState + Clone>(
+        &mut self,
+        game: &G,
+        time: Option<Duration>,
+    ) -> G::Move {
+        let game = &mut game.clone();
+        let depth = self.depth.max(1);
+        if self.blunder_rate > 0.0 && self.rng.gen_bool(self.blunder_rate.min(1.0)) {
+            let mut search = Search::with_options(SearchOptions {
+                seed: Some(self.rng.gen()),
+                ..self.options
+            });
+            let lines = search.multipv(game, depth, 2);
+            self.evaluations += search.evaluations;
+            // The only move when there is no other
+            return lines
+                .last()
+                .and_then(SearchResult::best)
+                .expect("Can't chose from 0 moves");
+        }
+        if let Some(nodes) = self.proof_nodes {
+            let mut proof = DfPn::new(nodes);
+            let win = proof.winning_move(game);
+            self.evaluations += proof.nodes as u32;
+            if let Some(mv) = win {
+                return mv;
+            }
+        }
+        let mut search = Search::with_options(SearchOptions {
+            seed: Some(self.rng.gen()),
+            ..self.options
+        });
+        if let Some(personality) = self.personality {
+            let mv = personality.choose(&mut search, game, depth, &mut self.rng);
+            self.evaluations += search.evaluations;
+            return mv;
+        }
+        if self.temperature.is_some() || self.noise.is_some() {
+            let temperature = self.temperature.unwrap_or(0.0);
+            let mut policy = search.root_policy(game, depth, temperature);
+            self.evaluations += search.evaluations;
+            if let Some(noise) = self.noise {
+                noise.apply(&mut policy, &mut self.rng);
+            }
+            return policy
+                .choose_weighted(&mut self.rng, |&(_, p)| p)
+                .expect("Can't chose from 0 moves")
+                .0;
+        }
+        let result = if time.is_some() || self.max_nodes.is_some() {
+            let limits = SearchLimits {
+                depth: Some(depth),
+                max_nodes: self.max_nodes,
+                time,
+            };
+            search.search_limited(game, limits)
+        } else {
+            search.search(game, depth)
+        };
+        self.evaluations += search.evaluations;
+        result.best().expect("Can't chose from 0 moves")
+    }
+}
+
+/// Named strength of a [`NegamaxPlayer`], for players who'd rather not
+/// pick search depths. Weaker levels search shallower, draw their moves
+/// from a softmax of the scores and more often blunder, playing their
+/// second best move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Perfect,
+}
+
+impl Difficulty {
+    /// Every level, weakest first.
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Perfect,
+    ];
+
+    /// Chance of a blunder on each move at this level, see
+    /// [`NegamaxPlayer::blunder_rate`].
+    pub fn blunder_rate(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.3,
+            Difficulty::Medium => 0.1,
+            Difficulty::Hard => 0.02,
+            Difficulty::Perfect => 0.0,
+        }
+    }
+
+    /// Player of this strength.
+    pub fn player(self) -> NegamaxPlayer {
+        let (depth, temperature) = match self {
+            Difficulty::Easy => (1, Some(60.0)),
+            Difficulty::Medium => (2, Some(20.0)),
+            Difficulty::Hard => (4, None),
+            Difficulty::Perfect => (u8::MAX, None),
+        };
+        NegamaxPlayer {
+            temperature,
+            blunder_rate: self.blunder_rate(),
+            ..NegamaxPlayer::new(depth)
+        }
+    }
+}
+
+// Printed as its lowercase name
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Perfect => "perfect",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Error returned when a string names no [`Difficulty`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseDifficultyError(String);
+
+impl fmt::Display for ParseDifficultyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected easy, medium, hard or perfect, got {:?}",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseDifficultyError {}
+
+impl FromStr for Difficulty {
+    type Err = ParseDifficultyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|level| level.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ParseDifficultyError(s.to_string()))
+    }
+}
+
+/// Style of a [`NegamaxPlayer`], so that games against it vary while it
+/// plays as well. Among the root moves keeping the outcome of the best one
+/// and, short of the end of the game, scoring within [`STYLE_MARGIN`] of
+/// it, the player picks the one its style values most, ties being drawn at
+/// random.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Personality {
+    /// Values its own threats twice as much as those of the opponent, see
+    /// [`GameState::threat_scores`].
+    Aggressive,
+    /// Values the threats of the opponent twice as much as its own.
+    Defensive,
+    /// Values the replies of the opponent that lose, setting traps for
+    /// opponents who don't see the end of the game.
+    Tricky,
+}
+
+/// Score a [`Personality`] gives up at most over the best move, in
+/// positions whose outcome searches don't tell.
+pub const STYLE_MARGIN: Score = THREAT_SCORE;
+
+impl Personality {
+    /// Every style.
+    pub const ALL: [Personality; 3] = [
+        Personality::Aggressive,
+        Personality::Defensive,
+        Personality::Tricky,
+    ];
+
+    // Choose a move of this style from the root moves searched to `depth`
+    fn choose<G: GameState>(
+        self,
+        search: &mut Search<G>,
+        game: &mut G,
+        depth: u8,
+        rng: &mut StdRng,
+    ) -> G::Move {
+        let lines = search.multipv(game, depth, usize::MAX);
+        let best = lines.first().expect("Can't chose from 0 moves").score;
+        let outcome = Outcome::from_score(best);
+        let mut styled = vec![];
+        for line in &lines {
+            let close = is_decisive(best) || line.score.saturating_add(STYLE_MARGIN) >= best;
+            if Outcome::from_score(line.score) != outcome || !close {
+                continue;
+            }
+            let mv = line.best().expect("Root lines start with their move");
+            game.apply(mv);
+            // The opponent is to move
+            let [theirs, ours] = game.threat_scores();
+            let value = match self {
+                Personality::Aggressive => 2 * ours - theirs,
+                Personality::Defensive => ours - 2 * theirs,
+                Personality::Tricky => losing_replies(search, game, depth) as Score,
+            };
+            game.undo(mv);
+            styled.push((mv, value));
+        }
+        let top = styled.iter().map(|&(_, value)| value).max();
+        let favourites: Vec<_> = styled
+            .into_iter()
+            .filter(|&(_, value)| Some(value) == top)
+            .collect();
+        favourites
+            .choose(rng)
+            .expect("The best move keeps its outcome")
+            .0
+    }
+}
+
+// Number of replies of the opponent to move leaving it lost, searched
+// two plies shallower than the root
+fn losing_replies<G: GameState>(search: &mut Search<G>, game: &mut G, depth: u8) -> usize {
+    let depth = depth.saturating_sub(2).max(1);
+    let mut losing = 0;
+    for reply in game.legal_moves() {
+        game.apply(reply);
+        let score = search.search(game, depth).score;
+        game.undo(reply);
+        if is_decisive(score) && score > DRAW {
+            losing += 1;
+        }
+    }
+    losing
+}
+
+// Printed as its lowercase name
+impl fmt::Display for Personality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Personality::Aggressive => "aggressive",
+            Personality::Defensive => "defensive",
+            Personality::Tricky => "tricky",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Error returned when a string names no [`Personality`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePersonalityError(String);
+
+impl fmt::Display for ParsePersonalityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected aggressive, defensive or tricky, got {:?}",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParsePersonalityError {}
+
+impl FromStr for Personality {
+    type Err = ParsePersonalityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Personality::ALL
+            .into_iter()
+            .find(|style| style.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| ParsePersonalityError(s.to_string()))
+    }
+}
+
+/// Plays the move found by a Monte Carlo tree search, see [`Mcts`].
+#[derive(Clone, Debug)]
+pub struct MctsPlayer {
+    /// Playouts per move.
+    pub iterations: u32,
+    pub options: MctsOptions,
+    /// Number of playouts over all searches of this player.
+    pub playouts: u32,
+    rng: StdRng,
+}
+
+impl MctsPlayer {
+    pub fn new(iterations: u32) -> Self {
+        MctsPlayer {
+            iterations,
+            options: MctsOptions::default(),
+            playouts: 0,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Same player drawing the seeds of its searches from `seed`.
+    pub fn seeded(self, seed: u64) -> Self {
+        MctsPlayer {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Player scoring the leaves of its tree by negamax searches to `depth`
+    /// rather than by playouts.
+    pub fn hybrid(iterations: u32, depth: u8) -> Self {
+        MctsPlayer {
+            options: MctsOptions {
+                leaf_depth: Some(depth),
+                ..MctsOptions::default()
+            },
+            ..Self::new(iterations)
+        }
+    }
+}
+
+impl<G: GameState + Clone> Player<G> for MctsPlayer {
+    fn choose_move(&mut self, game: &G) -> G::Move {
+        let options = MctsOptions {
+            seed: Some(self.rng.gen()),
+            ..self.options
+        };
+        let mut search = Mcts::with_options(self.iterations, options);
+        let best = search.best_move(&mut game.clone());
+        self.playouts += search.playouts;
+        best.expect("Can't chose from 0 moves")
+    }
+}
+
+/// Plays the most visited move of a Monte Carlo tree search guided by an
+/// oracle, see [`Mcts::guided_visits`].
+#[derive(Clone, Debug)]
+pub struct GuidedMctsPlayer<'a, O> {
+    /// Simulations per move.
+    pub iterations: u32,
+    pub options: MctsOptions,
+    pub oracle: &'a O,
+    rng: StdRng,
+}
+
+impl<'a, O> GuidedMctsPlayer<'a, O> {
+    pub fn new(iterations: u32, oracle: &'a O) -> Self {
+        GuidedMctsPlayer {
+            iterations,
+            options: MctsOptions::default(),
+            oracle,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Same player drawing the seeds of its searches from `seed`.
+    pub fn seeded(self, seed: u64) -> Self {
+        GuidedMctsPlayer {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+}
+
+impl<G: GameState + Clone, O: Oracle<G>> Player<G> for GuidedMctsPlayer<'_, O> {
+    fn choose_move(&mut self, game: &G) -> G::Move {
+        let options = MctsOptions {
+            seed: Some(self.rng.gen()),
+            ..self.options
+        };
+        let mut search = Mcts::with_options(self.iterations, options);
+        search
+            .best_move_guided(&mut game.clone(), self.oracle)
+            .expect("Can't chose from 0 moves")
+    }
+}
+
+/// Plays the move with the best expected score against an opponent
+/// modeled as random, see [`Expectimax`].
+#[derive(Clone, Debug)]
+pub struct ExpectimaxPlayer {
+    pub depth: u8,
+    pub opponent: Opponent,
+    /// Number of positions visited over all searches of this player.
+    pub evaluations: u32,
+}
+
+impl ExpectimaxPlayer {
+    pub fn new(depth: u8, opponent: Opponent) -> Self {
+        ExpectimaxPlayer {
+            depth,
+            opponent,
+            evaluations: 0,
+        }
+    }
+}
+
+impl<G: GameState + Clone> Player<G> for ExpectimaxPlayer {
+    fn choose_move(&mut self, game: &G) -> G::Move {
+        let mut search = Expectimax::new(self.opponent);
+        let best = search.best_move(&mut game.clone(), self.depth.max(1));
+        self.evaluations += search.evaluations;
+        best.expect("Can't chose from 0 moves").0
+    }
+}
+
+/// Plays uniformly at random among legal moves.
+#[derive(Clone, Debug)]
+pub struct RandomPlayer {
+    rng: StdRng,
+}
+
+impl Default for RandomPlayer {
+    fn default() -> Self {
+        RandomPlayer {
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl RandomPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Player drawing its moves from `seed`.
+    pub fn seeded(seed: u64) -> Self {
+        RandomPlayer {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl<G: GameState> Player<G> for RandomPlayer {
+    fn choose_move(&mut self, game: &G) -> G::Move {
+        *game
+            .legal_moves()
+            .choose(&mut self.rng)
+            .expect("Can't chose from 0 moves")
+    }
+}
+
+/// Plays optimal moves read from a tablebase, without searching.
+#[derive(Clone, Debug)]
+pub struct TablebasePlayer<'a> {
+    pub table: &'a Tablebase,
+    rng: StdRng,
+}
+
+impl<'a> TablebasePlayer<'a> {
+    pub fn new(table: &'a Tablebase) -> Self {
+        TablebasePlayer {
+            table,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Same player choosing among equally good moves from `seed`.
+    pub fn seeded(self, seed: u64) -> Self {
+        TablebasePlayer {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+}
+
+impl TablebasePlayer<'static> {
+    /// Player of the classic 3×3 game, see [`Tablebase::classic`].
+    pub fn classic() -> Self {
+        Self::new(Tablebase::classic())
+    }
+}
+
+impl Player for TablebasePlayer<'_> {
+    fn choose_move(&mut self, game: &Game) -> Move {
+        self.table
+            .best_move(game, &mut self.rng)
+            .expect("Position missing from the tablebase")
+    }
+}
+
+/// Plays the moves with the highest learned values of a Q-table, see
+/// [`QLearning`](crate::qlearning::QLearning).
+#[derive(Clone, Debug)]
+pub struct QPlayer<'a> {
+    pub table: &'a QTable,
+    rng: StdRng,
+}
+
+impl<'a> QPlayer<'a> {
+    pub fn new(table: &'a QTable) -> Self {
+        QPlayer {
+            table,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Same player choosing among equally valued moves from `seed`.
+    pub fn seeded(self, seed: u64) -> Self {
+        QPlayer {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+}
+
+impl Player for QPlayer<'_> {
+    fn choose_move(&mut self, game: &Game) -> Move {
+        self.table
+            .best_move(game, &mut self.rng)
+            .expect("Can't chose from 0 moves")
+    }
+}
+
+/// Plays moves drawn from a learned stochastic policy, see
+/// [`Reinforce`](crate::reinforce::Reinforce).
+#[derive(Clone, Debug)]
+pub struct PolicyPlayer<'a> {
+    pub policy: &'a Policy,
+    rng: StdRng,
+}
+
+impl<'a> PolicyPlayer<'a> {
+    pub fn new(policy: &'a Policy) -> Self {
+        PolicyPlayer {
+            policy,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Same player drawing its moves from `seed`.
+    pub fn seeded(self, seed: u64) -> Self {
+        PolicyPlayer {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+}
+
+impl Player for PolicyPlayer<'_> {
+    fn choose_move(&mut self, game: &Game) -> Move {
+        self.policy
+            .sample(game, &mut self.rng)
+            .expect("Can't chose from 0 moves")
+    }
+}
+
+/// Plays replies drawn from an opening book while the game is in book,
+/// then the moves of another player.
+#[derive(Clone, Debug)]
+pub struct BookPlayer<'a, P> {
+    pub book: &'a Book,
+    pub fallback: P,
+    rng: StdRng,
+}
+
+impl<'a, P> BookPlayer<'a, P> {
+    pub fn new(book: &'a Book, fallback: P) -> Self {
+        BookPlayer {
+            book,
+            fallback,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Same player drawing book replies from `seed`, the fallback player
+    /// being seeded on its own.
+    pub fn seeded(self, seed: u64) -> Self {
+        BookPlayer {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+}
+
+impl<P: Player> Player for BookPlayer<'_, P> {
+    fn choose_move(&mut self, game: &Game) -> Move {
+        self.book
+            .probe(game.history(), &mut self.rng)
+            .filter(|&mv| game.check_move(mv).is_ok())
+            .unwrap_or_else(|| self.fallback.choose_move(game))
+    }
+}
+
+/// Time the engine thinks for each hint a [`HumanPlayer`] asks for.
+pub const HINT_TIME: Duration = Duration::from_millis(200);
+
+/// Reads squares such as `b2` typed by a human on a board labelled with
+/// their coordinates, asking again until a legal move is entered. Typing
+/// `hint` shows the move the engine would play on the board, with its
+/// evaluation and principal variation. Typing `quit` or closing the input
+/// gives up the game, see [`Player::try_choose_move`]. Interactive games
+/// reading moves with [`HumanPlayer::act`] also take back and replay moves.
+pub struct HumanPlayer<R> {
+    input: R,
+    /// Draw boards in color, see [`BoardView::colored`].
+    pub colored: bool,
+}
+
+impl HumanPlayer<StdinLock<'static>> {
+    /// Human reading moves from the standard input.
+    pub fn stdin() -> Self {
+        Self::new(io::stdin().lock())
+    }
+}
+
+impl<R: BufRead> HumanPlayer<R> {
+    pub fn new(input: R) -> Self {
+        HumanPlayer {
+            input,
+            colored: false,
+        }
+    }
+
+    // Board of the game labelled with coordinates, in color if asked
+    fn board<'a>(&self, game: &'a Game) -> BoardView<'a> {
+        let board = game.labelled();
+        if self.colored {
+            board.colored()
+        } else {
+            board
+        }
+    }
+
+    // Print a prompt and read the line typed in answer, none once the
+    // input is closed or the human quits
+    fn ask(&mut self, prompt: &str) -> Option<String> {
+        print!("{}", prompt);
+        io::stdout().flush().expect("Can't flush stdout");
+        let mut line = String::new();
+        if self.input.read_line(&mut line).expect("Can't read input") == 0 {
+            println!();
+            return None;
+        }
+        let line = line.trim();
+        (!line.eq_ignore_ascii_case("quit")).then(|| line.to_string())
+    }
+}
+
+// Search done for a hint, thinking for `HINT_TIME`
+fn hint<G: GameState + Clone>(game: &G) -> SearchResult<G::Move> {
+    Search::new().iterative_deepening(&mut game.clone(), u8::MAX, HINT_TIME)
+}
+
+// Hinted move with its score for the side to move and the line expected
+fn hint_line<M: Copy + fmt::Display>(result: &SearchResult<M>) -> String {
+    let pv: Vec<_> = result.pv.iter().map(M::to_string).collect();
+    format!(
+        "Hint: {}, evaluation {}, line {}",
+        result
+            .best()
+            .expect("Hints are asked for games still going on"),
+        describe_score(result.score),
+        pv.join(" ")
+    )
+}
+
+/// Answer of a human to [`HumanPlayer::act`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Play(Move),
+    /// Take back the last move of the human, the reply to it included.
+    Undo,
+    /// Play again the moves of the last undo.
+    Redo,
+    /// Save the game to a file, typed `:save FILE`.
+    Save(PathBuf),
+}
+
+impl<R: BufRead> HumanPlayer<R> {
+    /// Draw the board and read a move, `undo` and `redo` to go back and
+    /// forth in the game or `:save FILE`, none when the human quits.
+    pub fn act(&mut self, game: &Game) -> Option<Action> {
+        self.read(game, true)
+    }
+
+    // Draw the board and read an action, `undo`, `redo` and `:save` being
+    // moves like others unless `takeback` is set
+    fn read(&mut self, game: &Game, takeback: bool) -> Option<Action> {
+        println!("{}", self.board(game));
+        let prompt = format!(
+            "Move (a1-{}, hint{} or quit): ",
+            Move::new(game.height() - 1, game.width() - 1),
+            if takeback {
+                ", undo, redo, :save FILE"
+            } else {
+                ""
+            }
+        );
+        loop {
+            let line = self.ask(&prompt)?;
+            if line.eq_ignore_ascii_case("hint") {
+                let result = hint(game);
+                let mv = result.best().expect("A move");
+                println!("{}", self.board(game).highlight(mv));
+                println!("{}", hint_line(&result));
+                continue;
+            } else if takeback && line.eq_ignore_ascii_case("undo") {
+                return Some(Action::Undo);
+            } else if takeback && line.eq_ignore_ascii_case("redo") {
+                return Some(Action::Redo);
+            } else if let Some(path) = line.strip_prefix(":save").filter(|_| takeback) {
+                match path.trim() {
+                    "" => println!("Missing file, type :save FILE"),
+                    path => return Some(Action::Save(path.into())),
+                }
+                continue;
+            }
+            match line.parse() {
+                Ok(mv) => match game.check_move(mv) {
+                    Ok(()) => return Some(Action::Play(mv)),
+                    Err(e) => println!("Illegal move: {}", e),
+                },
+                Err(e) => println!("Invalid move: {}", e),
+            }
+        }
+    }
+}
+
+// Draws the board before reading a move
+impl<R: BufRead> Player for HumanPlayer<R> {
+    fn choose_move(&mut self, game: &Game) -> Move {
+        self.try_choose_move(game)
+            .expect("Input closed before a move was chosen")
+    }
+
+    fn try_choose_move(&mut self, game: &Game) -> Option<Move> {
+        match self.read(game, false)? {
+            Action::Play(mv) => Some(mv),
+            Action::Undo | Action::Redo | Action::Save(_) => {
+                unreachable!("Moves are only taken back on request")
+            }
+        }
+    }
+}
+
+// Also reads `swap` when sides may be swapped
+impl<R: BufRead> Player<Pie<Game>> for HumanPlayer<R> {
+    fn choose_move(&mut self, pie: &Pie<Game>) -> PieMove<Move> {
+        self.try_choose_move(pie)
+            .expect("Input closed before a move was chosen")
+    }
+
+    fn try_choose_move(&mut self, pie: &Pie<Game>) -> Option<PieMove<Move>> {
+        let game = pie.game();
+        println!("{}", self.board(game));
+        if pie.is_swapped() {
+            println!("sides swapped");
+        }
+        let prompt = format!(
+            "Move (a1-{}{}, hint or quit): ",
+            Move::new(game.height() - 1, game.width() - 1),
+            if pie.can_swap() { ", swap" } else { "" }
+        );
+        loop {
+            let line = self.ask(&prompt)?;
+            if line.eq_ignore_ascii_case("hint") {
+                let result = hint(pie);
+                match result.best().expect("A move") {
+                    PieMove::Place(mv) => println!("{}", self.board(game).highlight(mv)),
+                    PieMove::Swap => println!("{}", self.board(game)),
+                }
+                println!("{}", hint_line(&result));
+                continue;
+            }
+            match line.parse() {
+                Ok(PieMove::Swap) if pie.can_swap() => return Some(PieMove::Swap),
+                Ok(PieMove::Swap) => println!("Illegal move: sides can't be swapped"),
+                Ok(PieMove::Place(mv)) => match game.check_move(mv) {
+                    Ok(()) => return Some(PieMove::Place(mv)),
+                    Err(e) => println!("Illegal move: {}", e),
+                },
+                Err(e) => println!("Invalid move: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+
+use crate::search::{GameState, Outcome, DRAW};
+
+/// Default number of nodes a [`ProofSearch`] may build per question.
+pub const MAX_NODES: usize = 1 << 22;
+
+// Proof and disproof numbers of solved nodes
+const INFINITY: u32 = u32::MAX;
+
+/// Proof-number search, proving the outcome of a position with the rules
+/// only, without depth limit nor heuristic.
+#[derive(Clone, Debug)]
+pub struct ProofSearch {
+    /// Largest tree built for one question, the search gives up past it.
+    pub max_nodes: usize,
+    /// Number of nodes built so far.
+    pub nodes: usize,
+}
+
+impl Default for ProofSearch {
+    fn default() -> Self {
+        Self::new(MAX_NODES)
+    }
+}
+
+// Tree node, proving that the side to move at the root reaches its goal
+struct Node<M> {
+    // Move leading to the node, None at the root
+    mv: Option<M>,
+    parent: usize,
+    children: Vec<usize>,
+    // Whether the side to move at the root is to move here
+    or: bool,
+    proof: u32,
+    disproof: u32,
+}
+
+impl ProofSearch {
+    pub fn new(max_nodes: usize) -> Self {
+        ProofSearch {
+            max_nodes,
+            nodes: 0,
+        }
+    }
+
+    /// Outcome of the position for the side to move, `None` when it could
+    /// not be proven within `max_nodes`. Asks whether the side to move wins,
+    /// then whether it avoids losing.
+    pub fn solve<G: GameState>(&mut self, game: &mut G) -> Option<Outcome> {
+        if game.is_terminal() {
+            Some(Outcome::from_score(game.evaluate()))
+        } else if self.prove(game, false)? {
+            Some(Outcome::Win)
+        } else if self.prove(game, true)? {
+            Some(Outcome::Draw)
+        } else {
+            Some(Outcome::Loss)
+        }
+    }
+
+    // Whether the side to move wins, or draws when `draw` is set
+    fn prove<G: GameState>(&mut self, game: &mut G, draw: bool) -> Option<bool> {
+        let mut tree = vec![Node {
+            mv: None,
+            parent: 0,
+            children: vec![],
+            or: true,
+            proof: 1,
+            disproof: 1,
+        }];
+        self.expand(game, &mut tree, 0, draw);
+        while tree[0].proof != 0 && tree[0].disproof != 0 {
+            if tree.len() >= self.max_nodes {
+                return None;
+            }
+            // Descend to the most proving node
+            let mut node = 0;
+            let mut path = vec![];
+            while !tree[node].children.is_empty() {
+                let children = &tree[node].children;
+                node = if tree[node].or {
+                    *children.iter().min_by_key(|&&child| tree[child].proof)?
+                } else {
+                    *children.iter().min_by_key(|&&child| tree[child].disproof)?
+                };
+                let mv = tree[node].mv?;
+                game.apply(mv);
+                path.push(mv);
+            }
+            self.expand(game, &mut tree, node, draw);
+            for &mv in path.iter().rev() {
+                game.undo(mv);
+            }
+            while node != 0 {
+                node = tree[node].parent;
+                update(&mut tree, node);
+            }
+        }
+        Some(tree[0].proof == 0)
+    }
+
+    // Create the children of a node, solving terminal ones right away
+    fn expand<G: GameState>(
+        &mut self,
+        game: &mut G,
+        tree: &mut Vec<Node<G::Move>>,
+        node: usize,
+        draw: bool,
+    ) {
+        let or = !tree[node].or;
+        for mv in game.legal_moves() {
+            game.apply(mv);
+            let (proof, disproof) = if game.is_terminal() {
+                // Scored from the side to move, the root one when or
+                let score = if or {
+                    game.evaluate()
+                } else {
+                    -game.evaluate()
+                };
+                if score > DRAW || (draw && score == DRAW) {
+                    (0, INFINITY)
+                } else {
+                    (INFINITY, 0)
+                }
+            } else {
+                (1, 1)
+            };
+            game.undo(mv);
+            tree.push(Node {
+                mv: Some(mv),
+                parent: node,
+                children: vec![],
+                or,
+                proof,
+                disproof,
+            });
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+        }
+        self.nodes += tree[node].children.len();
+        update(tree, node);
+    }
+}
+
+/// Depth-first proof-number search, answering whether the side to move can
+/// force a win. Keeps only a table of proof and disproof numbers by
+/// [`GameState::hash_key`] rather than a tree, re-searching positions of
+/// games without keys.
+#[derive(Clone, Debug)]
+pub struct DfPn {
+    /// Most nodes searched for one question, the search gives up past it.
+    pub max_nodes: usize,
+    /// Number of nodes searched so far.
+    pub nodes: usize,
+    // Proof and disproof numbers of the positions searched for the question
+    // being answered
+    table: HashMap<u64, (u32, u32)>,
+    // Nodes at which the question is given up
+    budget: usize,
+}
+
+impl Default for DfPn {
+    fn default() -> Self {
+        Self::new(MAX_NODES)
+    }
+}
+
+impl DfPn {
+    pub fn new(max_nodes: usize) -> Self {
+        DfPn {
+            max_nodes,
+            nodes: 0,
+            table: HashMap::new(),
+            budget: 0,
+        }
+    }
+
+    /// Whether the side to move can force a win, `None` when it could not
+    /// be decided within `max_nodes`.
+    pub fn forced_win<G: GameState>(&mut self, game: &mut G) -> Option<bool> {
+        self.table.clear();
+        self.budget = self.nodes + self.max_nodes;
+        self.prove(game, true)
+    }
+
+    /// Move forcing a win for the side to move, `None` when there is none
+    /// or it could not be found within `max_nodes`.
+    pub fn winning_move<G: GameState>(&mut self, game: &mut G) -> Option<G::Move> {
+        if !self.forced_win(game)? {
+            return None;
+        }
+        game.legal_moves().into_iter().find(|&mv| {
+            game.apply(mv);
+            let won = self.prove(game, false) == Some(true);
+            game.undo(mv);
+            won
+        })
+    }
+
+    // Whether the side to move at the root wins, it being to move here
+    // when `or`
+    fn prove<G: GameState>(&mut self, game: &mut G, or: bool) -> Option<bool> {
+        let (proof, disproof) = self.mid(game, or, INFINITY, INFINITY);
+        if proof == 0 {
+            Some(true)
+        } else if disproof == 0 {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // Search a node until its proof or disproof number reaches its
+    // threshold, returning both numbers
+    fn mid<G: GameState>(
+        &mut self,
+        game: &mut G,
+        or: bool,
+        max_proof: u32,
+        max_disproof: u32,
+    ) -> (u32, u32) {
+        self.nodes += 1;
+        if game.is_terminal() {
+            return self.lookup(game, or);
+        }
+        let key = game.hash_key();
+        let mut children: Vec<_> = game
+            .legal_moves()
+            .into_iter()
+            .map(|mv| {
+                game.apply(mv);
+                let numbers = self.lookup(game, !or);
+                game.undo(mv);
+                (mv, numbers)
+            })
+            .collect();
+        loop {
+            let numbers = combine(or, children.iter().map(|&(_, numbers)| numbers));
+            let (proof, disproof) = numbers;
+            if proof >= max_proof || disproof >= max_disproof || self.nodes >= self.budget {
+                if let Some(key) = key {
+                    self.table.insert(key, numbers);
+                }
+                return numbers;
+            }
+            // Most proving child, and the number of the next best one
+            // bounding how long it is searched
+            let rank = |i: usize| {
+                let (proof, disproof) = children[i].1;
+                if or {
+                    proof
+                } else {
+                    disproof
+                }
+            };
+            let best = (0..children.len()).min_by_key(|&i| rank(i)).unwrap_or(0);
+            let second = (0..children.len())
+                .filter(|&i| i != best)
+                .map(rank)
+                .min()
+                .unwrap_or(INFINITY);
+            let (mv, (child_proof, child_disproof)) = children[best];
+            let (max_proof, max_disproof) = if or {
+                (
+                    max_proof.min(second.saturating_add(1)),
+                    max_disproof
+                        .saturating_sub(disproof)
+                        .saturating_add(child_disproof),
+                )
+            } else {
+                (
+                    max_proof.saturating_sub(proof).saturating_add(child_proof),
+                    max_disproof.min(second.saturating_add(1)),
+                )
+            };
+            game.apply(mv);
+            children[best].1 = self.mid(game, !or, max_proof, max_disproof);
+            game.undo(mv);
+        }
+    }
+
+    // Numbers of a position, solving terminal ones and starting unknown
+    // ones at 1
+    fn lookup<G: GameState>(&self, game: &G, or: bool) -> (u32, u32) {
+        if game.is_terminal() {
+            // Scored from the side to move, the root one when or
+            let score = if or {
+                game.evaluate()
+            } else {
+                -game.evaluate()
+            };
+            if score > DRAW {
+                (0, INFINITY)
+            } else {
+                (INFINITY, 0)
+            }
+        } else {
+            game.hash_key()
+                .and_then(|key| self.table.get(&key).copied())
+                .unwrap_or((1, 1))
+        }
+    }
+}
+
+// Proof and disproof numbers of a node from those of its children, an or
+// node is proven by any child and disproven by all of them
+fn combine(or: bool, children: impl Iterator<Item = (u32, u32)>) -> (u32, u32) {
+    let (mut min, mut sum) = (INFINITY, 0u32);
+    for (proof, disproof) in children {
+        let (own, other) = if or {
+            (proof, disproof)
+        } else {
+            (disproof, proof)
+        };
+        min = min.min(own);
+        sum = sum.saturating_add(other);
+    }
+    if or {
+        (min, sum)
+    } else {
+        (sum, min)
+    }
+}
+
+// Recompute proof and disproof numbers of a node from its children, an
+// or node is proven by any child and disproven by all of them
+fn update<M>(tree: &mut [Node<M>], node: usize) {
+    let children = &tree[node].children;
+    if children.is_empty() {
+        return;
+    }
+    let proofs = children.iter().map(|&child| tree[child].proof);
+    let disproofs = children.iter().map(|&child| tree[child].disproof);
+    let (proof, disproof) = if tree[node].or {
+        let proof = proofs.min().unwrap_or(INFINITY);
+        (proof, disproofs.fold(0, u32::saturating_add))
+    } else {
+        let disproof = disproofs.min().unwrap_or(INFINITY);
+        (proofs.fold(0, u32::saturating_add), disproof)
+    };
+    tree[node].proof = proof;
+    tree[node].disproof = disproof;
+}
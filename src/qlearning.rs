@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::search::GameState;
+use crate::{Bitboard, Game, GameStatus, Move};
+
+/// Learned values of moves by position, the expected result for the side
+/// playing them, 1 for a win, 0 for a draw and -1 for a loss. Unseen moves
+/// are worth 0.
+///
+/// Positions are the exact boards, without symmetries, of the board the
+/// table was trained on. Table files have one `x o square value` line per
+/// move, the stones of each side as hexadecimal bitboards.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QTable {
+    values: HashMap<([Bitboard; 2], u32), f64>,
+}
+
+impl QTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn value(&self, game: &Game, mv: Move) -> f64 {
+        let key = (game.players(), game.square(mv));
+        self.values.get(&key).copied().unwrap_or(0.0)
+    }
+
+    pub fn set_value(&mut self, game: &Game, mv: Move, value: f64) {
+        self.values.insert((game.players(), game.square(mv)), value);
+    }
+
+    /// Highest value of the moves of a position, 0 when the game is over.
+    pub fn best_value(&self, game: &Game) -> f64 {
+        game.legal_moves()
+            .into_iter()
+            .map(|mv| self.value(game, mv))
+            .max_by(f64::total_cmp)
+            .unwrap_or(0.0)
+    }
+
+    /// Move with the highest value drawn among equal ones, `None` when the
+    /// game is over.
+    pub fn best_move(&self, game: &Game, rng: &mut impl Rng) -> Option<Move> {
+        let best = self.best_value(game);
+        let moves: Vec<_> = game
+            .legal_moves()
+            .into_iter()
+            .filter(|&mv| self.value(game, mv) == best)
+            .collect();
+        moves.choose(rng).copied()
+    }
+
+    /// Number of moves with a learned value.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Write the table to a file, see [`QTable`] for the format.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let mut values: Vec<_> = self.values.iter().collect();
+        values.sort_by_key(|(&key, _)| key);
+        for (([x, o], square), value) in values {
+            writeln!(file, "{:x} {:x} {} {}", x, o, square, value)?;
+        }
+        file.flush()
+    }
+
+    /// Read a table file, see [`QTable`] for the format.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut table = QTable::new();
+        for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: expected `x o square value`", i + 1),
+                )
+            };
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let [x, o, square, value] = fields[..] else {
+                return Err(invalid());
+            };
+            let x = Bitboard::from_hex(x).ok_or_else(invalid)?;
+            let o = Bitboard::from_hex(o).ok_or_else(invalid)?;
+            let square = square.parse().map_err(|_| invalid())?;
+            let value = value.parse().map_err(|_| invalid())?;
+            table.values.insert(([x, o], square), value);
+        }
+        Ok(table)
+    }
+}
+
+/// Tabular Q-learning over self-play episodes, both sides sharing the
+/// table. The value of a move is pulled toward its result when it ends the
+/// game, and otherwise toward the opposite of the best value of the
+/// opponent's moves, discounted.
+#[derive(Clone, Debug)]
+pub struct QLearning {
+    /// Learning rate, how far values move toward their targets.
+    pub alpha: f64,
+    /// Discount of the values of later moves.
+    pub gamma: f64,
+    /// Chance of playing a random move rather than the best one.
+    pub epsilon: f64,
+    rng: StdRng,
+}
+
+impl Default for QLearning {
+    fn default() -> Self {
+        QLearning {
+            alpha: 0.2,
+            gamma: 0.95,
+            epsilon: 0.2,
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl QLearning {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Same training drawing its moves from `seed`.
+    pub fn seeded(self, seed: u64) -> Self {
+        QLearning {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Play `episodes` games from `start`, updating the table after every
+    /// move.
+    pub fn train(&mut self, table: &mut QTable, start: &Game, episodes: u32) {
+        for _ in 0..episodes {
+            let mut game = start.clone();
+            while !game.is_over() {
+                let mv = if self.rng.gen_bool(self.epsilon) {
+                    game.random_move_with(&mut self.rng)
+                } else {
+                    table
+                        .best_move(&game, &mut self.rng)
+                        .expect("Game isn't over")
+                };
+                let next = game.with_move(mv).expect("Chosen moves are legal");
+                let target = match next.status() {
+                    GameStatus::Won(_) => 1.0,
+                    GameStatus::Draw => 0.0,
+                    GameStatus::InProgress => -self.gamma * table.best_value(&next),
+                };
+                let value = table.value(&game, mv);
+                table.set_value(&game, mv, value + self.alpha * (target - value));
+                game = next;
+            }
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::search::{GameState, Score, DRAW, LOSS, WIN};
+use crate::tt::mix;
+use crate::{Game, GameStatus, Move, MoveError, ParseMoveError, PlayerId};
+
+/// Move of [`Quantum`] tic-tac-toe. When the opponent's last mark closed a
+/// cycle, the player to move first chooses which of its two squares that
+/// mark collapses to, then places its own mark, if the game is still on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QuantumMove {
+    /// Square the mark closing a cycle collapses to.
+    pub collapse: Option<Move>,
+    /// Squares of a spooky mark, or the same square twice for the classical
+    /// mark on the last free square. `None` when the collapse ends the
+    /// game.
+    pub mark: Option<(Move, Move)>,
+}
+
+impl QuantumMove {
+    /// Spooky mark on two squares.
+    pub fn spooky(a: Move, b: Move) -> Self {
+        QuantumMove {
+            collapse: None,
+            mark: Some((a, b)),
+        }
+    }
+}
+
+// Printed as `a+b` for a spooky mark, a single square for a classical one,
+// after `square:` for a collapse
+impl fmt::Display for QuantumMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(square) = self.collapse {
+            write!(f, "{}:", square)?;
+        }
+        match self.mark {
+            Some((a, b)) if a == b => write!(f, "{}", a),
+            Some((a, b)) => write!(f, "{}+{}", a, b),
+            None => Ok(()),
+        }
+    }
+}
+
+// Parses what `Display` prints
+impl FromStr for QuantumMove {
+    type Err = ParseMoveError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (collapse, mark) = match s.split_once(':') {
+            Some((collapse, mark)) => (Some(collapse.parse()?), mark.trim()),
+            None => (None, s.trim()),
+        };
+        let mark = match mark.split_once('+') {
+            _ if mark.is_empty() && collapse.is_some() => None,
+            Some((a, b)) => Some((a.parse()?, b.parse()?)),
+            None => {
+                let square = mark.parse()?;
+                Some((square, square))
+            }
+        };
+        Ok(QuantumMove { collapse, mark })
+    }
+}
+
+// Mark of a player in superposition over two squares, numbered by the move
+// placing it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Spooky {
+    player: PlayerId,
+    number: u8,
+    squares: [u32; 2],
+}
+
+// Marks on the board, what moves change and undo restores
+#[derive(Clone, Debug)]
+struct Marks {
+    // Player and number of the classical mark of each square
+    classical: Vec<Option<(PlayerId, u8)>>,
+    spooky: Vec<Spooky>,
+    // Whether the last spooky mark closed a cycle, to be collapsed
+    cycle: bool,
+}
+
+/// Quantum tic-tac-toe: each move places a spooky mark in two squares at
+/// once, entangling them. When marks form a cycle, the opponent of the
+/// player closing it chooses which square the closing mark collapses to,
+/// fixing every entangled mark of the cycle and those hanging on it as
+/// classical marks. Three classical marks in a row win, and when a
+/// collapse gives both players a line, the one completed with the older
+/// marks wins. The last free square takes a classical mark.
+///
+/// The board is an empty [`Game`] giving the win lines. The collapse
+/// choice is part of the chooser's next move, so players still alternate
+/// and the generic searches play it.
+#[derive(Clone, Debug)]
+pub struct Quantum {
+    board: Game,
+    marks: Marks,
+    turn: PlayerId,
+    history: Vec<QuantumMove>,
+    // Marks before each move, restored by undo
+    undo: Vec<Marks>,
+}
+
+impl Default for Quantum {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Quantum {
+    /// Create an empty 3×3 board with the first player to move.
+    pub fn new() -> Self {
+        Self::with_board(Game::new())
+    }
+
+    /// Play on the board of a game, any stones on it left out.
+    pub fn with_board(board: Game) -> Self {
+        let squares = board.squares() as usize;
+        Quantum {
+            board,
+            marks: Marks {
+                classical: vec![None; squares],
+                spooky: vec![],
+                cycle: false,
+            },
+            turn: 0,
+            history: vec![],
+            undo: vec![],
+        }
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Empty board the game is played on.
+    pub fn board(&self) -> &Game {
+        &self.board
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[QuantumMove] {
+        &self.history
+    }
+
+    /// Player and move number of the classical mark of a square.
+    pub fn classical(&self, square: Move) -> Option<(PlayerId, u8)> {
+        self.marks.classical[self.board.square(square) as usize]
+    }
+
+    /// Squares of the marks still in superposition, with their player and
+    /// move number.
+    pub fn spooky_marks(&self) -> impl Iterator<Item = (PlayerId, u8, [Move; 2])> + '_ {
+        self.marks.spooky.iter().map(|mark| {
+            let squares = mark.squares.map(|square| self.board.square_move(square));
+            (mark.player, mark.number, squares)
+        })
+    }
+
+    /// Whether the last mark closed a cycle the player to move collapses.
+    pub fn must_collapse(&self) -> bool {
+        self.marks.cycle
+    }
+
+    // Squares without a classical mark
+    fn free(&self) -> Vec<u32> {
+        (0..self.board.squares())
+            .filter(|&square| {
+                self.marks.classical[square as usize].is_none()
+                    && !self.board.blocked().contains(square)
+            })
+            .collect()
+    }
+
+    // Whether two squares are entangled through spooky marks
+    fn entangled(&self, from: u32, to: u32) -> bool {
+        let mut seen = vec![from];
+        let mut stack = vec![from];
+        while let Some(square) = stack.pop() {
+            if square == to {
+                return true;
+            }
+            for mark in &self.marks.spooky {
+                for (a, b) in [(0, 1), (1, 0)] {
+                    if mark.squares[a] == square && !seen.contains(&mark.squares[b]) {
+                        seen.push(mark.squares[b]);
+                        stack.push(mark.squares[b]);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    // Collapse the last mark to a square, then every mark sharing a square
+    // with a collapsed one to its other square
+    fn collapse(&mut self, square: u32) {
+        let last = self.marks.spooky.len() - 1;
+        let mut queue = vec![(last, square)];
+        while let Some((index, square)) = queue.pop() {
+            let mark = self.marks.spooky[index];
+            self.marks.classical[square as usize] = Some((mark.player, mark.number));
+            for (other, mark) in self.marks.spooky.iter().enumerate() {
+                if other != index && !queue.iter().any(|&(queued, _)| queued == other) {
+                    if let Some(side) = mark.squares.iter().position(|&s| s == square) {
+                        queue.push((other, mark.squares[1 - side]));
+                    }
+                }
+            }
+            // Indices above the collapsed mark shift down once it's gone
+            self.marks.spooky.remove(index);
+            for (queued, _) in &mut queue {
+                if *queued > index {
+                    *queued -= 1;
+                }
+            }
+        }
+        self.marks.cycle = false;
+    }
+
+    // Place a mark without legality checks
+    fn place(&mut self, a: u32, b: u32) {
+        let number = self.history.len() as u8 + 1;
+        if a == b {
+            self.marks.classical[a as usize] = Some((self.turn, number));
+            return;
+        }
+        self.marks.cycle = self.entangled(a, b);
+        self.marks.spooky.push(Spooky {
+            player: self.turn,
+            number,
+            squares: [a, b],
+        });
+    }
+
+    // Highest move number of each player's oldest line of classical
+    // marks, completed lines only
+    fn lines(&self) -> [Option<u8>; 2] {
+        let mut lines: [Option<u8>; 2] = [None; 2];
+        for mask in self.board.wins() {
+            let marks: Option<Vec<_>> = mask
+                .ones()
+                .map(|square| self.marks.classical[square as usize])
+                .collect();
+            let Some(marks) = marks else { continue };
+            let player = marks[0].0;
+            if marks.iter().all(|&(owner, _)| owner == player) {
+                let newest = marks.iter().map(|&(_, number)| number).max().unwrap_or(0);
+                let line = &mut lines[usize::from(player)];
+                *line = Some(line.map_or(newest, |line| line.min(newest)));
+            }
+        }
+        lines
+    }
+
+    pub fn status(&self) -> GameStatus {
+        match self.lines() {
+            [Some(x), Some(o)] => GameStatus::Won(PlayerId::from(o < x)),
+            [Some(_), None] => GameStatus::Won(0),
+            [None, Some(_)] => GameStatus::Won(1),
+            [None, None] if self.free().is_empty() => GameStatus::Draw,
+            [None, None] => GameStatus::InProgress,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.status() != GameStatus::InProgress
+    }
+
+    /// Check that a move can be played in the current position.
+    pub fn check_move(&self, mv: QuantumMove) -> Result<(), MoveError> {
+        for square in mv
+            .collapse
+            .into_iter()
+            .chain(mv.mark.into_iter().flat_map(|(a, b)| [a, b]))
+        {
+            self.board.check_move(square)?;
+        }
+        if self.is_over() {
+            return Err(MoveError::GameOver);
+        }
+        // Squares of a mark in either order
+        let sorted = QuantumMove {
+            mark: mv.mark.map(|(a, b)| (a.min(b), a.max(b))),
+            ..mv
+        };
+        if self.legal_moves().contains(&sorted) {
+            Ok(())
+        } else {
+            Err(MoveError::Occupied)
+        }
+    }
+
+    pub fn make_move(&mut self, mv: QuantumMove) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+
+    // Marks the player to move may place once any collapse is done
+    fn marks_to_place(&self) -> Vec<Option<(Move, Move)>> {
+        if self.is_over() {
+            return vec![None];
+        }
+        let free = self.free();
+        let to_move = |square| self.board.square_move(square);
+        if let [last] = free[..] {
+            return vec![Some((to_move(last), to_move(last)))];
+        }
+        free.iter()
+            .enumerate()
+            .flat_map(|(i, &a)| {
+                free[i + 1..]
+                    .iter()
+                    .map(move |&b| Some((to_move(a), to_move(b))))
+            })
+            .collect()
+    }
+}
+
+// One row per line, classical marks as X or O with their move number,
+// spooky ones in lowercase, cells separated by bars
+impl fmt::Display for Quantum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = |player: PlayerId| if player == 0 { 'x' } else { 'o' };
+        let cells: Vec<String> = (0..self.board.squares())
+            .map(|square| match self.marks.classical[square as usize] {
+                Some((player, number)) => {
+                    format!("{}{}", symbol(player).to_ascii_uppercase(), number)
+                }
+                None if self.board.blocked().contains(square) => "#".to_string(),
+                None => {
+                    let spooky: Vec<_> = self
+                        .marks
+                        .spooky
+                        .iter()
+                        .filter(|mark| mark.squares.contains(&square))
+                        .map(|mark| format!("{}{}", symbol(mark.player), mark.number))
+                        .collect();
+                    if spooky.is_empty() {
+                        ".".to_string()
+                    } else {
+                        spooky.join(",")
+                    }
+                }
+            })
+            .collect();
+        let width = cells.iter().map(String::len).max().unwrap_or(1);
+        let rows: Vec<_> = cells
+            .chunks(usize::from(self.board.width()))
+            .map(|row| {
+                let row: Vec<_> = row.iter().map(|cell| format!("{:width$}", cell)).collect();
+                row.join(" | ")
+            })
+            .collect();
+        write!(f, "{}", rows.join("\n"))
+    }
+}
+
+impl GameState for Quantum {
+    type Move = QuantumMove;
+
+    fn legal_moves(&self) -> Vec<QuantumMove> {
+        if self.is_over() {
+            return vec![];
+        }
+        if !self.marks.cycle {
+            return self
+                .marks_to_place()
+                .into_iter()
+                .map(|mark| QuantumMove {
+                    collapse: None,
+                    mark,
+                })
+                .collect();
+        }
+        let last = *self.marks.spooky.last().expect("A mark closed the cycle");
+        let mut moves = vec![];
+        for square in last.squares {
+            let mut collapsed = self.clone();
+            collapsed.collapse(square);
+            let collapse = Some(self.board.square_move(square));
+            moves.extend(
+                collapsed
+                    .marks_to_place()
+                    .into_iter()
+                    .map(|mark| QuantumMove { collapse, mark }),
+            );
+        }
+        moves
+    }
+
+    fn apply(&mut self, mv: QuantumMove) {
+        self.undo.push(self.marks.clone());
+        if let Some(square) = mv.collapse {
+            self.collapse(self.board.square(square));
+        }
+        if let Some((a, b)) = mv.mark {
+            self.place(self.board.square(a), self.board.square(b));
+        }
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    fn undo(&mut self, _mv: QuantumMove) {
+        self.marks = self.undo.pop().expect("A move to undo");
+        self.turn = 1 - self.turn;
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Squares of the mark, for the history heuristic
+    fn move_index(&self, mv: QuantumMove) -> Option<usize> {
+        let (a, b) = mv.mark?;
+        let squares = self.board.squares() as usize;
+        Some(self.board.square(a) as usize * squares + self.board.square(b) as usize)
+    }
+
+    // Marks with their numbers, which break ties between lines, and the
+    // side to move
+    fn hash_key(&self) -> Option<u64> {
+        let mut key = mix(u64::from(self.turn), u64::from(self.marks.cycle));
+        for (square, mark) in self.marks.classical.iter().enumerate() {
+            if let Some((player, number)) = mark {
+                key = mix(
+                    key,
+                    (square as u64) << 16 | u64::from(*player) << 8 | u64::from(*number),
+                );
+            }
+        }
+        for mark in &self.marks.spooky {
+            let [a, b] = mark.squares.map(u64::from);
+            key = mix(
+                key,
+                a << 32 | b << 24 | u64::from(mark.player) << 8 | u64::from(mark.number),
+            );
+        }
+        Some(key)
+    }
+
+    fn evaluate(&self) -> Score {
+        match self.status() {
+            GameStatus::Won(player) if player == self.turn => WIN,
+            GameStatus::Won(_) => LOSS,
+            GameStatus::Draw => DRAW,
+            // Lines still open to each side, by the square of their
+            // classical marks
+            GameStatus::InProgress => {
+                let mut score = 0;
+                for mask in self.board.wins() {
+                    let owners: Vec<_> = mask
+                        .ones()
+                        .filter_map(|square| self.marks.classical[square as usize])
+                        .map(|(player, _)| player)
+                        .collect();
+                    let ours = owners.iter().filter(|&&player| player == self.turn).count();
+                    let theirs = owners.len() - ours;
+                    score += match (ours, theirs) {
+                        (_, 0) => (ours * ours) as Score,
+                        (0, _) => -((theirs * theirs) as Score),
+                        _ => 0,
+                    };
+                }
+                score
+            }
+        }
+    }
+}
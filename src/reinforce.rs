@@ -0,0 +1,136 @@
+use std::io;
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::qlearning::QTable;
+use crate::search::GameState;
+use crate::{Game, GameStatus, Move};
+
+/// Stochastic policy playing moves with the probabilities of a softmax of
+/// their learned preferences, kept by position like the values of a
+/// [`QTable`] and saved in the same format. Unseen moves have preference 0.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Policy {
+    pub preferences: QTable,
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Legal moves with their probabilities, empty when the game is over.
+    pub fn probabilities(&self, game: &Game) -> Vec<(Move, f64)> {
+        let moves = game.legal_moves();
+        let preferences: Vec<_> = moves
+            .iter()
+            .map(|&mv| self.preferences.value(game, mv))
+            .collect();
+        // Shifted by the highest preference not to overflow
+        let highest = preferences
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<_> = preferences.iter().map(|p| (p - highest).exp()).collect();
+        let total: f64 = weights.iter().sum();
+        moves
+            .into_iter()
+            .zip(weights)
+            .map(|(mv, weight)| (mv, weight / total))
+            .collect()
+    }
+
+    /// Move drawn from the policy, `None` when the game is over.
+    pub fn sample(&self, game: &Game, rng: &mut impl Rng) -> Option<Move> {
+        self.probabilities(game)
+            .choose_weighted(rng, |&(_, p)| p)
+            .ok()
+            .map(|&(mv, _)| mv)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.preferences.save(path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        QTable::load(path).map(|preferences| Policy { preferences })
+    }
+}
+
+/// REINFORCE, policy gradient over self-play episodes, both sides sharing
+/// the policy. After each game, the preferences of the moves of each side
+/// follow the gradient of their log probabilities scaled by the result of
+/// that side, 1 for a win, 0 for a draw and -1 for a loss, less a running
+/// average of the results of the side.
+#[derive(Clone, Debug)]
+pub struct Reinforce {
+    /// Learning rate.
+    pub alpha: f64,
+    /// Weight of the last result in the running averages.
+    pub baseline_rate: f64,
+    // Running averages of the results of each side
+    baselines: [f64; 2],
+    rng: StdRng,
+}
+
+impl Default for Reinforce {
+    fn default() -> Self {
+        Reinforce {
+            alpha: 0.1,
+            baseline_rate: 0.01,
+            baselines: [0.0; 2],
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl Reinforce {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Same training drawing its moves from `seed`.
+    pub fn seeded(self, seed: u64) -> Self {
+        Reinforce {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Play `episodes` games from `start` with the policy, updating it after
+    /// each one.
+    pub fn train(&mut self, policy: &mut Policy, start: &Game, episodes: u32) {
+        for _ in 0..episodes {
+            let mut game = start.clone();
+            let mut steps = vec![];
+            while !game.is_over() {
+                let mv = policy
+                    .sample(&game, &mut self.rng)
+                    .expect("Game isn't over");
+                steps.push((game.clone(), mv));
+                game.make_move(mv).expect("Sampled moves are legal");
+            }
+            let results = match game.status() {
+                GameStatus::Won(0) => [1.0, -1.0],
+                GameStatus::Won(_) => [-1.0, 1.0],
+                _ => [0.0, 0.0],
+            };
+            let advantages = [0, 1].map(|side| results[side] - self.baselines[side]);
+            for (position, played) in steps {
+                let advantage = advantages[position.turn() as usize];
+                for (mv, p) in policy.probabilities(&position) {
+                    let gradient = if mv == played { 1.0 - p } else { -p };
+                    let preference = policy.preferences.value(&position, mv);
+                    let preference = preference + self.alpha * advantage * gradient;
+                    policy.preferences.set_value(&position, mv, preference);
+                }
+            }
+            for (baseline, result) in self.baselines.iter_mut().zip(results) {
+                *baseline += self.baseline_rate * (result - *baseline);
+            }
+        }
+    }
+}
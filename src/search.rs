@@ -0,0 +1,1275 @@
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Gamma};
+
+use crate::eval::{DefaultEvaluator, Evaluator};
+use crate::tablebase::Tablebase;
+use crate::tt::{Bound, Entry, TranspositionTable};
+
+mod parallel;
+
+// Number of positions searched between two clock checks
+const CHECK_INTERVAL: u32 = 1024;
+
+// Depth taken off the shallow search of internal iterative deepening
+const IID_REDUCTION: u8 = 2;
+
+/// Default number of positions kept in the transposition table.
+pub const TT_CAPACITY: usize = 1 << 20;
+
+/// Score of a position, from the point of view of the side to move.
+pub type Score = i32;
+
+/// Score of a won game. The search lowers it by the number of plies
+/// needed to reach the win, so faster wins and slower losses are preferred.
+pub const WIN: Score = 1_000_000;
+
+/// Score of a lost game.
+pub const LOSS: Score = -WIN;
+
+/// Score of a drawn game.
+pub const DRAW: Score = 0;
+
+/// Game theoretic value of a position, from the point of view of the side
+/// to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Outcome {
+    /// Outcome given by a score, heuristic scores counting as draws.
+    pub fn from_score(score: Score) -> Self {
+        if !is_decisive(score) {
+            Outcome::Draw
+        } else if score > DRAW {
+            Outcome::Win
+        } else {
+            Outcome::Loss
+        }
+    }
+}
+
+// Bound of full windows, above any score
+const INFINITY: Score = Score::MAX;
+
+/// Whether a score comes from a won or lost terminal position rather than
+/// from the heuristic.
+pub fn is_decisive(score: Score) -> bool {
+    (WIN - Score::from(u8::MAX)..=WIN).contains(&score.abs())
+}
+
+/// Score for the side to move in words, decisive ones by the plies to the
+/// end of the game such as `win in 3`, heuristic ones as a number.
+pub fn describe_score(score: Score) -> String {
+    let plies = WIN - score.abs();
+    match Outcome::from_score(score) {
+        Outcome::Win => format!("win in {}", plies),
+        Outcome::Loss => format!("loss in {}", plies),
+        Outcome::Draw => score.to_string(),
+    }
+}
+
+/// Two-player, zero-sum, perfect information game searchable by [`Search`].
+pub trait GameState {
+    /// Move representation of the game.
+    type Move: Copy + PartialEq;
+
+    /// Moves available to the side to move.
+    fn legal_moves(&self) -> Vec<Self::Move>;
+
+    /// Play a move and hand the turn to the opponent.
+    fn apply(&mut self, mv: Self::Move);
+
+    /// Take back a move previously played with [`GameState::apply`].
+    fn undo(&mut self, mv: Self::Move);
+
+    /// Whether the game is over.
+    fn is_terminal(&self) -> bool;
+
+    /// Static score from the point of view of the side to move, exact on
+    /// terminal positions: [`LOSS`] when the side to move lost, [`WIN`]
+    /// when it won and [`DRAW`] otherwise.
+    fn evaluate(&self) -> Score;
+
+    /// Ordering hint, moves with a higher priority are searched first when
+    /// [`SearchOptions::ordering`] is set.
+    fn move_priority(&self, _mv: Self::Move) -> i32 {
+        0
+    }
+
+    /// Small dense index of a move, such as its square, used by the history
+    /// heuristic. Games without one don't benefit from it.
+    fn move_index(&self, _mv: Self::Move) -> Option<usize> {
+        None
+    }
+
+    /// Hash of the position, side to move included, used as transposition
+    /// table key. Games without one are searched without the table.
+    fn hash_key(&self) -> Option<u64> {
+        None
+    }
+
+    /// Moves winning at once, or failing that moves stopping the opponent
+    /// from winning at once, that [`SearchOptions::extend_forcing`] keeps
+    /// searching past the depth limit. Games without tactics give none.
+    fn forcing_moves(&self) -> Vec<Self::Move> {
+        vec![]
+    }
+
+    /// Static score of the threats of the side to move and of those of the
+    /// opponent, each on its own, for players weighting them differently,
+    /// see [`Personality`](crate::player::Personality). Games without
+    /// threats score none.
+    fn threat_scores(&self) -> [Score; 2] {
+        [0, 0]
+    }
+
+    /// Hand the turn to the opponent without playing, for null-move
+    /// pruning. Games not allowing it return false and stay unchanged.
+    fn pass(&mut self) -> bool {
+        false
+    }
+
+    /// Take back a pass made with [`GameState::pass`].
+    fn undo_pass(&mut self) {}
+}
+
+/// Work done by a search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// Number of positions visited.
+    pub nodes: u32,
+    /// Number of beta cutoffs.
+    pub cutoffs: u32,
+    /// Number of positions scored from the transposition table.
+    pub tt_hits: u32,
+    /// Deepest ply visited.
+    pub max_depth: u8,
+    pub elapsed: Duration,
+}
+
+/// Outcome of a search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult<M> {
+    /// Score of the position from the side to move.
+    pub score: Score,
+    /// Principal variation, the expected line of play starting with the
+    /// chosen move, empty on terminal or depth 0 positions. Lines returned
+    /// by [`Search::negamax`] may stop early where a stored result was
+    /// reused.
+    pub pv: Vec<M>,
+    pub stats: SearchStats,
+}
+
+impl<M: Copy> SearchResult<M> {
+    fn new(score: Score, pv: Vec<M>) -> Self {
+        SearchResult {
+            score,
+            pv,
+            stats: SearchStats::default(),
+        }
+    }
+
+    /// Chosen move, the first one of the principal variation.
+    pub fn best(&self) -> Option<M> {
+        self.pv.first().copied()
+    }
+}
+
+/// Exact value of a position, with a move achieving it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Solution<M> {
+    pub outcome: Outcome,
+    /// Fastest win, slowest loss or any drawing move. `None` when the game
+    /// is over.
+    pub best: Option<M>,
+}
+
+/// Algorithm driving the negamax search at the root.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Driver {
+    /// Single search with a full window.
+    AlphaBeta,
+    /// MTD(f), null window searches converging on the score from a first
+    /// guess, relying on the transposition table between passes.
+    Mtdf,
+}
+
+/// Way [`Search::search_parallel`] spreads a search over threads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parallel {
+    /// [`Search::search_root_split`]
+    RootSplit,
+    /// [`Search::search_lazy_smp`]
+    LazySmp,
+    /// [`Search::search_ybwc`]
+    Ybwc,
+}
+
+/// Late move reductions, searching moves ordered late at a reduced depth
+/// first and fully only when they beat alpha.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reductions {
+    /// Moves searched at full depth before reducing the others.
+    pub full_moves: usize,
+    /// Plies taken off reduced moves.
+    pub depth: u8,
+}
+
+impl Default for Reductions {
+    fn default() -> Self {
+        Reductions {
+            full_moves: 3,
+            depth: 1,
+        }
+    }
+}
+
+/// Dirichlet noise mixed into root move probabilities so that self-play
+/// games explore moves the policy would rarely choose, as in AlphaZero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RootNoise {
+    /// Concentration of the Dirichlet distribution, lower values putting
+    /// the noise on fewer moves.
+    pub alpha: f64,
+    /// Share of the noise in the mixed probabilities.
+    pub weight: f64,
+}
+
+impl Default for RootNoise {
+    fn default() -> Self {
+        RootNoise {
+            alpha: 0.3,
+            weight: 0.25,
+        }
+    }
+}
+
+impl RootNoise {
+    /// Mix a Dirichlet sample into move probabilities summing to 1.
+    pub fn apply<M>(&self, policy: &mut [(M, f64)], rng: &mut impl Rng) {
+        let Ok(gamma) = Gamma::new(self.alpha, 1.0) else {
+            return;
+        };
+        let noise: Vec<f64> = policy.iter().map(|_| gamma.sample(rng)).collect();
+        let total: f64 = noise.iter().sum();
+        if total <= 0.0 {
+            return;
+        }
+        for ((_, p), eta) in policy.iter_mut().zip(noise) {
+            *p = (1.0 - self.weight) * *p + self.weight * eta / total;
+        }
+    }
+}
+
+/// Tunable behaviour of a [`Search`].
+#[derive(Clone, Copy, Debug)]
+pub struct SearchOptions {
+    /// Search the previous best move first, then moves by decreasing
+    /// [`GameState::move_priority`], rather than in generation order.
+    pub ordering: bool,
+    /// Search first the moves that caused a beta cutoff in sibling nodes.
+    pub killers: bool,
+    /// Order moves by how often they caused cutoffs anywhere in the tree,
+    /// before [`GameState::move_priority`]. Pays off on large boards only.
+    pub history: bool,
+    /// Principal variation search, only proving that moves after the first
+    /// one are worse with a null window and searching them fully if not.
+    pub pvs: bool,
+    /// Cache results in a transposition table and search its best move
+    /// first when ordering.
+    pub tt: bool,
+    pub tt_capacity: usize,
+    pub driver: Driver,
+    /// Half width of the window around the previous score each
+    /// iterative deepening step starts with, doubled after each failure.
+    /// Only used by [`Driver::AlphaBeta`], `None` searches full windows.
+    pub aspiration: Option<Score>,
+    /// Return the best score found even when outside of the window, giving
+    /// tighter bounds, rather than clamping it to the window like fail-hard
+    /// alpha-beta. Ignored by [`Driver::Mtdf`].
+    pub fail_soft: bool,
+    /// Search [`GameState::forcing_moves`] a ply further when they exist
+    /// at the depth limit, rather than scoring the position statically.
+    pub extend_forcing: bool,
+    /// Depth reduction of null-move pruning, failing high without
+    /// searching when passing still fails high at the reduced depth.
+    /// Needs [`GameState::pass`], `None` disables it.
+    pub null_move: Option<u8>,
+    /// Margin of futility pruning, searching only forcing moves one ply
+    /// above the depth limit when the static score plus the margin can't
+    /// reach alpha. `None` disables it.
+    pub futility: Option<Score>,
+    /// Late move reductions below the root, forcing moves being searched
+    /// fully. `None` disables them.
+    pub reductions: Option<Reductions>,
+    /// Enhanced transposition cutoffs, probing the table for every child
+    /// before searching any and failing high at once if one entry does.
+    pub etc: bool,
+    /// Internal iterative deepening, running a shallower search for a move
+    /// to order first at nodes without a table move.
+    pub iid: bool,
+    /// Score taken off drawn endings for the side to move at the root, and
+    /// given to its opponent. Positive values avoid draws, keeping play
+    /// alive against weaker opponents.
+    pub contempt: Score,
+    /// Number of threads of [`Search::search_lazy_smp`], the calling one
+    /// included.
+    pub threads: usize,
+    pub parallel: Parallel,
+    /// Search only one of the root moves reaching positions with the same
+    /// [`GameState::hash_key`], such as rotations of each other when keys
+    /// are canonical. The chosen move is drawn among all equivalent ones.
+    pub symmetry: bool,
+    /// Seed of the random choices among equally good moves, drawn from the
+    /// system when `None`. Parallel searches aren't reproducible anyway.
+    pub seed: Option<u64>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            ordering: true,
+            killers: true,
+            history: false,
+            pvs: true,
+            tt: true,
+            tt_capacity: TT_CAPACITY,
+            driver: Driver::AlphaBeta,
+            aspiration: None,
+            fail_soft: true,
+            extend_forcing: true,
+            null_move: None,
+            futility: None,
+            reductions: None,
+            etc: false,
+            iid: false,
+            contempt: 0,
+            threads: 1,
+            parallel: Parallel::RootSplit,
+            symmetry: true,
+            seed: None,
+        }
+    }
+}
+
+/// Bounds on the work of [`Search::search_limited`], the search stopping at
+/// the first one reached. No bound searches until the result is exact.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Deepest iteration.
+    pub depth: Option<u8>,
+    /// Number of positions visited, deterministic unlike time.
+    pub max_nodes: Option<u32>,
+    pub time: Option<Duration>,
+}
+
+/// Negamax search with alpha/beta pruning over any [`GameState`], scoring
+/// the positions where it stops before the end of the game with an
+/// [`Evaluator`].
+#[derive(Debug)]
+pub struct Search<G: GameState, E = DefaultEvaluator> {
+    pub options: SearchOptions,
+    pub evaluator: E,
+    /// Number of positions visited so far.
+    pub evaluations: u32,
+    deadline: Option<Instant>,
+    // Value of the evaluation counter stopping the running search
+    node_limit: Option<u32>,
+    // Set once the deadline or node limit passed, the running search is
+    // then discarded
+    aborted: bool,
+    // Evaluation counter when the clock was last checked
+    last_check: u32,
+    // Set when a leaf was scored by the heuristic rather than by the rules
+    depth_limited: bool,
+    // Plies from the root to the position searched at ply 0, which PV
+    // completion searches from within the line
+    root_ply: u8,
+    // Move searched first at the root, best move of the previous iteration
+    root_hint: Option<G::Move>,
+    // Last two moves that caused a beta cutoff, per ply
+    killers: Vec<[Option<G::Move>; 2]>,
+    // Cutoff statistics per move index, weighted by remaining depth
+    history: Vec<u32>,
+    tt: Arc<TranspositionTable<G::Move>>,
+    // Raised by another thread to abort the running search
+    stop: Option<Arc<AtomicBool>>,
+    // Whether a null move is being searched, not to pass twice in a line
+    in_null: bool,
+    tablebase: Option<Arc<Tablebase>>,
+    // Statistics since the creation of the search, but the deepest ply
+    // which is the one of the running search
+    stats: SearchStats,
+    // Score of the last completed search, first guess of MTD(f)
+    last_score: Score,
+    rng: StdRng,
+}
+
+impl<G: GameState> Default for Search<G> {
+    fn default() -> Self {
+        Self::with_options(SearchOptions::default())
+    }
+}
+
+impl<G: GameState> Search<G> {
+    /// Create a search with a zeroed evaluation counter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: SearchOptions) -> Self {
+        Self::with_evaluator(options, DefaultEvaluator)
+    }
+}
+
+impl<G: GameState, E: Evaluator<G>> Search<G, E> {
+    /// Create a search scoring positions with a custom evaluator.
+    pub fn with_evaluator(options: SearchOptions, evaluator: E) -> Self {
+        Search {
+            options,
+            evaluator,
+            evaluations: 0,
+            deadline: None,
+            node_limit: None,
+            aborted: false,
+            last_check: 0,
+            depth_limited: false,
+            root_ply: 0,
+            root_hint: None,
+            killers: vec![],
+            history: vec![],
+            tt: Arc::new(TranspositionTable::new(options.tt_capacity)),
+            stop: None,
+            in_null: false,
+            tablebase: None,
+            stats: SearchStats::default(),
+            last_score: DRAW,
+            rng: options
+                .seed
+                .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64),
+        }
+    }
+
+    /// Score positions found in a tablebase from it rather than searching
+    /// them. The table must have been built for the searched board.
+    pub fn with_tablebase(mut self, tablebase: Arc<Tablebase>) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    /// Search to a fixed depth with the configured [`Driver`].
+    pub fn search(&mut self, game: &mut G, depth: u8) -> SearchResult<G::Move> {
+        self.measured(|search| {
+            let mut result = match search.options.driver {
+                Driver::AlphaBeta => search.negamax(game, -INFINITY, INFINITY, depth),
+                Driver::Mtdf => search.mtdf(game, depth, search.last_score),
+            };
+            if !search.aborted {
+                search.last_score = result.score;
+                search.complete_pv(game, &mut result, depth, 0);
+            }
+            result
+        })
+    }
+
+    /// Search a window of half width `window` around a guess of the score,
+    /// widening it and searching again until the score falls inside.
+    pub fn aspiration(
+        &mut self,
+        game: &mut G,
+        depth: u8,
+        guess: Score,
+        window: Score,
+    ) -> SearchResult<G::Move> {
+        self.measured(|search| {
+            let mut width = window;
+            let (mut alpha, mut beta) = (guess.saturating_sub(width), guess.saturating_add(width));
+            loop {
+                let mut result = search.negamax(game, alpha, beta, depth);
+                let score = result.score;
+                if search.aborted {
+                    return result;
+                } else if score > alpha && score < beta {
+                    search.complete_pv(game, &mut result, depth, 0);
+                    return result;
+                }
+                width = width.saturating_mul(2);
+                if score <= alpha {
+                    alpha = score.saturating_sub(width);
+                } else {
+                    beta = score.saturating_add(width);
+                }
+            }
+        })
+    }
+
+    /// MTD(f) search starting from a guess of the score. Each null window
+    /// pass proves the score is either below the window or at least its
+    /// top, giving a bound and the next guess, until both bounds meet.
+    /// The principal variation is the one of the last pass failing high.
+    /// Passes always run fail-soft, fail-hard ones would only move the guess
+    /// by one.
+    pub fn mtdf(&mut self, game: &mut G, depth: u8, guess: Score) -> SearchResult<G::Move> {
+        let fail_soft = self.options.fail_soft;
+        self.options.fail_soft = true;
+        let result = self.measured(|search| {
+            let (mut lower, mut upper) = (-INFINITY, INFINITY);
+            let mut score = guess;
+            let mut pv = vec![];
+            while lower < upper {
+                let beta = score.max(lower + 1);
+                let result = search.negamax(game, beta - 1, beta, depth);
+                if search.aborted {
+                    break;
+                }
+                score = result.score;
+                if score < beta {
+                    upper = score;
+                } else {
+                    lower = score;
+                    pv = result.pv;
+                }
+            }
+            SearchResult::new(score, pv)
+        });
+        self.options.fail_soft = fail_soft;
+        result
+    }
+
+    /// Evaluate positions according to the negamax algorithm, returning
+    /// the score from the side to move and the line leading to it. Equally
+    /// good moves are chosen at random.
+    pub fn negamax(
+        &mut self,
+        game: &mut G,
+        alpha: Score,
+        beta: Score,
+        depth: u8,
+    ) -> SearchResult<G::Move> {
+        self.measured(|search| search.negamax_ply(game, alpha, beta, depth, 0))
+    }
+
+    /// Search to a fixed depth and return the `n` best root moves, best
+    /// first, each with its score and principal variation. Moves are only
+    /// scored exactly when they may enter the top `n`. All lines share the
+    /// statistics of the whole search.
+    pub fn multipv(&mut self, game: &mut G, depth: u8, n: usize) -> Vec<SearchResult<G::Move>> {
+        let (mut lines, stats) = self.measure(|search| search.multipv_lines(game, depth, n));
+        for line in &mut lines {
+            line.stats = stats;
+        }
+        lines
+    }
+
+    /// Probability of choosing each root move, a softmax of their scores
+    /// divided by `temperature`, scores coming from a MultiPV search of
+    /// all moves to `depth`. At temperature 0 the best moves only remain,
+    /// equally likely. Best moves first, empty when the game is over.
+    pub fn root_policy(
+        &mut self,
+        game: &mut G,
+        depth: u8,
+        temperature: f64,
+    ) -> Vec<(G::Move, f64)> {
+        let n = game.legal_moves().len();
+        let lines = self.multipv(game, depth, n);
+        let best = lines.first().map_or(DRAW, |line| line.score);
+        let weights: Vec<_> = lines
+            .iter()
+            .filter_map(|line| {
+                let gap = f64::from(best) - f64::from(line.score);
+                let weight = if temperature > 0.0 {
+                    (-gap / temperature).exp()
+                } else if gap == 0.0 {
+                    1.0
+                } else {
+                    0.0
+                };
+                Some((line.best()?, weight))
+            })
+            .collect();
+        let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+        weights
+            .into_iter()
+            .map(|(mv, weight)| (mv, weight / total))
+            .collect()
+    }
+
+    fn multipv_lines(&mut self, game: &mut G, depth: u8, n: usize) -> Vec<SearchResult<G::Move>> {
+        let mut lines: Vec<SearchResult<G::Move>> = vec![];
+        if n == 0 || depth == 0 || game.is_terminal() {
+            return lines;
+        }
+        for mv in self.ordered_moves(game, 0, None) {
+            // Moves scoring above alpha are scored exactly
+            let alpha = if lines.len() == n {
+                lines[n - 1].score - 1
+            } else {
+                -INFINITY
+            };
+            self.evaluations += 1;
+            self.stats.nodes += 1;
+            game.apply(mv);
+            let child = self.negamax_ply(game, -INFINITY, -alpha, depth - 1, 1);
+            game.undo(mv);
+            if self.aborted {
+                break;
+            }
+            let score = -child.score;
+            if score > alpha {
+                let pv = [vec![mv], child.pv].concat();
+                lines.push(SearchResult::new(score, pv));
+                lines.sort_by_key(|line| Reverse(line.score));
+                lines.truncate(n);
+            }
+        }
+        if !self.aborted {
+            for line in &mut lines {
+                self.complete_pv(game, line, depth, 0);
+            }
+        }
+        lines
+    }
+
+    /// Search to the end of the game, remembering positions in the
+    /// transposition table whatever the options, and return its exact
+    /// value.
+    pub fn solve(&mut self, game: &mut G) -> Solution<G::Move> {
+        let tt = self.options.tt;
+        self.options.tt = true;
+        let result = self.search(game, u8::MAX);
+        self.options.tt = tt;
+        Solution {
+            outcome: Outcome::from_score(result.score),
+            best: result.best(),
+        }
+    }
+
+    /// Search with increasing depths until `max_depth`, an exact result or
+    /// the `budget` is spent, see [`Search::search_limited`].
+    pub fn iterative_deepening(
+        &mut self,
+        game: &mut G,
+        max_depth: u8,
+        budget: Duration,
+    ) -> SearchResult<G::Move> {
+        let limits = SearchLimits {
+            depth: Some(max_depth),
+            time: Some(budget),
+            ..SearchLimits::default()
+        };
+        self.search_limited(game, limits)
+    }
+
+    /// Search with increasing depths until an exact result or one of the
+    /// limits is reached, returning the result of the deepest completed
+    /// iteration, searched with the configured [`Driver`]. With ordering,
+    /// each iteration searches the previous best move first. The depth 1
+    /// iteration always completes.
+    pub fn search_limited(&mut self, game: &mut G, limits: SearchLimits) -> SearchResult<G::Move> {
+        self.measured(|search| {
+            let deadline = limits.time.map(|time| Instant::now() + time);
+            let node_limit = limits
+                .max_nodes
+                .map(|nodes| search.evaluations.saturating_add(nodes));
+            let mut result = SearchResult::new(game.evaluate(), vec![]);
+            for depth in 1..=limits.depth.unwrap_or(u8::MAX) {
+                if depth > 1 {
+                    search.deadline = deadline;
+                    search.node_limit = node_limit;
+                }
+                search.aborted = false;
+                search.depth_limited = false;
+                let iteration = match search.options.aspiration {
+                    Some(window)
+                        if depth > 1
+                            && search.options.driver == Driver::AlphaBeta
+                            && !is_decisive(result.score) =>
+                    {
+                        search.aspiration(game, depth, result.score, window)
+                    }
+                    _ => search.search(game, depth),
+                };
+                if search.aborted {
+                    break;
+                }
+                result = iteration;
+                search.root_hint = result.best();
+                let spent = deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                    || node_limit.is_some_and(|limit| search.evaluations >= limit);
+                if !search.depth_limited || is_decisive(result.score) || spent {
+                    break;
+                }
+            }
+            search.deadline = None;
+            search.node_limit = None;
+            search.aborted = false;
+            search.root_hint = None;
+            result
+        })
+    }
+
+    // Run a search, returning the statistics of this run only
+    fn measure<R>(&mut self, run: impl FnOnce(&mut Self) -> R) -> (R, SearchStats) {
+        let before = self.stats;
+        let start = Instant::now();
+        self.stats.max_depth = 0;
+        let result = run(self);
+        let stats = SearchStats {
+            nodes: self.stats.nodes - before.nodes,
+            cutoffs: self.stats.cutoffs - before.cutoffs,
+            tt_hits: self.stats.tt_hits - before.tt_hits,
+            max_depth: self.stats.max_depth,
+            elapsed: start.elapsed(),
+        };
+        self.stats.max_depth = stats.max_depth.max(before.max_depth);
+        (result, stats)
+    }
+
+    fn measured(
+        &mut self,
+        run: impl FnOnce(&mut Self) -> SearchResult<G::Move>,
+    ) -> SearchResult<G::Move> {
+        let (mut result, stats) = self.measure(run);
+        result.stats = stats;
+        result
+    }
+
+    // Extend a principal variation cut short by a transposition table
+    // cutoff, searching from where it stops with the table covering most
+    // of the remaining tree. The line starts `ply` plies below the root.
+    fn complete_pv(
+        &mut self,
+        game: &mut G,
+        result: &mut SearchResult<G::Move>,
+        depth: u8,
+        ply: u8,
+    ) {
+        if !self.options.tt {
+            return;
+        }
+        for &mv in &result.pv {
+            game.apply(mv);
+        }
+        let played = result.pv.len();
+        while result.pv.len() < depth as usize && !game.is_terminal() {
+            let remaining = depth - result.pv.len() as u8;
+            self.root_ply = ply + result.pv.len() as u8;
+            let rest = self.negamax_ply(game, -INFINITY, INFINITY, remaining, 0);
+            self.root_ply = 0;
+            if self.aborted || rest.pv.is_empty() {
+                break;
+            }
+            for &mv in &rest.pv {
+                game.apply(mv);
+            }
+            result.pv.extend(rest.pv);
+        }
+        for &mv in result.pv.iter().rev() {
+            game.undo(mv);
+        }
+        // The searched result stays valid when only its line was cut short
+        if self.aborted {
+            result.pv.truncate(played);
+            self.aborted = false;
+        }
+    }
+
+    // Whether the node limit or the deadline passed or another thread asked
+    // to stop, the clock and other threads being checked once CHECK_INTERVAL
+    // positions were visited since the last check, however many leaves a
+    // single node has
+    fn out_of_budget(&mut self) -> bool {
+        if !self.aborted {
+            self.aborted = self
+                .node_limit
+                .is_some_and(|limit| self.evaluations >= limit);
+        }
+        if !self.aborted && self.evaluations.wrapping_sub(self.last_check) >= CHECK_INTERVAL {
+            self.last_check = self.evaluations;
+            self.aborted = self.deadline.is_some_and(|d| Instant::now() >= d)
+                || self
+                    .stop
+                    .as_ref()
+                    .is_some_and(|stop| stop.load(Ordering::Relaxed));
+        }
+        self.aborted
+    }
+
+    // Remember a move that caused a beta cutoff at this ply
+    fn store_killer(&mut self, mv: G::Move, ply: u8) {
+        let ply = ply as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+    }
+
+    // Reward a move that caused a beta cutoff, more so far from the leaves
+    fn store_history(&mut self, game: &G, mv: G::Move, depth: u8) {
+        if let Some(index) = game.move_index(mv) {
+            if self.history.len() <= index {
+                self.history.resize(index + 1, 0);
+            }
+            self.history[index] = self.history[index].saturating_add(u32::from(depth).pow(2));
+        }
+    }
+
+    fn history_score(&self, game: &G, mv: G::Move) -> u32 {
+        game.move_index(mv)
+            .and_then(|index| self.history.get(index))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // Legal moves in the order they should be searched
+    fn ordered_moves(&self, game: &G, ply: u8, tt_move: Option<G::Move>) -> Vec<G::Move> {
+        let mut moves = game.legal_moves();
+        if self.options.ordering {
+            moves.sort_by_key(|&mv| -game.move_priority(mv));
+        }
+        if self.options.history {
+            moves.sort_by_key(|&mv| Reverse(self.history_score(game, mv)));
+        }
+        if let Some(killers) = self
+            .killers
+            .get(ply as usize)
+            .filter(|_| self.options.killers)
+        {
+            moves.sort_by_key(|&mv| !killers.contains(&Some(mv)));
+        }
+        if !self.options.ordering {
+            return moves;
+        }
+        let hint = if ply == 0 {
+            self.root_hint.or(tt_move)
+        } else {
+            tt_move
+        };
+        if let Some(i) = hint.and_then(|hint| moves.iter().position(|&mv| mv == hint)) {
+            moves[..=i].rotate_right(1);
+        }
+        moves
+    }
+
+    // Score of a move whose child entry proves it fails high, searched to
+    // `depth` at least, and whether that entry reached the depth limit
+    fn transposition_cutoff(
+        &self,
+        game: &mut G,
+        beta: Score,
+        depth: u8,
+        ply: u8,
+    ) -> Option<(Score, bool)> {
+        game.legal_moves().into_iter().find_map(|mv| {
+            game.apply(mv);
+            let entry = game.hash_key().and_then(|key| self.tt.probe(key));
+            game.undo(mv);
+            let entry = entry.filter(|entry| entry.depth >= depth)?;
+            // Bounds from the child, an upper one bounding this score below
+            let score = -from_node(entry.score, ply + 1);
+            let lower = matches!(entry.bound, Bound::Exact | Bound::Upper);
+            (lower && score >= beta).then_some((score, entry.limited))
+        })
+    }
+
+    // Score of a decided position from its side to move, draws being
+    // biased by the contempt
+    fn exact_score(&self, score: Score, ply: u8) -> Score {
+        match score {
+            DRAW if (self.root_ply + ply).is_multiple_of(2) => -self.options.contempt,
+            DRAW => self.options.contempt,
+            score => from_node(score, ply),
+        }
+    }
+
+    // Search a position with its own depth limit flag, so that the table
+    // entry stored for it tells whether its subtree reached the limit
+    fn negamax_ply(
+        &mut self,
+        game: &mut G,
+        alpha: Score,
+        beta: Score,
+        depth: u8,
+        ply: u8,
+    ) -> SearchResult<G::Move> {
+        let outer = std::mem::take(&mut self.depth_limited);
+        let result = self.negamax_node(game, alpha, beta, depth, ply);
+        self.depth_limited |= outer;
+        result
+    }
+
+    fn negamax_node(
+        &mut self,
+        game: &mut G,
+        mut alpha: Score,
+        beta: Score,
+        depth: u8,
+        ply: u8,
+    ) -> SearchResult<G::Move> {
+        let leaf = |score| SearchResult::new(score, vec![]);
+        self.stats.max_depth = self.stats.max_depth.max(ply);
+        let known = self
+            .tablebase
+            .as_ref()
+            .filter(|_| ply > 0)
+            .and_then(|table| table.probe(game.hash_key()?));
+        if game.is_terminal() {
+            return leaf(self.exact_score(game.evaluate(), ply));
+        } else if let Some(score) = known {
+            return leaf(self.exact_score(score, ply));
+        }
+        // Positions with forcing moves at the depth limit have their
+        // forcing moves searched at depth 0 again
+        let mut forcing = if depth == 0 && self.options.extend_forcing {
+            game.forcing_moves()
+        } else {
+            vec![]
+        };
+        if depth == 0 && forcing.is_empty() {
+            self.depth_limited = true;
+            return leaf(self.evaluator.evaluate(game));
+        } else if self.out_of_budget() {
+            return leaf(DRAW);
+        }
+        let child_depth = depth.saturating_sub(1);
+        let alpha_orig = alpha;
+        let fail_soft = self.options.fail_soft;
+        let clamp = |score: Score| {
+            if fail_soft {
+                score
+            } else {
+                score.max(alpha_orig).min(beta)
+            }
+        };
+        let key = game.hash_key().filter(|_| self.options.tt);
+        let entry = key.and_then(|key| self.tt.probe(key));
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            let score = from_node(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                // The stored best move may be the one of a symmetric
+                // position, the line stops here
+                self.stats.tt_hits += 1;
+                self.depth_limited |= entry.limited;
+                return leaf(clamp(score));
+            }
+        }
+        if self.options.etc && key.is_some() && ply > 0 && depth > 0 {
+            if let Some((score, limited)) = self.transposition_cutoff(game, beta, child_depth, ply)
+            {
+                self.stats.tt_hits += 1;
+                self.depth_limited |= limited;
+                self.stats.cutoffs += 1;
+                return leaf(clamp(score));
+            }
+        }
+        // Passing failing high means that any move would, unless the game
+        // has zugzwangs. Decisive bounds are left to the full search.
+        let quiet_window = |bound: Score| bound.abs() < WIN && !is_decisive(bound);
+        if let Some(reduction) = self.options.null_move.filter(|&r| depth > r) {
+            if ply > 0 && !self.in_null && quiet_window(beta) && game.pass() {
+                self.in_null = true;
+                let child = self.negamax_ply(game, -beta, 1 - beta, depth - 1 - reduction, ply + 1);
+                self.in_null = false;
+                game.undo_pass();
+                if !self.aborted && -child.score >= beta {
+                    self.stats.cutoffs += 1;
+                    return leaf(beta);
+                }
+            }
+        }
+        // Hopeless positions next to the depth limit only get their forcing
+        // moves searched, the result being no longer exact
+        let futile = depth == 1
+            && ply > 0
+            && quiet_window(alpha)
+            && self.options.futility.is_some_and(|margin| {
+                self.evaluator.evaluate(game).saturating_add(margin) <= alpha
+            });
+        if futile {
+            forcing = game.forcing_moves();
+            if forcing.is_empty() {
+                self.depth_limited = true;
+                return leaf(clamp(self.evaluator.evaluate(game)));
+            }
+        }
+        let mut tt_move = entry.and_then(|entry| entry.best);
+        if self.options.iid && self.options.ordering && tt_move.is_none() && depth > IID_REDUCTION {
+            let shallow = self.negamax_ply(game, alpha, beta, depth - IID_REDUCTION, ply);
+            if self.aborted {
+                return leaf(DRAW);
+            }
+            tt_move = shallow.best();
+        }
+        let mut moves = if depth == 0 || futile {
+            forcing
+        } else {
+            self.ordered_moves(game, ply, tt_move)
+        };
+        let twins = if ply == 0 && self.options.symmetry {
+            group_equivalent(game, &mut moves)
+        } else {
+            vec![]
+        };
+        let reductions = self
+            .options
+            .reductions
+            .filter(|reductions| ply > 0 && child_depth > reductions.depth);
+        let unreduced = if reductions.is_some() {
+            game.forcing_moves()
+        } else {
+            vec![]
+        };
+        let mut value = -INFINITY;
+        let mut best_lines: Vec<Vec<G::Move>> = vec![];
+        for (i, mv) in moves.into_iter().enumerate() {
+            self.evaluations += 1;
+            self.stats.nodes += 1;
+            // Late quiet moves are first searched shallower, with a null
+            // window, and again as usual when beating alpha
+            let reduced = reductions
+                .filter(|reductions| i >= reductions.full_moves.max(1) && !unreduced.contains(&mv));
+            game.apply(mv);
+            let reduced = reduced.map(|reductions| {
+                let depth = child_depth - reductions.depth;
+                self.negamax_ply(game, -alpha - 1, -alpha, depth, ply + 1)
+            });
+            let child = match reduced {
+                Some(child) if self.aborted || -child.score <= alpha => child,
+                _ if i == 0 || !self.options.pvs => {
+                    self.negamax_ply(game, -beta, -alpha, child_depth, ply + 1)
+                }
+                _ => {
+                    let child = self.negamax_ply(game, -alpha - 1, -alpha, child_depth, ply + 1);
+                    if -child.score > alpha && -child.score < beta {
+                        self.negamax_ply(game, -beta, -alpha, child_depth, ply + 1)
+                    } else {
+                        child
+                    }
+                }
+            };
+            game.undo(mv);
+            if self.aborted {
+                break;
+            }
+            let score = -child.score;
+            let line = || [vec![mv], child.pv].concat();
+            if score > value {
+                value = score;
+                best_lines = vec![line()];
+            } else if score == value && ply == 0 {
+                best_lines.push(line());
+            }
+            if score >= beta {
+                self.stats.cutoffs += 1;
+                self.store_killer(mv, ply);
+                if self.options.history {
+                    self.store_history(game, mv, depth);
+                }
+                break;
+            }
+            // Root moves are searched with a window starting just below the
+            // best score, so that moves equal to it are scored exactly and
+            // the chosen one is drawn among them
+            alpha = alpha.max(if ply == 0 {
+                score.saturating_sub(1)
+            } else {
+                score
+            });
+        }
+        let value = clamp(value);
+        let pv = draw_line(game, &twins, &best_lines, &mut self.rng);
+        let best = pv.first().copied();
+        if let Some(key) = key.filter(|_| !self.aborted && !futile) {
+            // Fail highs give lower bounds, fail lows upper bounds,
+            // anything strictly inside the window is exact
+            let bound = if value >= beta {
+                Bound::Lower
+            } else if value <= alpha_orig {
+                Bound::Upper
+            } else {
+                Bound::Exact
+            };
+            self.tt.store(
+                key,
+                Entry {
+                    depth,
+                    score: to_node(value, ply),
+                    bound,
+                    best,
+                    limited: self.depth_limited,
+                },
+            );
+        }
+        SearchResult::new(value, pv)
+    }
+}
+
+// Group moves by the key of the position they reach, keeping only the
+// first move of each group in `moves`. Nothing is grouped for games
+// without keys.
+fn group_equivalent<G: GameState>(game: &mut G, moves: &mut Vec<G::Move>) -> Vec<Vec<G::Move>> {
+    let mut groups: Vec<(u64, Vec<G::Move>)> = vec![];
+    for &mv in moves.iter() {
+        game.apply(mv);
+        let key = game.hash_key();
+        game.undo(mv);
+        let Some(key) = key else {
+            return vec![];
+        };
+        match groups.iter_mut().find(|(other, _)| *other == key) {
+            Some((_, group)) => group.push(mv),
+            None => groups.push((key, vec![mv])),
+        }
+    }
+    *moves = groups.iter().map(|(_, group)| group[0]).collect();
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+// Draw one of the best lines at random. When root moves were grouped,
+// draw among all moves equivalent to the first move of a best line instead,
+// playing that line in the orientation of the drawn move.
+fn draw_line<G: GameState>(
+    game: &mut G,
+    twins: &[Vec<G::Move>],
+    best_lines: &[Vec<G::Move>],
+    rng: &mut impl Rng,
+) -> Vec<G::Move> {
+    if twins.is_empty() {
+        return best_lines.choose(rng).cloned().unwrap_or_default();
+    }
+    let twins: Vec<_> = twins
+        .iter()
+        .filter_map(|group| {
+            let line = best_lines.iter().find(|line| line[0] == group[0])?;
+            Some(group.iter().map(move |&mv| (mv, line)))
+        })
+        .flatten()
+        .collect();
+    match twins.choose(rng) {
+        Some(&(mv, line)) => transpose_line(game, mv, line),
+        None => vec![],
+    }
+}
+
+// Replay a line from the position reached by `mv` rather than by the
+// first move of the line, both reaching the same key, playing at each step
+// the move reaching the same key as the one of the line
+fn transpose_line<G: GameState>(game: &mut G, mv: G::Move, line: &[G::Move]) -> Vec<G::Move> {
+    if line[0] == mv {
+        return line.to_vec();
+    }
+    let mut keys = vec![];
+    for &next in line {
+        game.apply(next);
+        keys.push(game.hash_key());
+    }
+    for &next in line.iter().rev() {
+        game.undo(next);
+    }
+    let mut transposed = vec![mv];
+    game.apply(mv);
+    for &key in &keys[1..] {
+        let next = game.legal_moves().into_iter().find(|&next| {
+            game.apply(next);
+            let reached = game.hash_key();
+            game.undo(next);
+            reached == key
+        });
+        let Some(next) = next else {
+            break;
+        };
+        game.apply(next);
+        transposed.push(next);
+    }
+    for &next in transposed.iter().rev() {
+        game.undo(next);
+    }
+    transposed
+}
+
+// Turn a decisive score counted from a node `ply` plies deep into one
+// counted from the root, wins being further away from the root
+pub(crate) fn from_node(score: Score, ply: u8) -> Score {
+    if is_decisive(score) {
+        score - score.signum() * Score::from(ply)
+    } else {
+        score
+    }
+}
+
+// Inverse of from_node, transposition table entries are stored counted
+// from their own node as the same position may be reached at other plies
+fn to_node(score: Score, ply: u8) -> Score {
+    if is_decisive(score) {
+        score + score.signum() * Score::from(ply)
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Game, Move};
+
+    #[test]
+    fn history_saturates() {
+        let game = Game::new();
+        let mut search = Search::with_options(SearchOptions {
+            history: true,
+            ..SearchOptions::default()
+        });
+        let mv = game.legal_moves()[0];
+        search.store_history(&game, mv, u8::MAX);
+        let index = game.move_index(mv).unwrap();
+        search.history[index] = u32::MAX - 1;
+        search.store_history(&game, mv, u8::MAX);
+        assert_eq!(search.history_score(&game, mv), u32::MAX);
+    }
+
+    #[test]
+    fn contempt_survives_pv_completion() {
+        for (contempt, moves) in [(7, &["c2"][..]), (-7, &["b2"]), (7, &["b2", "a1"])] {
+            let options = SearchOptions {
+                contempt,
+                ..SearchOptions::default()
+            };
+            let moves: Vec<Move> = moves.iter().map(|mv| mv.parse().unwrap()).collect();
+            let mut game = Game::new();
+            game.play_moves(&moves).unwrap();
+            let fresh = Search::with_options(options).search(&mut game, u8::MAX);
+            let mut search = Search::with_options(options);
+            search.multipv(&mut game, u8::MAX, 9);
+            let again = search.search(&mut game, u8::MAX);
+            assert_eq!(again.score, fresh.score, "after {:?}", moves);
+        }
+    }
+
+    #[test]
+    fn history_off_is_not_updated() {
+        let mut game = Game::with_dimensions(4, 4, 4).unwrap();
+        let mut search = Search::new();
+        search.search(&mut game, 6);
+        assert!(search.history.is_empty());
+    }
+}
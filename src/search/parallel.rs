@@ -0,0 +1,241 @@
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use rayon::prelude::*;
+
+use super::{
+    draw_line, group_equivalent, Evaluator, GameState, Parallel, Score, Search, SearchResult,
+    INFINITY,
+};
+
+// Remaining depth below which YBWC nodes are searched serially
+const SPLIT_DEPTH: u8 = 3;
+
+impl<G, E> Search<G, E>
+where
+    G: GameState + Clone + Send + Sync,
+    G::Move: Send + Sync,
+    E: Evaluator<G> + Clone + Send + Sync,
+{
+    /// Search to a fixed depth with the strategy of
+    /// [`SearchOptions::parallel`](super::SearchOptions::parallel).
+    pub fn search_parallel(&mut self, game: &G, depth: u8) -> SearchResult<G::Move> {
+        match self.options.parallel {
+            Parallel::RootSplit => self.search_root_split(game, depth),
+            Parallel::LazySmp => self.search_lazy_smp(game, depth),
+            Parallel::Ybwc => self.search_ybwc(game, depth),
+        }
+    }
+
+    /// Search to a fixed depth, splitting the root moves across the rayon
+    /// thread pool. Each move is searched on its own copy of the game by
+    /// its own search with these options, the best score found so far
+    /// being shared to narrow the windows of the moves left.
+    pub fn search_root_split(&mut self, game: &G, depth: u8) -> SearchResult<G::Move> {
+        let mut game = game.clone();
+        self.measured(|search| {
+            if depth == 0 || game.is_terminal() {
+                return search.negamax_ply(&mut game, -INFINITY, INFINITY, depth, 0);
+            }
+            let mut moves = search.ordered_moves(&game, 0, None);
+            let twins = if search.options.symmetry {
+                group_equivalent(&mut game, &mut moves)
+            } else {
+                vec![]
+            };
+            // One above the lowest score, so that alpha stays in range
+            let best = AtomicI32::new(-INFINITY + 1);
+            let lines: Vec<_> = moves
+                .into_par_iter()
+                .map(|mv| {
+                    let mut worker =
+                        Search::with_evaluator(search.options, search.evaluator.clone());
+                    worker.tablebase = search.tablebase.clone();
+                    let mut game = game.clone();
+                    game.apply(mv);
+                    // Moves at least as good as the best one are scored exactly
+                    let alpha = best.load(Ordering::Relaxed) - 1;
+                    let mut child = worker.negamax_ply(&mut game, -INFINITY, -alpha, depth - 1, 1);
+                    worker.complete_pv(&mut game, &mut child, depth - 1, 1);
+                    best.fetch_max(-child.score, Ordering::Relaxed);
+                    (mv, child, worker)
+                })
+                .collect();
+
+            let value = lines
+                .iter()
+                .map(|(_, child, _)| -child.score)
+                .max()
+                .unwrap_or(-INFINITY);
+            let mut best_lines = vec![];
+            for (mv, child, worker) in lines {
+                search.evaluations += worker.evaluations + 1;
+                search.stats.nodes += worker.stats.nodes + 1;
+                search.stats.cutoffs += worker.stats.cutoffs;
+                search.stats.tt_hits += worker.stats.tt_hits;
+                search.stats.max_depth = search.stats.max_depth.max(worker.stats.max_depth);
+                if -child.score == value {
+                    best_lines.push([vec![mv], child.pv].concat());
+                }
+            }
+            search.last_score = value;
+            let pv = draw_line(&mut game, &twins, &best_lines, &mut search.rng);
+            SearchResult::new(value, pv)
+        })
+    }
+
+    /// Lazy SMP search to a fixed depth. Helper threads search the whole
+    /// tree too, every other one a ply deeper, sharing the transposition
+    /// table with this search, which gives the result. They stop when it
+    /// is done, so the result may use their deeper entries. Uses
+    /// [`SearchOptions::threads`](super::SearchOptions::threads) threads.
+    pub fn search_lazy_smp(&mut self, game: &G, depth: u8) -> SearchResult<G::Move> {
+        let mut game = game.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        self.measured(|search| {
+            thread::scope(|scope| {
+                let helpers: Vec<_> = (1..search.options.threads)
+                    .map(|id| {
+                        let mut helper = search.helper();
+                        helper.stop = Some(stop.clone());
+                        let mut game = game.clone();
+                        let depth = depth.saturating_add((id % 2) as u8);
+                        scope.spawn(move || {
+                            helper.negamax_ply(&mut game, -INFINITY, INFINITY, depth, 0);
+                            helper
+                        })
+                    })
+                    .collect();
+                let result = search.search(&mut game, depth);
+                stop.store(true, Ordering::Relaxed);
+                for helper in helpers {
+                    let helper = helper.join().expect("Helper search panicked");
+                    search.evaluations += helper.evaluations;
+                    search.stats.nodes += helper.stats.nodes;
+                    search.stats.cutoffs += helper.stats.cutoffs;
+                    search.stats.tt_hits += helper.stats.tt_hits;
+                }
+                result
+            })
+        })
+    }
+
+    /// Young Brothers Wait search to a fixed depth. At each node deep
+    /// enough, the first move is searched alone and the others split across
+    /// the rayon thread pool once its score narrowed their window, all
+    /// searches sharing the transposition table.
+    pub fn search_ybwc(&mut self, game: &G, depth: u8) -> SearchResult<G::Move> {
+        let mut game = game.clone();
+        self.measured(|search| {
+            let mut result = search.ybwc_ply(&mut game, -INFINITY, INFINITY, depth, 0);
+            search.last_score = result.score;
+            search.complete_pv(&mut game, &mut result, depth, 0);
+            result
+        })
+    }
+
+    fn ybwc_ply(
+        &mut self,
+        game: &mut G,
+        alpha: Score,
+        beta: Score,
+        depth: u8,
+        ply: u8,
+    ) -> SearchResult<G::Move> {
+        if depth < SPLIT_DEPTH || game.is_terminal() {
+            return self.negamax_ply(game, alpha, beta, depth, ply);
+        }
+        let mut moves = self.ordered_moves(game, ply, None);
+        let twins = if ply == 0 && self.options.symmetry {
+            group_equivalent(game, &mut moves)
+        } else {
+            vec![]
+        };
+        // Root moves equal to the best one are scored exactly, as in
+        // negamax_ply
+        let raise = |alpha: Score, score: Score| {
+            alpha.max(if ply == 0 {
+                score.saturating_sub(1)
+            } else {
+                score
+            })
+        };
+
+        // Eldest brother
+        let first = moves.remove(0);
+        self.evaluations += 1;
+        self.stats.nodes += 1;
+        game.apply(first);
+        let child = self.ybwc_ply(game, -beta, -alpha, depth - 1, ply + 1);
+        game.undo(first);
+        let mut value = -child.score;
+        let mut best_lines = vec![[vec![first], child.pv].concat()];
+        if value >= beta || moves.is_empty() {
+            if value >= beta {
+                self.stats.cutoffs += 1;
+            }
+            return SearchResult::new(self.clamp(value, alpha, beta), best_lines.remove(0));
+        }
+
+        // Young brothers
+        let best = AtomicI32::new(raise(alpha, value));
+        let cutoff = AtomicBool::new(false);
+        let lines: Vec<_> = moves
+            .into_par_iter()
+            .filter_map(|mv| {
+                if cutoff.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let mut worker = self.helper();
+                let mut game = game.clone();
+                game.apply(mv);
+                let alpha = best.load(Ordering::Relaxed);
+                let child = worker.ybwc_ply(&mut game, -beta, -alpha, depth - 1, ply + 1);
+                let score = -child.score;
+                best.fetch_max(raise(alpha, score), Ordering::Relaxed);
+                if score >= beta {
+                    cutoff.store(true, Ordering::Relaxed);
+                }
+                Some((mv, child, worker))
+            })
+            .collect();
+        for (mv, child, worker) in lines {
+            self.evaluations += worker.evaluations + 1;
+            self.stats.nodes += worker.stats.nodes + 1;
+            self.stats.cutoffs += worker.stats.cutoffs;
+            self.stats.tt_hits += worker.stats.tt_hits;
+            self.stats.max_depth = self.stats.max_depth.max(worker.stats.max_depth);
+            let score = -child.score;
+            let line = || [vec![mv], child.pv].concat();
+            if score > value {
+                value = score;
+                best_lines = vec![line()];
+            } else if score == value && ply == 0 {
+                best_lines.push(line());
+            }
+        }
+        if value >= beta {
+            self.stats.cutoffs += 1;
+        }
+        let pv = draw_line(game, &twins, &best_lines, &mut self.rng);
+        SearchResult::new(self.clamp(value, alpha, beta), pv)
+    }
+
+    // Search sharing the options, tables and stop flag of this one
+    fn helper(&self) -> Search<G, E> {
+        let mut helper = Search::with_evaluator(self.options, self.evaluator.clone());
+        helper.tt = self.tt.clone();
+        helper.tablebase = self.tablebase.clone();
+        helper.stop = self.stop.clone();
+        helper
+    }
+
+    fn clamp(&self, score: Score, alpha: Score, beta: Score) -> Score {
+        if self.options.fail_soft {
+            score
+        } else {
+            score.max(alpha).min(beta)
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+use crate::search::{GameState, Score};
+use crate::{Game, Move, MoveError, MAX_SIZE, MIN_SIZE};
+
+/// Tic-tac-toe on an `N`×`N` board won by a full row, column or diagonal,
+/// the size being part of the type. Sizes the board can't hold fail to
+/// compile rather than being rejected at run time by
+/// [`Game::with_size`], and move generation loops over the `N * N`
+/// squares of the board. The rules are those of the runtime-sized
+/// [`Game`] it wraps, which [`SquareGame::game`] gives access to.
+#[derive(Clone, Debug)]
+pub struct SquareGame<const N: usize> {
+    game: Game,
+}
+
+impl<const N: usize> Default for SquareGame<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> SquareGame<N> {
+    // Evaluated for each size a game is built with, failing the build for
+    // sizes out of range
+    const VALID_SIZE: () = assert!(
+        N >= MIN_SIZE as usize && N <= MAX_SIZE as usize,
+        "board size out of range"
+    );
+
+    /// Number of squares of the board.
+    pub const SQUARES: usize = N * N;
+
+    /// Create an empty board with the first player to move.
+    pub fn new() -> Self {
+        let () = Self::VALID_SIZE;
+        SquareGame {
+            game: Game::with_size(N as u8).expect("size checked at compile time"),
+        }
+    }
+
+    /// Create a game by replaying a sequence of moves.
+    pub fn from_moves(moves: &[Move]) -> Result<Self, MoveError> {
+        let mut game = Self::new();
+        game.play_moves(moves)?;
+        Ok(game)
+    }
+
+    /// Play a sequence of moves, see [`Game::play_moves`].
+    pub fn play_moves(&mut self, moves: &[Move]) -> Result<(), MoveError> {
+        self.game.play_moves(moves)
+    }
+
+    /// Underlying runtime-sized game.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn into_game(self) -> Game {
+        self.game
+    }
+}
+
+impl<const N: usize> From<SquareGame<N>> for Game {
+    fn from(game: SquareGame<N>) -> Self {
+        game.game
+    }
+}
+
+impl<const N: usize> fmt::Display for SquareGame<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.game)
+    }
+}
+
+impl<const N: usize> GameState for SquareGame<N> {
+    type Move = Move;
+
+    // Empty squares in board order, like `Game::moves`
+    fn legal_moves(&self) -> Vec<Move> {
+        let [x, o] = self.game.players();
+        let taken = x | o;
+        (0..Self::SQUARES)
+            .filter(|&square| !taken.contains(square as u32))
+            .map(|square| Move {
+                row: (square / N) as u8,
+                col: (square % N) as u8,
+            })
+            .collect()
+    }
+
+    fn apply(&mut self, mv: Move) {
+        self.game.apply(mv)
+    }
+
+    fn undo(&mut self, mv: Move) {
+        self.game.undo(mv)
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.game.is_terminal()
+    }
+
+    fn evaluate(&self) -> Score {
+        self.game.evaluate()
+    }
+
+    fn move_priority(&self, mv: Move) -> i32 {
+        self.game.move_priority(mv)
+    }
+
+    fn move_index(&self, mv: Move) -> Option<usize> {
+        Some(usize::from(mv.row) * N + usize::from(mv.col))
+    }
+
+    fn hash_key(&self) -> Option<u64> {
+        self.game.hash_key()
+    }
+
+    fn forcing_moves(&self) -> Vec<Move> {
+        self.game.forcing_moves()
+    }
+
+    fn threat_scores(&self) -> [Score; 2] {
+        self.game.threat_scores()
+    }
+
+    fn pass(&mut self) -> bool {
+        self.game.pass()
+    }
+
+    fn undo_pass(&mut self) {
+        self.game.undo_pass()
+    }
+}
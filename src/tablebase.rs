@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::search::{from_node, GameState, Score};
+use crate::{Game, Move};
+
+// Start of tablebase files, followed by a format version
+const MAGIC: &[u8; 4] = b"TTTB";
+const VERSION: u8 = 4;
+
+/// Exact scores of every position reachable from a starting one, keyed by
+/// [`GameState::hash_key`] and counted from the position itself like
+/// terminal scores. Built by retrograde analysis, practical up to 4×4.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tablebase {
+    width: u8,
+    height: u8,
+    layers: u8,
+    win_length: u8,
+    misere: bool,
+    toroidal: bool,
+    entries: HashMap<u64, Score>,
+}
+
+impl Tablebase {
+    /// Enumerate the positions reachable from `game` by number of stones,
+    /// then score them from the fullest ones backwards, each one from the
+    /// scores of the positions one stone further.
+    pub fn build(game: &Game) -> Self {
+        let mut scratch = game.clone();
+        let mut layers = vec![vec![game.players()]];
+        loop {
+            let mut next = HashSet::new();
+            for &players in layers.last().into_iter().flatten() {
+                scratch.set_players(players);
+                if scratch.is_terminal() {
+                    continue;
+                }
+                for mv in scratch.legal_moves() {
+                    scratch.apply(mv);
+                    next.insert(scratch.canonical());
+                    scratch.undo(mv);
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            layers.push(next.into_iter().collect());
+        }
+
+        let mut entries = HashMap::new();
+        for players in layers.into_iter().rev().flatten() {
+            scratch.set_players(players);
+            let score = if scratch.is_terminal() {
+                scratch.evaluate()
+            } else {
+                let mut best = Score::MIN;
+                for mv in scratch.legal_moves() {
+                    scratch.apply(mv);
+                    let child = entries[&key(&scratch)];
+                    scratch.undo(mv);
+                    best = best.max(-from_node(child, 1));
+                }
+                best
+            };
+            entries.insert(key(&scratch), score);
+        }
+        Tablebase {
+            width: game.width(),
+            height: game.height(),
+            layers: game.layers(),
+            win_length: game.win_length(),
+            misere: game.is_misere(),
+            toroidal: game.is_toroidal(),
+            entries,
+        }
+    }
+
+    /// Table of the classic 3×3 game, built on first use in about a
+    /// millisecond.
+    pub fn classic() -> &'static Tablebase {
+        static CLASSIC: OnceLock<Tablebase> = OnceLock::new();
+        CLASSIC.get_or_init(|| Tablebase::build(&Game::new()))
+    }
+
+    /// Exact score of a position from its side to move.
+    pub fn probe(&self, key: u64) -> Option<Score> {
+        self.entries.get(&key).copied()
+    }
+
+    /// Optimal move, chosen at random among equally good ones, without
+    /// searching. `None` when the game is over or missing from the table.
+    pub fn best_move(&self, game: &Game, rng: &mut impl Rng) -> Option<Move> {
+        if game.is_terminal() {
+            return None;
+        }
+        let mut game = game.clone();
+        let mut best_moves = vec![];
+        let mut best_score = Score::MIN;
+        for mv in game.legal_moves() {
+            game.apply(mv);
+            let score = self.probe(key(&game)).map(|child| -from_node(child, 1));
+            game.undo(mv);
+            let score = score?;
+            if score == best_score {
+                best_moves.push(mv);
+            } else if score > best_score {
+                best_score = score;
+                best_moves = vec![mv];
+            }
+        }
+        best_moves.choose(rng).copied()
+    }
+
+    /// Whether the table was built for the board of a game.
+    pub fn is_for(&self, game: &Game) -> bool {
+        (self.width, self.height, self.layers) == (game.width(), game.height(), game.layers())
+            && (self.win_length, self.misere, self.toroidal)
+                == (game.win_length(), game.is_misere(), game.is_toroidal())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the table to a file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[
+            VERSION,
+            self.width,
+            self.height,
+            self.layers,
+            self.win_length,
+            // Rules as flags, misère first
+            u8::from(self.misere) | u8::from(self.toroidal) << 1,
+        ])?;
+        file.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for (key, score) in &self.entries {
+            file.write_all(&key.to_le_bytes())?;
+            file.write_all(&score.to_le_bytes())?;
+        }
+        file.flush()
+    }
+
+    /// Read a table written by [`Tablebase::save`].
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut header = [0; 10];
+        file.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a tablebase file",
+            ));
+        }
+        let [width, height, layers, win_length] = [header[5], header[6], header[7], header[8]];
+        let mut len = [0; 8];
+        file.read_exact(&mut len)?;
+        let mut entries = HashMap::new();
+        for _ in 0..u64::from_le_bytes(len) {
+            let mut key = [0; 8];
+            let mut score = [0; 4];
+            file.read_exact(&mut key)?;
+            file.read_exact(&mut score)?;
+            entries.insert(u64::from_le_bytes(key), Score::from_le_bytes(score));
+        }
+        Ok(Tablebase {
+            width,
+            height,
+            layers,
+            win_length,
+            misere: header[9] & 1 != 0,
+            toroidal: header[9] & 2 != 0,
+            entries,
+        })
+    }
+}
+
+fn key(game: &Game) -> u64 {
+    game.hash_key().expect("games always have a key")
+}
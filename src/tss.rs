@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use crate::search::GameState;
+use crate::{Bitboard, Game, Move};
+
+/// Default number of threats a [`ThreatSearch`] may chain.
+pub const MAX_THREATS: u8 = 32;
+
+/// Threat-space search, looking for wins by a sequence of threats only,
+/// each one leaving the opponent a single square to block. The replies
+/// being forced, sequences far longer than a full width search could
+/// reach are proven. Failing to find one doesn't mean there is no win.
+#[derive(Clone, Debug)]
+pub struct ThreatSearch {
+    /// Most threats chained in a sequence.
+    pub max_threats: u8,
+    /// Number of positions visited so far.
+    pub nodes: u32,
+    // Threats left when positions were searched without finding a win
+    refuted: HashMap<u64, u8>,
+}
+
+impl Default for ThreatSearch {
+    fn default() -> Self {
+        Self::new(MAX_THREATS)
+    }
+}
+
+impl ThreatSearch {
+    pub fn new(max_threats: u8) -> Self {
+        ThreatSearch {
+            max_threats,
+            nodes: 0,
+            refuted: HashMap::new(),
+        }
+    }
+
+    /// Winning line of the side to move, its threats and the forced replies
+    /// ending with the winning move, or `None` when threats alone don't
+    /// win within `max_threats`, as always under misère rules.
+    pub fn find_win(&mut self, game: &Game) -> Option<Vec<Move>> {
+        if game.is_over() || game.is_misere() {
+            return None;
+        }
+        self.refuted.clear();
+        self.attack(&mut game.clone(), self.max_threats)
+    }
+
+    // Winning line of the side to move, attacking with `threats` at most
+    fn attack(&mut self, game: &mut Game, threats: u8) -> Option<Vec<Move>> {
+        self.nodes += 1;
+        let turn = game.turn();
+        if let Some(square) = first_square(game.winning_squares(turn)) {
+            return Some(vec![game.square_move(square)]);
+        }
+        let key = game.hash_key();
+        if threats == 0 || key.is_some_and(|key| self.refuted.get(&key) >= Some(&threats)) {
+            return None;
+        }
+        // A threat of the opponent must be blocked, by a threat to go on
+        let blocks = game.winning_squares(1 - turn);
+        let candidates = match blocks.count_ones() {
+            0 => threat_squares(game, turn),
+            1 => blocks & threat_squares(game, turn),
+            _ => Bitboard::EMPTY,
+        };
+        for square in candidates.ones() {
+            let mv = game.square_move(square);
+            game.apply(mv);
+            let line = self.defend(game, mv, threats);
+            game.undo(mv);
+            if line.is_some() {
+                return line;
+            }
+        }
+        if let Some(key) = key {
+            self.refuted.insert(key, threats);
+        }
+        None
+    }
+
+    // Winning line of the attacker who just threatened with `mv`, the
+    // opponent answering on the only square stopping an immediate win
+    fn defend(&mut self, game: &mut Game, mv: Move, threats: u8) -> Option<Vec<Move>> {
+        self.nodes += 1;
+        let turn = game.turn();
+        if game.is_over() || !game.winning_squares(turn).is_empty() {
+            return None;
+        }
+        let wins = game.winning_squares(1 - turn);
+        let block = game.square_move(first_square(wins)?);
+        if wins.count_ones() > 1 {
+            // Double threat, one of them is left open
+            let win = game.square_move(first_square(wins ^ Bitboard::bit(game.square(block)))?);
+            return Some(vec![mv, block, win]);
+        }
+        game.apply(block);
+        let rest = self.attack(game, threats - 1);
+        game.undo(block);
+        rest.map(|rest| [vec![mv, block], rest].concat())
+    }
+}
+
+// Empty squares completing a line of `turn` to one stone short of a win
+fn threat_squares(game: &Game, turn: u8) -> Bitboard {
+    let [x, o] = game.players();
+    let (player, opponent) = if turn == 0 { (x, o) } else { (o, x) };
+    game.wins()
+        .iter()
+        .filter(|&&mask| (opponent & mask).is_empty())
+        .map(|&mask| mask & !player)
+        .filter(|missing| missing.count_ones() == 2)
+        .fold(Bitboard::EMPTY, |squares, missing| squares | missing)
+}
+
+fn first_square(board: Bitboard) -> Option<u32> {
+    board.ones().next()
+}
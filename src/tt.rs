@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use crate::search::Score;
+
+// Number of independently locked parts of a table
+const SHARDS: usize = 64;
+
+/// Relation between a stored score and the true score of the position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The true score is at least the stored one.
+    Lower,
+    /// The true score is at most the stored one.
+    Upper,
+}
+
+/// Result of a search stored for a position.
+#[derive(Clone, Copy, Debug)]
+pub struct Entry<M> {
+    /// Remaining depth the position was searched to.
+    pub depth: u8,
+    pub score: Score,
+    pub bound: Bound,
+    pub best: Option<M>,
+    /// Whether the search stopped at the depth limit somewhere below the
+    /// position, the score then being heuristic rather than exact.
+    pub limited: bool,
+}
+
+/// Search results keyed by [`GameState::hash_key`](crate::GameState::hash_key),
+/// split in independently locked shards so that threads can share it.
+#[derive(Debug)]
+pub struct TranspositionTable<M> {
+    shards: Vec<Mutex<HashMap<u64, Entry<M>>>>,
+    // Positions kept per shard
+    capacity: usize,
+}
+
+impl<M: Copy> TranspositionTable<M> {
+    /// Table holding at most about `capacity` positions.
+    pub fn new(capacity: usize) -> Self {
+        TranspositionTable {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            capacity: capacity.div_ceil(SHARDS),
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry<M>> {
+        self.shard(key).get(&key).copied()
+    }
+
+    /// Store an entry, keeping a deeper one already stored for the position.
+    /// New positions are dropped once the table is full.
+    pub fn store(&self, key: u64, entry: Entry<M>) {
+        let mut shard = self.shard(key);
+        let full = shard.len() >= self.capacity;
+        match shard.get_mut(&key) {
+            Some(old) if old.depth <= entry.depth => *old = entry,
+            None if !full => {
+                shard.insert(key, entry);
+            }
+            _ => {}
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        (0..SHARDS).map(|i| self.lock(i).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        (0..SHARDS).for_each(|i| self.lock(i).clear())
+    }
+
+    fn shard(&self, key: u64) -> MutexGuard<'_, HashMap<u64, Entry<M>>> {
+        self.lock((key % SHARDS as u64) as usize)
+    }
+
+    // A thread panicking while holding a shard can't leave it inconsistent
+    fn lock(&self, shard: usize) -> MutexGuard<'_, HashMap<u64, Entry<M>>> {
+        self.shards[shard]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Combine a word into a hash key, used to build position keys.
+pub fn mix(key: u64, word: u64) -> u64 {
+    // splitmix64 finalizer
+    let mut z = (key ^ word).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,413 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use rayon::prelude::*;
+
+use crate::eval::{Evaluator, ThreatEvaluator, Weights};
+use crate::player::Player;
+use crate::search::{Outcome, Score, Search, SearchOptions};
+use crate::{Game, GameStatus};
+
+// Steps tried on each weight by Texel tuning, coarse ones first
+const STEPS: [Score; 3] = [16, 4, 1];
+
+/// Position with the result expected for its side to move, 1 for a win,
+/// 0.5 for a draw and 0 for a loss.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub position: Game,
+    pub result: f64,
+}
+
+/// Positions labeled with results, to tune evaluation weights on.
+#[derive(Clone, Debug, Default)]
+pub struct Corpus {
+    pub samples: Vec<Sample>,
+}
+
+impl Corpus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Corpus of `games` games played from `start` by two players, each
+    /// position labeled with the result of its game.
+    pub fn self_play(start: &Game, games: usize, players: [&mut dyn Player; 2]) -> Self {
+        Self::self_play_from(&vec![start.clone(); games], players)
+    }
+
+    /// Corpus of one game from each of `starts`, such as random positions
+    /// from [`Game::random_start`], played by two players.
+    pub fn self_play_from(starts: &[Game], players: [&mut dyn Player; 2]) -> Self {
+        let mut corpus = Corpus::new();
+        for start in starts {
+            let mut game = start.clone();
+            while !game.is_over() {
+                let mv = players[game.turn() as usize].choose_move(&game);
+                game.make_move(mv).expect("Illegal move chosen");
+            }
+            corpus.add_game(start, &game);
+        }
+        corpus
+    }
+
+    /// Add the positions a finished game went through after `start`, but
+    /// the last one, labeled with its result.
+    pub fn add_game(&mut self, start: &Game, game: &Game) {
+        let status = game.status();
+        let mut position = start.clone();
+        for &mv in &game.history()[start.history().len()..] {
+            let result = match status {
+                GameStatus::Won(player) if player == position.turn() => 1.0,
+                GameStatus::Won(_) => 0.0,
+                _ => 0.5,
+            };
+            self.samples.push(Sample {
+                position: position.clone(),
+                result,
+            });
+            position.make_move(mv).expect("Illegal move in game");
+        }
+    }
+
+    /// Label every position with its exact value instead, searching each
+    /// one to the end of the game.
+    pub fn label_solved(&mut self) {
+        self.samples.par_iter_mut().for_each(|sample| {
+            let mut search = Search::new();
+            sample.result = match search.solve(&mut sample.position).outcome {
+                Outcome::Win => 1.0,
+                Outcome::Draw => 0.5,
+                Outcome::Loss => 0.0,
+            };
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Mean squared difference between the results of a corpus and those
+/// predicted from the scores of the weights, mapped to winning chances by
+/// a logistic of the score over `scale`.
+pub fn prediction_error(corpus: &Corpus, weights: Weights, scale: f64) -> f64 {
+    let evaluator = ThreatEvaluator::new(weights);
+    let total: f64 = corpus
+        .samples
+        .par_iter()
+        .map(|sample| {
+            let score = f64::from(evaluator.evaluate(&sample.position));
+            let predicted = 1.0 / (1.0 + (-score / scale).exp());
+            (sample.result - predicted).powi(2)
+        })
+        .sum();
+    total / corpus.len().max(1) as f64
+}
+
+/// Scale of the logistic of [`prediction_error`] fitting a corpus best
+/// with these weights, found by a search over powers of two refined by
+/// halving the interval.
+pub fn fit_scale(corpus: &Corpus, weights: Weights) -> f64 {
+    let error = |scale: f64| prediction_error(corpus, weights, scale);
+    let mut scale = (0..16)
+        .map(|power| f64::from(1 << power))
+        .min_by(|&a, &b| error(a).total_cmp(&error(b)))
+        .expect("Powers to try");
+    let mut step = scale / 2.0;
+    for _ in 0..16 {
+        scale = [scale - step, scale, scale + step]
+            .into_iter()
+            .filter(|&candidate| candidate > 0.0)
+            .min_by(|&a, &b| error(a).total_cmp(&error(b)))
+            .expect("Current scale is positive");
+        step /= 2.0;
+    }
+    scale
+}
+
+/// Texel tuning: weights minimizing the [`prediction_error`] of a corpus,
+/// from `initial` and with the logistic scale fitted to them. Each weight
+/// is moved up or down by decreasing steps as long as it lowers the error.
+/// The occupancy power is left as it is.
+pub fn texel(corpus: &Corpus, initial: Weights) -> Weights {
+    let scale = fit_scale(corpus, initial);
+    let mut best = initial;
+    let mut best_error = prediction_error(corpus, best, scale);
+    for step in STEPS {
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in 0..Weights::TUNABLE {
+                for delta in [step, -step] {
+                    let mut candidate = best;
+                    *candidate.tunable_mut()[i] += delta;
+                    let error = prediction_error(corpus, candidate, scale);
+                    if error < best_error {
+                        (best, best_error) = (candidate, error);
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Evolutionary tuning of weights, without gradient: each generation, a
+/// population of weights plays a round-robin tournament, the best half
+/// staying and breeding children replacing the other half. Children take
+/// each weight from either parent, some of them being mutated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Genetic {
+    /// Number of weights in each generation.
+    pub population: usize,
+    pub generations: u32,
+    /// Depth of the searches playing the matches.
+    pub depth: u8,
+    /// Random moves opening each match, none ending it, played once with
+    /// each side moving first so that matches between the same weights
+    /// differ.
+    pub opening_plies: u8,
+    /// Chance of each weight of a child to be mutated.
+    pub mutation_rate: f64,
+    /// Standard deviation of the mutations.
+    pub mutation_size: f64,
+    pub seed: Option<u64>,
+}
+
+impl Default for Genetic {
+    fn default() -> Self {
+        Genetic {
+            population: 16,
+            generations: 20,
+            depth: 2,
+            opening_plies: 2,
+            mutation_rate: 0.2,
+            mutation_size: 8.0,
+            seed: None,
+        }
+    }
+}
+
+impl Genetic {
+    /// Fittest weights of the last generation, evolved from mutations of
+    /// `initial` on games played from `start`.
+    pub fn evolve(&self, start: &Game, initial: Weights) -> Weights {
+        let mut rng = self
+            .seed
+            .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        let size = self.population.max(2);
+        let mut population = vec![initial];
+        while population.len() < size {
+            population.push(self.mutate(initial, &mut rng));
+        }
+        for _ in 0..self.generations {
+            let fitness = self.tournament(start, &population, &mut rng);
+            let mut ranked: Vec<_> = population.into_iter().zip(fitness).collect();
+            ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            population = ranked.into_iter().map(|(weights, _)| weights).collect();
+            population.truncate(size.div_ceil(2));
+            let parents = population.clone();
+            while population.len() < size {
+                let (a, b) = (
+                    parents.choose(&mut rng).expect("Parents survive"),
+                    parents.choose(&mut rng).expect("Parents survive"),
+                );
+                let child = self.crossover(*a, *b, &mut rng);
+                population.push(self.mutate(child, &mut rng));
+            }
+        }
+        let fitness = self.tournament(start, &population, &mut rng);
+        let best = (0..population.len())
+            .max_by(|&a, &b| fitness[a].total_cmp(&fitness[b]))
+            .expect("Population isn't empty");
+        population[best]
+    }
+
+    // Points of each weights over a round-robin of two games by pair, one
+    // for a win and a half for a draw
+    fn tournament(&self, start: &Game, population: &[Weights], rng: &mut StdRng) -> Vec<f64> {
+        let pairs: Vec<_> = (0..population.len())
+            .flat_map(|a| (a + 1..population.len()).map(move |b| (a, b)))
+            .map(|pair| (pair, rng.gen::<u64>()))
+            .collect();
+        let results: Vec<_> = pairs
+            .into_par_iter()
+            .map(|((a, b), seed)| {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let opening = start.with_random_moves(self.opening_plies, &mut rng);
+                let first = self.play(&opening, [population[a], population[b]]);
+                let second = self.play(&opening, [population[b], population[a]]);
+                (a, b, first + 1.0 - second)
+            })
+            .collect();
+        let mut points = vec![0.0; population.len()];
+        for (a, b, score) in results {
+            points[a] += score;
+            points[b] += 2.0 - score;
+        }
+        points
+    }
+
+    // Result of the weights moving first from `start`
+    fn play(&self, start: &Game, weights: [Weights; 2]) -> f64 {
+        let first = start.turn();
+        let mut game = start.clone();
+        while !game.is_over() {
+            let evaluator = ThreatEvaluator::new(weights[usize::from(game.turn() != first)]);
+            let mut search = Search::with_evaluator(SearchOptions::default(), evaluator);
+            let mv = search
+                .search(&mut game, self.depth.max(1))
+                .best()
+                .expect("Game isn't over");
+            game.make_move(mv).expect("Search moves are legal");
+        }
+        match game.status() {
+            GameStatus::Won(player) if player == first => 1.0,
+            GameStatus::Won(_) => 0.0,
+            _ => 0.5,
+        }
+    }
+
+    fn crossover(&self, mut child: Weights, mut other: Weights, rng: &mut StdRng) -> Weights {
+        for (weight, from_b) in child.tunable_mut().into_iter().zip(other.tunable_mut()) {
+            if rng.gen() {
+                *weight = *from_b;
+            }
+        }
+        child
+    }
+
+    fn mutate(&self, mut weights: Weights, rng: &mut StdRng) -> Weights {
+        let noise = Normal::new(0.0, self.mutation_size).expect("Valid mutation size");
+        for weight in weights.tunable_mut() {
+            if rng.gen_bool(self.mutation_rate) {
+                *weight += noise.sample(rng).round() as Score;
+            }
+        }
+        weights
+    }
+}
+
+/// TD(λ) training of weights from self-play: after each game, the winning
+/// chance predicted at every position, a logistic of its score over
+/// `scale`, is pulled toward its λ-return, the opposite of the chances
+/// predicted for the opponent later in the game down to the result, nearer
+/// ones counting more. Weights are learned as reals and rounded when read.
+#[derive(Clone, Debug)]
+pub struct TdLambda {
+    /// Decay of the later predictions in the λ-returns, 0 learning from the
+    /// next one only and 1 from the result only.
+    pub lambda: f64,
+    /// Learning rate.
+    pub alpha: f64,
+    /// Scale of the logistic mapping scores to winning chances.
+    pub scale: f64,
+    /// Depth of the searches choosing the moves of the games.
+    pub depth: u8,
+    /// Chance of playing a random move rather than the searched one.
+    pub epsilon: f64,
+    occupancy: u32,
+    weights: [f64; Weights::TUNABLE],
+    rng: StdRng,
+}
+
+impl TdLambda {
+    /// Training starting from `initial`, its occupancy power being kept.
+    pub fn new(mut initial: Weights) -> Self {
+        let weights = initial.tunable_mut().map(|&mut weight| f64::from(weight));
+        TdLambda {
+            lambda: 0.7,
+            alpha: 0.5,
+            scale: 100.0,
+            depth: 1,
+            epsilon: 0.1,
+            occupancy: initial.occupancy,
+            weights,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Same training drawing its moves from `seed`.
+    pub fn seeded(self, seed: u64) -> Self {
+        TdLambda {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Learned weights, rounded.
+    pub fn weights(&self) -> Weights {
+        let mut weights = Weights {
+            occupancy: self.occupancy,
+            ..Weights::default()
+        };
+        for (weight, &learned) in weights.tunable_mut().into_iter().zip(&self.weights) {
+            *weight = learned.round() as Score;
+        }
+        weights
+    }
+
+    /// Play `games` games from `start`, learning from each one once over.
+    pub fn train(&mut self, start: &Game, games: u32) {
+        for _ in 0..games {
+            let mut game = start.clone();
+            let mut features = vec![];
+            while !game.is_over() {
+                features.push(ThreatEvaluator::features(&game, self.occupancy));
+                let mv = if self.rng.gen_bool(self.epsilon) {
+                    game.random_move_with(&mut self.rng)
+                } else {
+                    let evaluator = ThreatEvaluator::new(self.weights());
+                    let mut search = Search::with_evaluator(SearchOptions::default(), evaluator);
+                    search
+                        .search(&mut game, self.depth.max(1))
+                        .best()
+                        .expect("Game isn't over")
+                };
+                game.make_move(mv).expect("Chosen moves are legal");
+            }
+            let result = match game.status() {
+                GameStatus::Won(player) if player == game.turn() => 1.0,
+                GameStatus::Won(_) => 0.0,
+                _ => 0.5,
+            };
+            self.learn(&features, result);
+        }
+    }
+
+    // Update the weights from the features of the positions of a game and
+    // the result of the side to move at its end
+    fn learn(&mut self, features: &[[Score; Weights::TUNABLE]], result: f64) {
+        let predictions: Vec<_> = features
+            .iter()
+            .map(|features| {
+                let score: f64 = features
+                    .iter()
+                    .zip(&self.weights)
+                    .map(|(&feature, weight)| f64::from(feature) * weight)
+                    .sum();
+                1.0 / (1.0 + (-score / self.scale).exp())
+            })
+            .collect();
+        let mut target = result;
+        for (i, (features, &predicted)) in features.iter().zip(&predictions).enumerate().rev() {
+            // λ-return, from the opponent's one after
+            target = match predictions.get(i + 1) {
+                None => 1.0 - result,
+                Some(next) => (1.0 - self.lambda) * (1.0 - next) + self.lambda * (1.0 - target),
+            };
+            let gradient = predicted * (1.0 - predicted) / self.scale;
+            for (weight, &feature) in self.weights.iter_mut().zip(features) {
+                *weight += self.alpha * (target - predicted) * gradient * f64::from(feature);
+            }
+        }
+    }
+}
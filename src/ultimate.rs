@@ -0,0 +1,324 @@
+use std::fmt;
+
+use crate::search::{GameState, Score, DRAW, LOSS};
+use crate::tt::mix;
+use crate::{GameStatus, Move, MoveError, PlayerId};
+
+// Squares of a local board, or local boards of the meta board
+const CELLS: u8 = 9;
+
+// Every cell of a 3×3 board
+const FULL: u16 = (1 << CELLS) - 1;
+
+// Winning lines of a 3×3 board, cells in row-major order
+const LINES: [u16; 8] = [
+    0b000_000_111,
+    0b000_111_000,
+    0b111_000_000,
+    0b001_001_001,
+    0b010_010_010,
+    0b100_100_100,
+    0b100_010_001,
+    0b001_010_100,
+];
+
+// Weight of a won local board on an open line of the meta board, against
+// one stone on an open line of a local board
+const META_SCORE: Score = 100;
+
+pub(crate) fn has_line(cells: u16) -> bool {
+    LINES.into_iter().any(|line| cells & line == line)
+}
+
+// Open line score of a 3×3 board from one side, the stones of each side on
+// a line still open to it squared
+fn open_lines(ours: u16, theirs: u16, blocked: u16) -> Score {
+    LINES
+        .iter()
+        .map(|&line| {
+            let (mine, other) = ((ours & line).count_ones(), (theirs & line).count_ones());
+            if line & blocked != 0 {
+                0
+            } else if other == 0 {
+                (mine * mine) as Score
+            } else if mine == 0 {
+                -((other * other) as Score)
+            } else {
+                0
+            }
+        })
+        .sum()
+}
+
+/// Ultimate tic-tac-toe: nine local 3×3 boards laid out as a 3×3 meta
+/// board. Winning a local board claims its cell of the meta board, and
+/// three claimed cells in a row win the game. Each move sends the opponent
+/// to the local board at the same position as the square played, unless
+/// that board is already won or full, in which case the opponent plays on
+/// any open board. The game is drawn when every board is closed without a
+/// line on the meta board.
+///
+/// Moves are squares of the whole 9×9 grid, local board `(row / 3, col /
+/// 3)` and square `(row % 3, col % 3)` within it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Ultimate {
+    // Squares of each local board held by each player
+    boards: [[u16; 2]; CELLS as usize],
+    // Local boards won by each player
+    won: [u16; 2],
+    // Local boards won or full
+    closed: u16,
+    // Local board the next move must be played on, any open one when None
+    forced: Option<u8>,
+    turn: PlayerId,
+    // Moves played, with the board forced before each of them
+    history: Vec<(Move, Option<u8>)>,
+}
+
+impl Default for Ultimate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ultimate {
+    pub fn new() -> Self {
+        Ultimate {
+            boards: [[0; 2]; CELLS as usize],
+            won: [0; 2],
+            closed: 0,
+            forced: None,
+            turn: 0,
+            history: vec![],
+        }
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = Move> + '_ {
+        self.history.iter().map(|&(mv, _)| mv)
+    }
+
+    /// Local board, numbered 0 to 8 in row-major order, the next move must
+    /// be played on, `None` when any open board will do.
+    pub fn forced_board(&self) -> Option<u8> {
+        self.forced
+    }
+
+    /// Player who won a local board, if any.
+    pub fn board_winner(&self, board: u8) -> Option<PlayerId> {
+        (0..2).find(|&player| self.won[usize::from(player)] & (1 << board) != 0)
+    }
+
+    /// Player holding a square of the 9×9 grid, if any.
+    pub fn stone(&self, mv: Move) -> Option<PlayerId> {
+        let (board, square) = Self::cell(mv);
+        let stones = self.boards[usize::from(board)];
+        (0..2).find(|&player| stones[usize::from(player)] & (1 << square) != 0)
+    }
+
+    // Local board and square within it of a move
+    fn cell(mv: Move) -> (u8, u8) {
+        (mv.row / 3 * 3 + mv.col / 3, mv.row % 3 * 3 + mv.col % 3)
+    }
+
+    // Move playing a square of a local board
+    fn cell_move(board: u8, square: u8) -> Move {
+        Move::new(board / 3 * 3 + square / 3, board % 3 * 3 + square % 3)
+    }
+
+    fn is_won(&self) -> bool {
+        has_line(self.won[0]) || has_line(self.won[1])
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.is_won() || self.closed == FULL
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.is_won() {
+            GameStatus::Won(1 - self.turn)
+        } else if self.closed == FULL {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// Check that a move can be played in the current position.
+    pub fn check_move(&self, mv: Move) -> Result<(), MoveError> {
+        if mv.row >= CELLS || mv.col >= CELLS {
+            return Err(MoveError::OutOfBounds);
+        }
+        let (board, _) = Self::cell(mv);
+        if self.is_over() {
+            Err(MoveError::GameOver)
+        } else if self.stone(mv).is_some() {
+            Err(MoveError::Occupied)
+        } else if self.closed & (1 << board) != 0 || self.forced.is_some_and(|f| f != board) {
+            Err(MoveError::WrongBoard)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+
+    // Whether playing a square claims its local board
+    fn claims(&self, board: u8, square: u8) -> bool {
+        let stones = self.boards[usize::from(board)][usize::from(self.turn)];
+        has_line(stones | 1 << square)
+    }
+}
+
+// Local boards side by side, rows of boards separated by blank lines, X for
+// the first player, O for the second, . when empty
+impl fmt::Display for Ultimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..CELLS {
+            if row > 0 {
+                writeln!(f)?;
+                if row % 3 == 0 {
+                    writeln!(f)?;
+                }
+            }
+            for col in 0..CELLS {
+                if col > 0 {
+                    write!(f, "{}", if col % 3 == 0 { "   " } else { " " })?;
+                }
+                let symbol = match self.stone(Move::new(row, col)) {
+                    Some(0) => 'X',
+                    Some(_) => 'O',
+                    None => '.',
+                };
+                write!(f, "{}", symbol)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GameState for Ultimate {
+    type Move = Move;
+
+    fn legal_moves(&self) -> Vec<Move> {
+        if self.is_over() {
+            return vec![];
+        }
+        let boards = match self.forced {
+            Some(board) => 1 << board,
+            None => FULL & !self.closed,
+        };
+        let mut moves = vec![];
+        for board in (0..CELLS).filter(|board| boards & (1 << board) != 0) {
+            let [x, o] = self.boards[usize::from(board)];
+            for square in (0..CELLS).filter(|square| (x | o) & (1 << square) == 0) {
+                moves.push(Self::cell_move(board, square));
+            }
+        }
+        moves
+    }
+
+    fn apply(&mut self, mv: Move) {
+        let (board, square) = Self::cell(mv);
+        self.history.push((mv, self.forced));
+        let stones = &mut self.boards[usize::from(board)];
+        stones[usize::from(self.turn)] |= 1 << square;
+        if has_line(stones[usize::from(self.turn)]) {
+            self.won[usize::from(self.turn)] |= 1 << board;
+            self.closed |= 1 << board;
+        } else if stones[0] | stones[1] == FULL {
+            self.closed |= 1 << board;
+        }
+        self.forced = (self.closed & (1 << square) == 0).then_some(square);
+        self.turn = 1 - self.turn;
+    }
+
+    // The board of a move was open before it, so undoing it reopens it
+    fn undo(&mut self, mv: Move) {
+        let (board, square) = Self::cell(mv);
+        let (_, forced) = self.history.pop().expect("A move was played");
+        self.turn = 1 - self.turn;
+        self.forced = forced;
+        self.boards[usize::from(board)][usize::from(self.turn)] &= !(1 << square);
+        self.won[usize::from(self.turn)] &= !(1 << board);
+        self.closed &= !(1 << board);
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Claiming a local board first, then center squares, boards sending
+    // the opponent to a closed board and so letting it play anywhere last
+    fn move_priority(&self, mv: Move) -> i32 {
+        let (board, square) = Self::cell(mv);
+        let mut priority = 0;
+        if self.claims(board, square) {
+            priority += 2;
+        }
+        if square == 4 {
+            priority += 1;
+        }
+        if square != board && self.closed & (1 << square) != 0 {
+            priority -= 2;
+        }
+        priority
+    }
+
+    fn move_index(&self, mv: Move) -> Option<usize> {
+        let (board, square) = Self::cell(mv);
+        Some(usize::from(board) * usize::from(CELLS) + usize::from(square))
+    }
+
+    fn hash_key(&self) -> Option<u64> {
+        let forced = self.forced.map_or(0, |board| u64::from(board) + 1);
+        let start = mix(mix(0, u64::from(self.turn)), forced);
+        let key = self
+            .boards
+            .iter()
+            .map(|&[x, o]| u64::from(x) | u64::from(o) << CELLS)
+            .fold(start, mix);
+        Some(key)
+    }
+
+    // Only moves winning the game at once, which board the opponent plays
+    // on depending on the reply
+    fn forcing_moves(&self) -> Vec<Move> {
+        self.legal_moves()
+            .into_iter()
+            .filter(|&mv| {
+                let (board, square) = Self::cell(mv);
+                self.claims(board, square)
+                    && has_line(self.won[usize::from(self.turn)] | 1 << board)
+            })
+            .collect()
+    }
+
+    // Open lines of the meta board, a drawn local board blocking the lines
+    // through it, then open lines of the open local boards
+    fn evaluate(&self) -> Score {
+        if self.is_won() {
+            return LOSS;
+        }
+        if self.closed == FULL {
+            return DRAW;
+        }
+        let turn = usize::from(self.turn);
+        let drawn = self.closed & !(self.won[0] | self.won[1]);
+        let mut score = META_SCORE * open_lines(self.won[turn], self.won[1 - turn], drawn);
+        for board in (0..CELLS).filter(|board| self.closed & (1 << board) == 0) {
+            let stones = self.boards[usize::from(board)];
+            score += open_lines(stones[turn], stones[1 - turn], 0);
+        }
+        score
+    }
+}
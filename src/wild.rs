@@ -0,0 +1,250 @@
+use std::fmt;
+
+use crate::search::{GameState, Score, DRAW, LOSS};
+use crate::tt::mix;
+use crate::{Bitboard, BoardError, Game, GameStatus, Move, MoveError, PlayerId};
+
+// Score of a position where the side to move completes a line at once,
+// found by searches anyway but ahead of any quiet evaluation
+const HOT_SCORE: Score = 100;
+
+/// Move of wild tic-tac-toe, a square and the symbol placed on it, 0 for X
+/// and 1 for O.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WildMove {
+    pub square: Move,
+    pub symbol: u8,
+}
+
+impl WildMove {
+    pub fn new(square: Move, symbol: u8) -> Self {
+        WildMove { square, symbol }
+    }
+}
+
+// Printed as `X b2` or `O b2`
+impl fmt::Display for WildMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = if self.symbol == 0 { 'X' } else { 'O' };
+        write!(f, "{} {}", symbol, self.square)
+    }
+}
+
+/// Wild tic-tac-toe: each player places either symbol, X or O, and the
+/// player completing a line of either symbol wins.
+///
+/// The board is a [`Game`] whose two sides are the symbols rather than the
+/// players, sharing its win masks and symmetries.
+#[derive(Clone, Debug)]
+pub struct Wild {
+    board: Game,
+    turn: PlayerId,
+    history: Vec<WildMove>,
+}
+
+impl Default for Wild {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Wild {
+    /// Create an empty 3×3 board with the first player to move.
+    pub fn new() -> Self {
+        Self::with_dimensions(3, 3, 3).expect("3×3 is a valid size")
+    }
+
+    /// Create an empty board of `width` columns and `height` rows won by
+    /// aligning `win_length` symbols in a row, column or diagonal.
+    pub fn with_dimensions(width: u8, height: u8, win_length: u8) -> Result<Self, BoardError> {
+        Ok(Wild {
+            board: Game::with_dimensions(width, height, win_length)?,
+            turn: 0,
+            history: vec![],
+        })
+    }
+
+    /// Player to move, 0 for the first player and 1 for the second one.
+    pub fn turn(&self) -> PlayerId {
+        self.turn
+    }
+
+    /// Moves played so far, oldest first.
+    pub fn history(&self) -> &[WildMove] {
+        &self.history
+    }
+
+    /// Bitboards of both symbols, bit `square` set for each square holding
+    /// the symbol.
+    pub fn symbols(&self) -> [Bitboard; 2] {
+        self.board.players()
+    }
+
+    /// Board of the symbols, X being its first side and O its second one.
+    pub fn board(&self) -> &Game {
+        &self.board
+    }
+
+    /// Check if the player who moved last completed a line.
+    pub fn is_won(&self) -> bool {
+        let [x, o] = self.symbols();
+        self.board
+            .wins()
+            .iter()
+            .any(|&mask| x & mask == mask || o & mask == mask)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.is_won() || self.board.is_full()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.is_won() {
+            GameStatus::Won(1 - self.turn)
+        } else if self.board.is_full() {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress
+        }
+    }
+
+    /// Check that a move can be played in the current position.
+    pub fn check_move(&self, mv: WildMove) -> Result<(), MoveError> {
+        let square = mv.square;
+        let [x, o] = self.symbols();
+        if square.row >= self.board.height() || square.col >= self.board.width() || mv.symbol > 1 {
+            Err(MoveError::OutOfBounds)
+        } else if (x | o).contains(self.board.square(square)) {
+            Err(MoveError::Occupied)
+        } else if self.is_over() {
+            Err(MoveError::GameOver)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn make_move(&mut self, mv: WildMove) -> Result<(), MoveError> {
+        self.check_move(mv)?;
+        self.apply(mv);
+        Ok(())
+    }
+
+    // Flip the symbol of a move on its square
+    fn toggle(&mut self, mv: WildMove) {
+        let mut symbols = self.symbols();
+        symbols[usize::from(mv.symbol)] ^= Bitboard::bit(self.board.square(mv.square));
+        self.board.set_players(symbols);
+    }
+
+    // Empty squares completing a line of either symbol
+    fn hot_squares(&self, [x, o]: [Bitboard; 2]) -> Bitboard {
+        self.board
+            .wins()
+            .iter()
+            .flat_map(|&mask| [(x, o), (o, x)].map(|(ours, theirs)| (mask, ours, theirs)))
+            .filter(|&(mask, _, theirs)| (theirs & mask).is_empty())
+            .map(|(mask, ours, _)| mask & !ours)
+            .filter(|missing| missing.count_ones() == 1)
+            .fold(Bitboard::EMPTY, |squares, missing| squares | missing)
+    }
+}
+
+// One row per line, X and O for the symbols, . when empty
+impl fmt::Display for Wild {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.board)
+    }
+}
+
+impl GameState for Wild {
+    type Move = WildMove;
+
+    fn legal_moves(&self) -> Vec<WildMove> {
+        if self.is_over() {
+            return vec![];
+        }
+        let squares = self.board.moves();
+        let mut moves = Vec::with_capacity(2 * squares.len());
+        for symbol in 0..2 {
+            moves.extend(squares.iter().map(|&square| WildMove::new(square, symbol)));
+        }
+        moves
+    }
+
+    fn apply(&mut self, mv: WildMove) {
+        self.toggle(mv);
+        self.turn = 1 - self.turn;
+        self.history.push(mv);
+    }
+
+    fn undo(&mut self, mv: WildMove) {
+        self.toggle(mv);
+        self.turn = 1 - self.turn;
+        self.history.pop();
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.is_over()
+    }
+
+    // Completing a line first, then quiet moves, moves leaving a line for
+    // the opponent to complete last
+    fn move_priority(&self, mv: WildMove) -> i32 {
+        let square = self.board.square(mv.square);
+        if self.board.winning_squares(mv.symbol).contains(square) {
+            return 2;
+        }
+        let mut symbols = self.symbols();
+        symbols[usize::from(mv.symbol)] |= Bitboard::bit(square);
+        if !self.hot_squares(symbols).is_empty() {
+            -1
+        } else {
+            0
+        }
+    }
+
+    fn move_index(&self, mv: WildMove) -> Option<usize> {
+        Some(2 * self.board.square(mv.square) as usize + usize::from(mv.symbol))
+    }
+
+    // Keyed by canonical orientation of the symbols, either way round as
+    // they serve both players, the side to move following from the number
+    // of moves
+    fn hash_key(&self) -> Option<u64> {
+        let [x, o] = self.board.canonical();
+        let [x, o] = [x, o].min([o, x]);
+        let words = x.words().into_iter().chain(o.words());
+        Some(words.fold(0, mix))
+    }
+
+    fn forcing_moves(&self) -> Vec<WildMove> {
+        if self.is_over() {
+            return vec![];
+        }
+        let mut moves = vec![];
+        for symbol in 0..2 {
+            let squares = self.board.winning_squares(symbol);
+            moves.extend(
+                squares
+                    .ones()
+                    .map(|square| WildMove::new(self.board.square_move(square), symbol)),
+            );
+        }
+        moves
+    }
+
+    // Either symbol serves both players, so a line one symbol short is a
+    // win for the side to move
+    fn evaluate(&self) -> Score {
+        if self.is_won() {
+            LOSS
+        } else if self.board.is_full() {
+            DRAW
+        } else if !self.hot_squares(self.symbols()).is_empty() {
+            HOT_SCORE
+        } else {
+            0
+        }
+    }
+}